@@ -4,17 +4,16 @@ extern crate prost;
 #[macro_use]
 extern crate prost_derive;
 extern crate prost_twirp;
-#[macro_use]
-extern crate serde_derive;
 extern crate serde_json;
 extern crate tokio_core;
 
 use futures::Future;
 use futures::future;
 use futures::sync::oneshot;
-use hyper::{Client, StatusCode};
+use hyper::Client;
 use hyper::server::Http;
-use prost_twirp::TwirpError;
+use prost_twirp::IntoTwirpError;
+use std::collections::BTreeMap;
 use std::env;
 use std::thread;
 use std::time::Duration;
@@ -34,7 +33,7 @@ fn main() {
             println!("Starting server");
             let addr = "0.0.0.0:8080".parse().unwrap();
             let server = Http::new().bind(&addr,
-                move || Ok(service::Haberdasher::new_server(HaberdasherService))).unwrap();
+                move || Ok(<dyn service::Haberdasher>::new_server(HaberdasherService))).unwrap();
             server.run_until(shutdown_recv.map_err(|_| ())).unwrap();
             println!("Server stopped");
         });
@@ -49,7 +48,7 @@ fn main() {
     if run_client {
         let mut core = Core::new().unwrap();
         let hyper_client = Client::new(&core.handle());
-        let service_client = service::Haberdasher::new_client(hyper_client, "http://localhost:8080");
+        let service_client = <dyn service::Haberdasher>::new_client(hyper_client, "http://localhost:8080");
         // Try one too small, then too large, then just right
         let work = future::join_all(vec![0, 11, 5].into_iter().map(|inches|
             service_client.make_hat(service::Size { inches }.into()).then(move |res|
@@ -60,15 +59,14 @@ fn main() {
 }
 
 pub struct HaberdasherService;
-impl service::Haberdasher for HaberdasherService {
-    fn make_hat(&self, i: service::PTReq<service::Size>) -> service::PTRes<service::Hat> {
+impl service::Haberdasher<HatError> for HaberdasherService {
+    fn make_hat(&self, i: service::PTReq<service::Size>) -> service::PTRes<service::Hat, HatError> {
+        let (min, max) = (1, 10);
         Box::new(future::result(
-            if i.input.inches < 1 {
-                Err(TwirpError::new_meta(StatusCode::BadRequest, "too_small", "Size too small",
-                    serde_json::to_value(MinMaxSize { min: 1, max: 10 }).ok()).into())
-            } else if i.input.inches > 10 {
-                Err(TwirpError::new_meta(StatusCode::BadRequest, "too_large", "Size too large",
-                    serde_json::to_value(MinMaxSize { min: 1, max: 10 }).ok()).into())
+            if i.input.inches < min {
+                Err(HatError::TooSmall(i.input.inches, bounds(min, max)))
+            } else if i.input.inches > max {
+                Err(HatError::TooLarge(i.input.inches, bounds(min, max)))
             } else {
                 Ok(service::Hat { size: i.input.inches, color: "blue".to_string(), name: "fedora".to_string() }.into())
             }
@@ -76,5 +74,15 @@ impl service::Haberdasher for HaberdasherService {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct MinMaxSize { min: i32, max: i32 }
\ No newline at end of file
+#[derive(IntoTwirpError, Debug)]
+pub enum HatError {
+    #[twirp(code = "too_small", msg = "Size {0} too small", status = 400)]
+    TooSmall(i32, #[twirp(meta)] BTreeMap<String, String>),
+    #[twirp(code = "too_large", msg = "Size {0} too large", status = 400)]
+    TooLarge(i32, #[twirp(meta)] BTreeMap<String, String>),
+}
+
+/// The size bounds as error meta, which maps strings to strings
+fn bounds(min: i32, max: i32) -> BTreeMap<String, String> {
+    vec![("min".to_string(), min.to_string()), ("max".to_string(), max.to_string())].into_iter().collect()
+}
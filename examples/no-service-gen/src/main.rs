@@ -9,9 +9,9 @@ extern crate tokio_core;
 use futures::Future;
 use futures::future;
 use futures::sync::oneshot;
-use hyper::{Client, Method, StatusCode};
+use hyper::{Client, Method};
 use hyper::server::Http;
-use prost_twirp::{FutResp, HyperClient, HyperServer, HyperService, ServiceRequest, ServiceResponse, TwirpError};
+use prost_twirp::{PTRes, HyperClient, HyperServer, HyperService, ServiceRequest, ServiceResponse, TwirpError};
 use std::env;
 use std::thread;
 use std::time::Duration;
@@ -63,7 +63,7 @@ fn main() {
 
 struct MyServer;
 impl HyperService for MyServer {
-    fn handle(&self, req: ServiceRequest<Vec<u8>>) -> FutResp<Vec<u8>> {
+    fn handle(&self, req: ServiceRequest<Vec<u8>>) -> PTRes<Vec<u8>> {
         match (req.method.clone(), req.uri.path()) {
             (Method::Post, "/twirp/twitch.twirp.example.Haberdasher/MakeHat") =>
                 Box::new(future::result(req.to_proto().and_then(|req| {
@@ -72,7 +72,9 @@ impl HyperService for MyServer {
                         service::Hat { size: size.inches, color: "blue".to_string(), name: "fedora".to_string() }
                     ).to_proto_raw()
                 }))),
-            _ => Box::new(future::ok(TwirpError::new("not_found", "Not found").to_resp_raw(StatusCode::NotFound)))
+            _ => Box::new(future::ok(
+                TwirpError::bad_route(&format!("no handler for path {:?}", req.uri.path()), &req.method, req.uri.path()).
+                    to_resp_raw()))
         }
     }
 }
@@ -0,0 +1,6 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate prost_twirp_testing;
+
+fuzz_target!(|data: &[u8]| { prost_twirp_testing::fuzz::error_json(data); });
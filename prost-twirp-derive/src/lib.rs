@@ -0,0 +1,228 @@
+//! Derive macros for [Prost Twirp](https://github.com/cretz/prost-twirp).
+//!
+//! Usually used through the `derive` feature of the `prost-twirp` crate instead of directly.
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use syn::{Data, DeriveInput, Fields, GenericArgument, Ident, LitInt, LitStr, Path, PathArguments, Type};
+
+/// Derive `IntoTwirpError` for an enum
+///
+/// Every variant needs a `#[twirp(code = "...", msg = "...")]` attribute. The message is a `format!` template that
+/// can reference named fields by name and tuple fields by index (e.g. `"Size {0} too small"`). The HTTP status is
+/// derived from the code unless `status = 400` is also given, which custom codes outside the Twirp spec need. A single
+/// field of a variant may be marked `#[twirp(meta)]` to be the error meta. Twirp meta maps strings to strings, so the
+/// field has to be a `HashMap` or `BTreeMap` of `String` or `&str` keys and values. If the runtime is not at
+/// `::prost_twirp` (e.g. when embedded or re-exported), the path can be set on the enum with
+/// `#[twirp(crate = "path::to::prost_twirp")]`. Generated code only refers to the runtime path, not to hyper or
+/// serde_json directly.
+#[proc_macro_derive(IntoTwirpError, attributes(twirp))]
+pub fn derive_into_twirp_error(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    match into_twirp_error_impl(&input) {
+        Ok(v) => v.into(),
+        Err(err) => compile_errors(err).into(),
+    }
+}
+
+/// The errors as `compile_error!` calls
+///
+/// Unlike `syn::Error::to_compile_error`, these don't go through `::core`, which 2015 edition crates can't see.
+fn compile_errors(err: syn::Error) -> TokenStream2 {
+    err.into_iter().map(|err| {
+        let msg = err.to_string();
+        quote_spanned!(err.span()=> compile_error!(#msg);)
+    }).collect()
+}
+
+/// The error codes in the Twirp spec
+const TWIRP_CODES: &[&str] = &["canceled", "unknown", "invalid_argument", "malformed", "deadline_exceeded", "not_found",
+    "bad_route", "already_exists", "permission_denied", "unauthenticated", "resource_exhausted", "failed_precondition",
+    "aborted", "out_of_range", "unimplemented", "internal", "unavailable", "dataloss"];
+
+struct VariantAttrs {
+    code: LitStr,
+    msg: LitStr,
+    status: Option<LitInt>,
+}
+
+fn into_twirp_error_impl(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let data = match input.data {
+        Data::Enum(ref data) => data,
+        _ => return Err(syn::Error::new_spanned(input, "IntoTwirpError can only be derived for enums")),
+    };
+    let mut runtime: Path = syn::parse_quote!(::prost_twirp);
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("twirp")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("crate") {
+                runtime = meta.value()?.parse::<LitStr>()?.parse()?;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported twirp enum attribute"))
+            }
+        })?;
+    }
+
+    let mut arms = Vec::new();
+    for variant in data.variants.iter() {
+        let attrs = variant_attrs(variant)?;
+        let ident = &variant.ident;
+        let mut meta_field = None;
+        let bindings: Vec<Ident> = match variant.fields {
+            Fields::Named(_) => variant.fields.iter().map(|f| f.ident.clone().unwrap()).collect(),
+            _ => (0..variant.fields.len()).map(|i| format_ident!("_{}", i)).collect(),
+        };
+        for (field, binding) in variant.fields.iter().zip(bindings.iter()) {
+            if is_meta_field(field)? {
+                if meta_field.is_some() {
+                    return Err(syn::Error::new_spanned(field, "only one field can be twirp meta"));
+                }
+                if !is_string_map(&field.ty) {
+                    return Err(syn::Error::new_spanned(&field.ty,
+                        "twirp meta must map strings to strings, like HashMap<String, String>"));
+                }
+                meta_field = Some(binding.clone());
+            }
+        }
+        let pattern = match variant.fields {
+            Fields::Named(_) => quote!(#ident { #(#bindings),* }),
+            Fields::Unnamed(_) => quote!(#ident( #(#bindings),* )),
+            Fields::Unit => quote!(#ident),
+        };
+        let code = &attrs.code;
+        let msg = LitStr::new(&positional_to_named(&attrs.msg.value()), attrs.msg.span());
+        let status = match attrs.status {
+            Some(ref status) => quote!(#runtime::__derive::StatusCode::try_from(#status).unwrap()),
+            None => quote!(#runtime::TwirpError::status_for_code(#code)),
+        };
+        let meta = match meta_field {
+            Some(ref field) => quote!(#runtime::__derive::to_value(#field).ok()),
+            None => quote!(None),
+        };
+        arms.push(quote! {
+            #[allow(unused_variables)]
+            Self::#pattern => #runtime::TwirpError::new_meta(#status, #code, &format!(#msg), #meta),
+        });
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #runtime::IntoTwirpError for #name #ty_generics #where_clause {
+            fn into_twirp_error(self) -> #runtime::TwirpError {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+
+        impl #impl_generics From<#name #ty_generics> for #runtime::ProstTwirpError #where_clause {
+            fn from(v: #name #ty_generics) -> #runtime::ProstTwirpError {
+                #runtime::IntoTwirpError::into_prost_twirp_error(v)
+            }
+        }
+    })
+}
+
+fn variant_attrs(variant: &syn::Variant) -> syn::Result<VariantAttrs> {
+    let (mut code, mut msg, mut status) = (None, None, None);
+    for attr in variant.attrs.iter().filter(|a| a.path().is_ident("twirp")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("code") {
+                code = Some(meta.value()?.parse::<LitStr>()?);
+            } else if meta.path.is_ident("msg") {
+                msg = Some(meta.value()?.parse::<LitStr>()?);
+            } else if meta.path.is_ident("status") {
+                let lit = meta.value()?.parse::<LitInt>()?;
+                match lit.base10_parse::<u16>() {
+                    Ok(100..=599) => status = Some(lit),
+                    _ => return Err(syn::Error::new(lit.span(), "status must be between 100 and 599")),
+                }
+            } else {
+                return Err(meta.error("unsupported twirp variant attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    match (code, msg) {
+        (Some(code), _) if code.value().is_empty() => Err(syn::Error::new(code.span(), "code can't be empty")),
+        (Some(code), Some(_)) if status.is_none() && !TWIRP_CODES.contains(&code.value().as_str()) =>
+            Err(syn::Error::new(code.span(), "unknown Twirp code, custom codes need a status like `status = 400`")),
+        (Some(code), Some(msg)) => Ok(VariantAttrs { code, msg, status }),
+        _ => Err(syn::Error::new_spanned(variant, "variant needs #[twirp(code = \"...\", msg = \"...\")]")),
+    }
+}
+
+fn is_meta_field(field: &syn::Field) -> syn::Result<bool> {
+    let mut is_meta = false;
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("twirp")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("meta") {
+                is_meta = true;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported twirp field attribute"))
+            }
+        })?;
+    }
+    Ok(is_meta)
+}
+
+/// Whether the type is a `HashMap` or `BTreeMap` of strings to strings, like Twirp meta
+///
+/// Only the type as written can be checked, so aliases of those maps aren't accepted.
+fn is_string_map(ty: &Type) -> bool {
+    let segment = match *ty {
+        Type::Path(ref ty) if ty.qself.is_none() => ty.path.segments.last(),
+        _ => None,
+    };
+    let args = match segment {
+        Some(segment) if segment.ident == "HashMap" || segment.ident == "BTreeMap" => match segment.arguments {
+            PathArguments::AngleBracketed(ref args) => &args.args,
+            _ => return false,
+        },
+        _ => return false,
+    };
+    // A `HashMap` can also have a hasher after the key and value
+    let types = args.iter().filter_map(|arg| match *arg {
+        GenericArgument::Type(ref ty) => Some(ty),
+        _ => None,
+    }).take(2).collect::<Vec<_>>();
+    types.len() == 2 && types.into_iter().all(is_string)
+}
+
+/// Whether the type is `String` or `&str`
+fn is_string(ty: &Type) -> bool {
+    match *ty {
+        Type::Path(ref ty) => ty.qself.is_none() &&
+            ty.path.segments.last().is_some_and(|v| v.ident == "String" && v.arguments.is_none()),
+        Type::Reference(ref ty) => match *ty.elem {
+            Type::Path(ref elem) => elem.qself.is_none() && elem.path.is_ident("str"),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Rewrite `{0}`-style positional references to the `_0`-style bindings used for tuple fields
+fn positional_to_named(template: &str) -> String {
+    let mut ret = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        ret.push(c);
+        if c == '{' {
+            match chars.peek() {
+                Some(&'{') => ret.push(chars.next().unwrap()),
+                Some(n) if n.is_ascii_digit() => ret.push('_'),
+                _ => (),
+            }
+        }
+    }
+    ret
+}
@@ -0,0 +1,183 @@
+#[cfg(feature = "log")]
+use log;
+use serde_json::Value;
+use service_run::{AccessLog, AccessRecord};
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+/// How an `AccessLogger` writes each record as a line
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AccessLogFormat {
+    /// A JSON object per line, with `null` for missing values
+    #[default]
+    JsonLines,
+    /// `key=value` pairs per line, leaving out missing values
+    Logfmt,
+}
+
+impl AccessLogFormat {
+    /// The record as a line without the trailing newline
+    ///
+    /// The fields are `ts` (RFC 3339 in UTC), `remote_addr`, `service`, `method`, `status`, `code`, `request_size`,
+    /// `response_size`, `duration_ms` and `request_id`.
+    pub fn format(&self, record: &AccessRecord) -> String {
+        let ts = rfc3339(record);
+        let duration_ms = record.duration.as_secs() as f64 * 1000.0 + f64::from(record.duration.subsec_nanos()) / 1e6;
+        match *self {
+            AccessLogFormat::JsonLines => {
+                // Built by hand to keep the fields in order
+                let string = |v: &str| Value::String(v.to_string());
+                let opt = |v: Option<&str>| v.map_or(Value::Null, string);
+                let remote_addr = record.remote_addr.map(|v| v.to_string());
+                let fields = [
+                    ("ts", string(&ts)),
+                    ("remote_addr", opt(remote_addr.as_deref())),
+                    ("service", string(&record.service)),
+                    ("method", string(&record.method)),
+                    ("status", Value::from(u16::from(record.status))),
+                    ("code", opt(record.code.as_deref())),
+                    ("request_size", Value::from(record.request_size)),
+                    ("response_size", Value::from(record.response_size)),
+                    ("duration_ms", Value::from((duration_ms * 1000.0).round() / 1000.0)),
+                    ("request_id", opt(record.request_id.as_deref())),
+                ];
+                let fields: Vec<_> = fields.iter().map(|&(key, ref value)| format!("\"{}\":{}", key, value)).collect();
+                format!("{{{}}}", fields.join(","))
+            },
+            AccessLogFormat::Logfmt => {
+                let mut line = format!("ts={}", ts);
+                let mut pair = |key: &str, value: &str| {
+                    let _ = write!(line, " {}={}", key, logfmt_value(value));
+                };
+                if let Some(addr) = record.remote_addr { pair("remote_addr", &addr.to_string()) }
+                pair("service", &record.service);
+                pair("method", &record.method);
+                pair("status", &u16::from(record.status).to_string());
+                if let Some(ref code) = record.code { pair("code", code) }
+                pair("request_size", &record.request_size.to_string());
+                pair("response_size", &record.response_size.to_string());
+                pair("duration_ms", &format!("{:.3}", duration_ms));
+                if let Some(ref id) = record.request_id { pair("request_id", id) }
+                line
+            },
+        }
+    }
+}
+
+/// The value quoted and escaped if logfmt needs it to be
+fn logfmt_value(value: &str) -> String {
+    if !value.is_empty() && !value.chars().any(|v| v <= ' ' || v == '=' || v == '"' || v == '\\') {
+        return value.to_string();
+    }
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The record's timestamp like `2018-01-02T03:04:05.678Z`
+fn rfc3339(record: &AccessRecord) -> String {
+    let since = record.timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let (days, secs) = (since.as_secs() / 86_400, since.as_secs() % 86_400);
+    // Civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days as i64 + 719_468;
+    let (era, doe) = (z / 146_097, z % 146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z", year, month, day, secs / 3600, secs / 60 % 60, secs % 60,
+        since.subsec_millis())
+}
+
+/// Where an `AccessLogger` sends its lines
+///
+/// Closures taking each line are sinks too.
+pub trait AccessLogSink {
+    /// Write the line, which has no trailing newline
+    fn write_line(&self, line: &str);
+}
+
+impl<F: Fn(&str)> AccessLogSink for F {
+    fn write_line(&self, line: &str) { self(line) }
+}
+
+/// A sink writing each line to a writer like `io::stdout()` or a `File`, flushing after each
+///
+/// Write errors are ignored so logging never fails a call.
+#[derive(Debug)]
+pub struct WriterSink<W: Write> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write> WriterSink<W> {
+    /// A sink for the writer
+    pub fn new(writer: W) -> WriterSink<W> { WriterSink { writer: Mutex::new(writer) } }
+
+    /// The writer back
+    pub fn into_inner(self) -> W { self.writer.into_inner().unwrap_or_else(|v| v.into_inner()) }
+}
+
+impl<W: Write> AccessLogSink for WriterSink<W> {
+    fn write_line(&self, line: &str) {
+        let mut writer = self.writer.lock().unwrap_or_else(|v| v.into_inner());
+        let _ = writeln!(writer, "{}", line).and_then(|_| writer.flush());
+    }
+}
+
+/// A sink logging each line with the `log` crate
+#[cfg(feature = "log")]
+#[derive(Clone, Debug)]
+pub struct LogSink {
+    /// The target lines are logged for. Default is `prost_twirp::access`.
+    pub target: String,
+    /// The level lines are logged at. Default is `Info`.
+    pub level: log::Level,
+}
+
+#[cfg(feature = "log")]
+impl Default for LogSink {
+    fn default() -> LogSink { LogSink { target: "prost_twirp::access".to_string(), level: log::Level::Info } }
+}
+
+#[cfg(feature = "log")]
+impl AccessLogSink for LogSink {
+    fn write_line(&self, line: &str) { log!(target: &self.target, self.level, "{}", line) }
+}
+
+/// An access log writing one line per call in a format to a sink
+///
+/// Set it as the `access_log` of a `HyperServer`.
+pub struct AccessLogger {
+    /// How records are written
+    pub format: AccessLogFormat,
+    /// Where lines go
+    pub sink: Box<dyn AccessLogSink + Send + Sync>,
+}
+
+impl AccessLogger {
+    /// A logger writing to the sink in the format
+    pub fn new<S: AccessLogSink + Send + Sync + 'static>(format: AccessLogFormat, sink: S) -> AccessLogger {
+        AccessLogger { format, sink: Box::new(sink) }
+    }
+
+    /// A logger writing JSON lines to standard out
+    pub fn stdout() -> AccessLogger { AccessLogger::new(AccessLogFormat::JsonLines, WriterSink::new(io::stdout())) }
+}
+
+impl AccessLog for AccessLogger {
+    fn log(&self, record: &AccessRecord) { self.sink.write_line(&self.format.format(record)) }
+}
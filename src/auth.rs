@@ -0,0 +1,279 @@
+use futures::{future, Future};
+use hyper::StatusCode;
+use service_run::{HyperService, IntoTwirpError, ProstTwirpError, PTRes, Principal, ServiceRequest, TwirpError};
+use std::str;
+
+#[cfg(feature = "auth")]
+use hmac::{Hmac, Mac};
+#[cfg(feature = "auth")]
+use jsonwebtoken::{self, Algorithm, DecodingKey, Validation};
+#[cfg(feature = "auth")]
+use jsonwebtoken::errors::ErrorKind;
+#[cfg(feature = "auth")]
+use serde_json;
+#[cfg(feature = "auth")]
+use sha2::Sha256;
+#[cfg(feature = "auth")]
+use std::collections::HashMap;
+#[cfg(feature = "auth")]
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Something that verifies the credentials of requests
+pub trait Verifier: Send + Sync {
+    /// The principal for the request's credentials, an error if they aren't valid, or `None` if the request has no
+    /// credentials this verifier understands
+    fn verify(&self, req: &ServiceRequest<Vec<u8>>) -> Option<Result<Principal, TwirpError>>;
+}
+
+/// The credentials from the request's `Authorization` header if it uses the given scheme, ignoring case
+pub fn authorization<'a, T>(req: &'a ServiceRequest<T>, scheme: &str) -> Option<&'a str> {
+    let value = req.headers.get_raw("Authorization").and_then(|v| v.one()).and_then(|v| str::from_utf8(v).ok())?;
+    let (value_scheme, credentials) = value.trim().split_once(' ')?;
+    if value_scheme.eq_ignore_ascii_case(scheme) { Some(credentials.trim()) } else { None }
+}
+
+/// An `unauthenticated` error with the given message
+pub fn unauthenticated(msg: &str) -> TwirpError { TwirpError::from_code("unauthenticated", msg) }
+
+/// Compare without exiting early so the time taken doesn't reveal how much matched
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Verifies bearer tokens against a fixed set
+#[derive(Clone, Debug, Default)]
+pub struct StaticTokens {
+    /// The tokens and the principal for each
+    pub tokens: Vec<(String, Principal)>,
+}
+
+impl StaticTokens {
+    /// Create a verifier with no tokens
+    pub fn new() -> StaticTokens { Default::default() }
+
+    /// This verifier with another token for the principal
+    pub fn with_token(mut self, token: &str, principal: Principal) -> StaticTokens {
+        self.tokens.push((token.to_string(), principal));
+        self
+    }
+}
+
+impl Verifier for StaticTokens {
+    fn verify(&self, req: &ServiceRequest<Vec<u8>>) -> Option<Result<Principal, TwirpError>> {
+        let token = authorization(req, "Bearer")?;
+        let principal = self.tokens.iter().
+            fold(None, |found, (v, principal)| if constant_time_eq(v.as_bytes(), token.as_bytes()) {
+                Some(principal)
+            } else { found });
+        Some(principal.cloned().ok_or_else(|| unauthenticated("invalid token")))
+    }
+}
+
+/// Verifies bearer tokens that are JWTs signed with a local key
+///
+/// The principal's subject is the `sub` claim and its scopes are from the space-separated `scope` claim or the `scp`
+/// array, with all claims kept. Tokens for the wrong audience or issuer get a `permission_denied` error.
+#[cfg(feature = "auth")]
+pub struct JwtVerifier {
+    /// The key the tokens are signed with
+    pub key: DecodingKey,
+    /// What is checked besides the signature, by default that the token hasn't expired
+    pub validation: Validation,
+}
+
+#[cfg(feature = "auth")]
+impl JwtVerifier {
+    /// Verify HS256 tokens signed with the shared secret
+    pub fn hs256(secret: &[u8]) -> JwtVerifier {
+        JwtVerifier { key: DecodingKey::from_secret(secret), validation: Validation::new(Algorithm::HS256) }
+    }
+
+    /// Verify RS256 tokens signed with the private key for the PEM encoded public key
+    pub fn rs256_pem(pem: &[u8]) -> Result<JwtVerifier, jsonwebtoken::errors::Error> {
+        Ok(JwtVerifier { key: DecodingKey::from_rsa_pem(pem)?, validation: Validation::new(Algorithm::RS256) })
+    }
+}
+
+#[cfg(feature = "auth")]
+impl Verifier for JwtVerifier {
+    fn verify(&self, req: &ServiceRequest<Vec<u8>>) -> Option<Result<Principal, TwirpError>> {
+        let token = authorization(req, "Bearer")?;
+        let claims = match jsonwebtoken::decode::<serde_json::Map<String, serde_json::Value>>(token, &self.key,
+                &self.validation) {
+            Ok(data) => data.claims,
+            Err(err) => return Some(Err(match *err.kind() {
+                ErrorKind::InvalidAudience | ErrorKind::InvalidIssuer =>
+                    TwirpError::from_code("permission_denied", "token not valid for this service"),
+                ErrorKind::ExpiredSignature => unauthenticated("token expired"),
+                _ => unauthenticated("invalid token"),
+            })),
+        };
+        let subject = claims.get("sub").and_then(|v| v.as_str()).unwrap_or("").to_string();
+        let scopes = match (claims.get("scope"), claims.get("scp")) {
+            (Some(serde_json::Value::String(scope)), _) => scope.split_whitespace().map(str::to_string).collect(),
+            (_, Some(serde_json::Value::Array(scopes))) =>
+                scopes.iter().filter_map(|v| v.as_str()).map(str::to_string).collect(),
+            _ => Vec::new(),
+        };
+        Some(Ok(Principal { subject, scopes, claims }))
+    }
+}
+
+/// The `Authorization` scheme for HMAC signed requests
+#[cfg(feature = "auth")]
+pub const HMAC_SCHEME: &str = "HMAC-SHA256";
+
+#[cfg(feature = "auth")]
+fn hmac_signature(secret: &[u8], timestamp: u64, path: &str, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC takes keys of any size");
+    mac.update(format!("{}\n{}\n", timestamp, path).as_bytes());
+    mac.update(body);
+    mac
+}
+
+/// The `Authorization` header value for a request signed by `HmacVerifier`
+///
+/// The value is `HMAC-SHA256 <key id>:<timestamp>:<signature>` where the timestamp is in seconds since the Unix epoch
+/// and the signature is the hex HMAC-SHA256 of the timestamp, path, and body, with a newline after the first two.
+#[cfg(feature = "auth")]
+pub fn hmac_authorization(key_id: &str, secret: &[u8], timestamp: u64, path: &str, body: &[u8]) -> String {
+    let signature = hmac_signature(secret, timestamp, path, body).finalize().into_bytes();
+    let hex = signature.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+    format!("{} {}:{}:{}", HMAC_SCHEME, key_id, timestamp, hex)
+}
+
+/// Verifies requests signed with shared secrets as described by `hmac_authorization`
+///
+/// The principal's subject is the key ID.
+#[cfg(feature = "auth")]
+#[derive(Clone, Debug)]
+pub struct HmacVerifier {
+    /// The secret for each key ID
+    pub keys: HashMap<String, Vec<u8>>,
+    /// How far the timestamp may be from now, limiting how long a signed request can be replayed. Default is 5
+    /// minutes.
+    pub max_skew: Duration,
+}
+
+#[cfg(feature = "auth")]
+impl HmacVerifier {
+    /// Create a verifier with no keys
+    pub fn new() -> HmacVerifier { HmacVerifier { keys: HashMap::new(), max_skew: Duration::from_secs(300) } }
+
+    /// This verifier with another key
+    pub fn with_key(mut self, key_id: &str, secret: &[u8]) -> HmacVerifier {
+        self.keys.insert(key_id.to_string(), secret.to_vec());
+        self
+    }
+}
+
+#[cfg(feature = "auth")]
+impl Default for HmacVerifier {
+    fn default() -> HmacVerifier { HmacVerifier::new() }
+}
+
+#[cfg(feature = "auth")]
+impl Verifier for HmacVerifier {
+    fn verify(&self, req: &ServiceRequest<Vec<u8>>) -> Option<Result<Principal, TwirpError>> {
+        let mut parts = authorization(req, HMAC_SCHEME)?.rsplitn(3, ':');
+        let (signature, timestamp, key_id) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(signature), Some(timestamp), Some(key_id)) => (signature, timestamp, key_id),
+            _ => return Some(Err(unauthenticated("malformed signature"))),
+        };
+        let (timestamp, signature) = match (timestamp.parse::<u64>(), decode_hex(signature)) {
+            (Ok(timestamp), Some(signature)) => (timestamp, signature),
+            _ => return Some(Err(unauthenticated("malformed signature"))),
+        };
+        let secret = match self.keys.get(key_id) {
+            Some(secret) => secret,
+            None => return Some(Err(unauthenticated("unknown key"))),
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        if now.abs_diff(timestamp) > self.max_skew.as_secs() {
+            return Some(Err(unauthenticated("signature expired")));
+        }
+        if hmac_signature(secret, timestamp, req.uri.path(), &req.input).verify_slice(&signature).is_err() {
+            return Some(Err(unauthenticated("invalid signature")));
+        }
+        Some(Ok(Principal { subject: key_id.to_string(), ..Default::default() }))
+    }
+}
+
+#[cfg(feature = "auth")]
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() { return None; }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok()).collect()
+}
+
+/// A `HyperService` wrapper that only lets through calls whose credentials are verified
+///
+/// Each verifier is tried in order and the first principal is put in the request for the wrapped service. If no
+/// verifier accepts the credentials, the first verifier's error is returned, or an `unauthenticated` error if there
+/// were no credentials. `unauthenticated` errors get a `WWW-Authenticate` header with the `challenge`, including the
+/// ones the wrapped service answers with, like when a generated server checks a method's policy.
+pub struct Authenticated<S: HyperService> {
+    /// The wrapped service
+    pub service: S,
+    /// The verifiers to try
+    pub verifiers: Vec<Box<dyn Verifier>>,
+    /// Whether calls without any credentials are let through without a principal. Default is false.
+    pub allow_anonymous: bool,
+    /// The `WWW-Authenticate` challenge for `unauthenticated` errors. Default is `Bearer`.
+    pub challenge: String,
+}
+
+impl<S: HyperService> Authenticated<S> {
+    /// Require calls to the service to be verified by one of the verifiers
+    pub fn new(service: S, verifiers: Vec<Box<dyn Verifier>>) -> Authenticated<S> {
+        Authenticated { service, verifiers, allow_anonymous: false, challenge: "Bearer".to_string() }
+    }
+
+    fn error_resp(&self, mut err: TwirpError) -> PTRes<Vec<u8>> {
+        add_challenge(&self.challenge, &mut err);
+        Box::new(future::ok(err.to_resp_raw()))
+    }
+
+    /// Handle the request with the wrapped service, challenging its `unauthenticated` responses and errors too
+    fn service_resp(&self, req: ServiceRequest<Vec<u8>>) -> PTRes<Vec<u8>> {
+        let (challenge, err_challenge) = (self.challenge.clone(), self.challenge.clone());
+        Box::new(self.service.handle(req).map(move |mut resp| {
+            if resp.status == StatusCode::Unauthorized && resp.headers.get_raw("WWW-Authenticate").is_none() {
+                resp.headers.set_raw("WWW-Authenticate", challenge);
+            }
+            resp
+        }).or_else(move |err| {
+            if err.twirp_code() != Some("unauthenticated") { return Err(err); }
+            let mut err = err.into_twirp_error();
+            add_challenge(&err_challenge, &mut err);
+            Err(ProstTwirpError::TwirpError(err))
+        }))
+    }
+}
+
+/// Set the `WWW-Authenticate` header of `unauthenticated` errors that don't have one yet
+fn add_challenge(challenge: &str, err: &mut TwirpError) {
+    if err.error_type == "unauthenticated" && err.headers.get_raw("WWW-Authenticate").is_none() {
+        err.headers.set_raw("WWW-Authenticate", challenge.to_string());
+    }
+}
+
+impl<S: HyperService> HyperService for Authenticated<S> {
+    fn handle(&self, mut req: ServiceRequest<Vec<u8>>) -> PTRes<Vec<u8>> {
+        let mut first_err = None;
+        for verifier in &self.verifiers {
+            match verifier.verify(&req) {
+                Some(Ok(principal)) => {
+                    req.principal = Some(principal);
+                    return self.service_resp(req);
+                },
+                Some(Err(err)) if first_err.is_none() => first_err = Some(err),
+                Some(Err(_)) | None => (),
+            }
+        }
+        match first_err {
+            Some(err) => self.error_resp(err),
+            None if self.allow_anonymous => self.service_resp(req),
+            None => self.error_resp(unauthenticated("missing credentials")),
+        }
+    }
+}
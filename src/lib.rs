@@ -4,17 +4,61 @@
 //! 
 //! See [the github project](https://github.com/cretz/prost-twirp) for more info.
 
+extern crate bytes;
 extern crate futures;
 extern crate hyper;
 extern crate prost;
 extern crate serde_json;
+extern crate tokio_core;
+#[cfg(feature = "log")]
+#[macro_use]
+extern crate log;
+#[cfg(feature = "tracing")]
+extern crate tracing;
 
 mod service_run;
 pub use service_run::*;
 
+mod rate_limit;
+pub use rate_limit::*;
+
+mod metrics;
+pub use metrics::*;
+
+mod access_log;
+pub use access_log::*;
+
+#[cfg(feature = "auth")]
+extern crate hmac;
+#[cfg(feature = "auth")]
+extern crate jsonwebtoken;
+#[cfg(feature = "auth")]
+extern crate sha2;
+mod auth;
+pub use auth::*;
+
+#[cfg(feature = "derive")]
+extern crate prost_twirp_derive;
+#[cfg(feature = "derive")]
+pub use prost_twirp_derive::*;
+
+#[cfg(feature = "service-gen")]
+extern crate prettyplease;
+#[cfg(feature = "service-gen")]
+extern crate proc_macro2;
 #[cfg(feature = "service-gen")]
 extern crate prost_build;
 #[cfg(feature = "service-gen")]
+#[macro_use]
+extern crate prost_derive;
+#[cfg(feature = "service-gen")]
+#[macro_use]
+extern crate quote;
+#[cfg(feature = "service-gen")]
+extern crate syn;
+#[cfg(feature = "service-gen")]
+mod options;
+#[cfg(feature = "service-gen")]
 mod service_gen;
 #[cfg(feature = "service-gen")]
 pub use service_gen::TwirpServiceGenerator;
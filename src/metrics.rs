@@ -0,0 +1,181 @@
+use futures::{future, Future};
+use hyper;
+use hyper::{Method, Request, Response};
+use hyper::header::ContentType;
+use hyper::server::Service;
+use service_run::{split_path, CallObserver, MethodDescriptor};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// The default upper bounds in seconds of the latency histogram buckets
+pub const DEFAULT_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// The media type of the Prometheus text exposition format
+pub const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// A call observer recording Prometheus-style metrics by service and method
+///
+/// Set it as the `observer` of a `HyperServer` or `HyperClient`, and serve it with `MetricsEndpoint`. The metrics,
+/// each starting with the prefix, are:
+///
+/// * `<prefix>_requests_total`, a counter of calls started
+/// * `<prefix>_errors_total`, a counter of calls failed, also labeled with the Twirp error `code`
+/// * `<prefix>_in_flight`, a gauge of calls started and not finished
+/// * `<prefix>_request_duration_seconds`, a histogram of how long calls took
+#[derive(Debug)]
+pub struct Metrics {
+    /// What metric names start with, like `twirp_server`
+    pub prefix: String,
+    /// The upper bounds in seconds of the latency histogram buckets, in order. Default is `DEFAULT_BUCKETS`.
+    pub buckets: Vec<f64>,
+    /// The methods calls may be labeled with, or empty to label calls by any path. Default is empty.
+    ///
+    /// Calls for other paths are labeled `unknown` so bad routes can't add series without bound. Servers open to the
+    /// public should set this, like to `Haberdasher::METHODS`.
+    pub methods: Vec<MethodDescriptor>,
+    series: Mutex<BTreeMap<(String, String), Series>>,
+}
+
+#[derive(Debug, Default)]
+struct Series {
+    requests: u64,
+    in_flight: u64,
+    errors: BTreeMap<String, u64>,
+    /// Calls in each bucket and no smaller one, with the last for calls over every bound
+    buckets: Vec<u64>,
+    seconds: f64,
+}
+
+impl Metrics {
+    /// Metrics with names starting with the prefix
+    pub fn new(prefix: &str) -> Metrics {
+        Metrics { prefix: prefix.to_string(), buckets: DEFAULT_BUCKETS.to_vec(), methods: Vec::new(),
+            series: Default::default() }
+    }
+
+    /// Metrics for a server, starting with `twirp_server`
+    pub fn server() -> Metrics { Metrics::new("twirp_server") }
+
+    /// Metrics for a client, starting with `twirp_client`
+    pub fn client() -> Metrics { Metrics::new("twirp_client") }
+
+    /// The service and method labels for the path
+    fn labels(&self, path: &str) -> (String, String) {
+        let unknown = || ("unknown".to_string(), "unknown".to_string());
+        if !self.methods.is_empty() {
+            return self.methods.iter().find(|v| v.path == path).
+                map_or_else(unknown, |v| (v.service.to_string(), v.method.to_string()));
+        }
+        split_path(path).map_or_else(unknown, |(service, method)| (service.to_string(), method.to_string()))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<(String, String), Series>> {
+        self.series.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// The metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let series = self.lock();
+        let mut out = String::new();
+        let prefix = &self.prefix;
+        let labels = |(service, method): &(String, String)|
+            format!("service=\"{}\",method=\"{}\"", escape(service), escape(method));
+        let _ = writeln!(out, "# HELP {}_requests_total Calls started.\n# TYPE {}_requests_total counter",
+            prefix, prefix);
+        for (key, v) in series.iter() {
+            let _ = writeln!(out, "{}_requests_total{{{}}} {}", prefix, labels(key), v.requests);
+        }
+        let _ = writeln!(out, "# HELP {}_errors_total Calls failed, by Twirp error code.\n\
+            # TYPE {}_errors_total counter", prefix, prefix);
+        for (key, v) in series.iter() {
+            for (code, count) in &v.errors {
+                let _ = writeln!(out, "{}_errors_total{{{},code=\"{}\"}} {}", prefix, labels(key), escape(code), count);
+            }
+        }
+        let _ = writeln!(out, "# HELP {}_in_flight Calls started and not finished.\n# TYPE {}_in_flight gauge",
+            prefix, prefix);
+        for (key, v) in series.iter() {
+            let _ = writeln!(out, "{}_in_flight{{{}}} {}", prefix, labels(key), v.in_flight);
+        }
+        let name = format!("{}_request_duration_seconds", prefix);
+        let _ = writeln!(out, "# HELP {} How long calls took.\n# TYPE {} histogram", name, name);
+        for (key, v) in series.iter() {
+            let labels = labels(key);
+            let mut count = 0;
+            for (i, bound) in self.buckets.iter().enumerate() {
+                count += v.buckets.get(i).cloned().unwrap_or(0);
+                let _ = writeln!(out, "{}_bucket{{{},le=\"{}\"}} {}", name, labels, bound, count);
+            }
+            count += v.buckets.get(self.buckets.len()).cloned().unwrap_or(0);
+            let _ = writeln!(out, "{}_bucket{{{},le=\"+Inf\"}} {}", name, labels, count);
+            let _ = writeln!(out, "{}_sum{{{}}} {}\n{}_count{{{}}} {}", name, labels, v.seconds, name, labels, count);
+        }
+        out
+    }
+}
+
+impl CallObserver for Metrics {
+    fn started(&self, path: &str) {
+        let key = self.labels(path);
+        let mut series = self.lock();
+        let series = series.entry(key).or_default();
+        series.requests += 1;
+        series.in_flight += 1;
+    }
+
+    fn finished(&self, path: &str, elapsed: Duration, code: Option<&str>) {
+        let key = self.labels(path);
+        let seconds = elapsed.as_secs_f64();
+        let bucket = self.buckets.iter().position(|&v| seconds <= v).unwrap_or(self.buckets.len());
+        let mut series = self.lock();
+        let series = series.entry(key).or_default();
+        series.in_flight = series.in_flight.saturating_sub(1);
+        if let Some(code) = code { *series.errors.entry(code.to_string()).or_insert(0) += 1; }
+        if series.buckets.len() <= bucket { series.buckets.resize(bucket + 1, 0); }
+        series.buckets[bucket] += 1;
+        series.seconds += seconds;
+    }
+}
+
+/// Escape a label value for the text exposition format
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// A hyper service answering `GET` requests for a path with metrics, passing other requests to the wrapped service
+///
+/// Hyper creates a service per connection, so clone the wrapped server and the metrics for each one.
+#[derive(Clone, Debug)]
+pub struct MetricsEndpoint<S> {
+    /// The wrapped service, like a `HyperServer`
+    pub service: S,
+    /// The path the metrics are served at, like `/metrics`
+    pub path: String,
+    /// The metrics served, one after the other, like a server's and the clients' it calls
+    pub metrics: Vec<Arc<Metrics>>,
+}
+
+impl<S> MetricsEndpoint<S> {
+    /// Serve the metrics at the path in front of the service
+    pub fn new(service: S, path: &str, metrics: Vec<Arc<Metrics>>) -> MetricsEndpoint<S> {
+        MetricsEndpoint { service, path: path.to_string(), metrics }
+    }
+}
+
+impl<S> Service for MetricsEndpoint<S>
+        where S: Service<Request = Request, Response = Response, Error = hyper::Error>, S::Future: 'static {
+    type Request = Request;
+    type Response = Response;
+    type Error = hyper::Error;
+    type Future = Box<dyn Future<Item = Response, Error = hyper::Error>>;
+
+    fn call(&self, req: Request) -> Self::Future {
+        if req.method() != &Method::Get || req.path() != self.path { return Box::new(self.service.call(req)); }
+        let body: String = self.metrics.iter().map(|v| v.render()).collect();
+        Box::new(future::ok(Response::new().
+            with_header(ContentType(PROMETHEUS_CONTENT_TYPE.parse().unwrap())).
+            with_body(body)))
+    }
+}
@@ -0,0 +1,99 @@
+use prost::Message;
+use prost_build;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The `prost_twirp.policy` method option
+#[derive(Clone, PartialEq, Message)]
+pub struct Policy {
+    #[prost(string, repeated, tag = "1")]
+    pub scopes: Vec<String>,
+    #[prost(string, repeated, tag = "2")]
+    pub roles: Vec<String>,
+}
+
+/// The start of what prost-build generates for `Policy` in `options.proto`, to recognize when it is compiled
+pub const POLICY_MESSAGE: &str = "/// Who may call a method, checked by generated servers against the principal \
+    of each call.\n#[derive(Clone, PartialEq, Message)]\npub struct Policy {";
+
+// Just the parts of the descriptors with the options
+
+#[derive(Clone, PartialEq, Message)]
+struct FileDescriptorSet {
+    #[prost(message, repeated, tag = "1")]
+    file: Vec<FileDescriptorProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct FileDescriptorProto {
+    #[prost(string, tag = "2")]
+    package: String,
+    #[prost(message, repeated, tag = "6")]
+    service: Vec<ServiceDescriptorProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ServiceDescriptorProto {
+    #[prost(string, tag = "1")]
+    name: String,
+    #[prost(message, repeated, tag = "2")]
+    method: Vec<MethodDescriptorProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct MethodDescriptorProto {
+    #[prost(string, tag = "1")]
+    name: String,
+    #[prost(message, optional, tag = "4")]
+    options: Option<MethodOptions>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct MethodOptions {
+    #[prost(message, optional, tag = "51234")]
+    policy: Option<Policy>,
+}
+
+/// The directory with `prost_twirp/options.proto`
+pub fn include_dir() -> PathBuf { Path::new(env!("CARGO_MANIFEST_DIR")).join("proto") }
+
+/// Run protoc over the protos and return the policies by qualified service and method, like `pkg.Service/Method`
+///
+/// prost drops unknown options from the descriptors it gives service generators, so they are read separately.
+pub fn read_policies<P: AsRef<Path>>(protos: &[P], includes: &[P]) -> io::Result<HashMap<String, Policy>> {
+    static READS: AtomicUsize = AtomicUsize::new(0);
+    let descriptor_set = env::temp_dir().
+        join(format!("prost-twirp-{}-{}.desc", process::id(), READS.fetch_add(1, Ordering::Relaxed)));
+    let mut cmd = Command::new(prost_build::protoc());
+    cmd.arg("--include_imports").arg("-o").arg(&descriptor_set);
+    for include in includes { cmd.arg("-I").arg(include.as_ref()); }
+    cmd.arg("-I").arg(include_dir()).arg("-I").arg(prost_build::protoc_include());
+    for proto in protos { cmd.arg(proto.as_ref()); }
+    let output = cmd.output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!("protoc failed: {}", String::from_utf8_lossy(&output.stderr))));
+    }
+    let mut buf = Vec::new();
+    let read = fs::File::open(&descriptor_set).and_then(|mut file| file.read_to_end(&mut buf));
+    let _ = fs::remove_file(&descriptor_set);
+    read?;
+    let descriptor_set = FileDescriptorSet::decode(&buf)?;
+    let mut policies = HashMap::new();
+    for file in descriptor_set.file {
+        for service in file.service {
+            let service_name =
+                if file.package.is_empty() { service.name } else { format!("{}.{}", file.package, service.name) };
+            for method in service.method {
+                if let Some(policy) = method.options.and_then(|v| v.policy) {
+                    policies.insert(format!("{}/{}", service_name, method.name), policy);
+                }
+            }
+        }
+    }
+    Ok(policies)
+}
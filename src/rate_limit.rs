@@ -0,0 +1,159 @@
+use futures::future;
+use service_run::{HyperService, PTRes, ServiceRequest, TwirpError};
+use std::cmp;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A function taking a key from a request
+pub type KeyFn = Box<dyn Fn(&ServiceRequest<Vec<u8>>) -> Option<String> + Send + Sync>;
+
+/// How the caller of a request is identified for rate limiting
+pub enum CallerKey {
+    /// The value of the given header, like an API key
+    Header(String),
+    /// The IP address the request came from
+    RemoteIp,
+    /// The subject of the principal verified by an authentication layer like `Authenticated` in front of this one
+    Principal,
+    /// A key taken from the request by the function
+    Custom(KeyFn),
+}
+
+impl CallerKey {
+    /// The key for the request, or `None` if the caller can't be identified
+    pub fn key(&self, req: &ServiceRequest<Vec<u8>>) -> Option<String> {
+        match *self {
+            CallerKey::Header(ref name) => req.headers.get_raw(name).and_then(|v| v.one()).
+                map(|v| String::from_utf8_lossy(v).into_owned()),
+            CallerKey::RemoteIp => req.remote_addr.map(|v| v.ip().to_string()),
+            CallerKey::Principal => req.principal.as_ref().map(|v| v.subject.clone()),
+            CallerKey::Custom(ref key) => key(req),
+        }
+    }
+}
+
+impl fmt::Debug for CallerKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CallerKey::Header(ref name) => f.debug_tuple("Header").field(name).finish(),
+            CallerKey::RemoteIp => f.write_str("RemoteIp"),
+            CallerKey::Principal => f.write_str("Principal"),
+            CallerKey::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+/// A token bucket budget of calls
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    /// The most calls that can be made at once after being idle
+    pub burst: u32,
+    /// How many calls are allowed per second over time
+    pub per_second: f64,
+}
+
+impl RateLimit {
+    /// A budget of the given calls per second with a burst of the same size
+    pub fn per_second(calls: u32) -> RateLimit { RateLimit { burst: calls, per_second: calls as f64 } }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    /// Take a token, or return how long until there is one
+    fn take(&mut self, limit: &RateLimit, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.duration_since(self.updated);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * limit.per_second).min(limit.burst as f64);
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else if limit.per_second > 0.0 {
+            Err(Duration::from_millis(((1.0 - self.tokens) / limit.per_second * 1000.0).ceil() as u64))
+        } else {
+            Err(Duration::from_secs(u64::from(u32::MAX)))
+        }
+    }
+
+    /// Whether the bucket has refilled so it's the same as a new one
+    fn is_full(&self, limit: &RateLimit, now: Instant) -> bool {
+        self.tokens + now.duration_since(self.updated).as_secs_f64() * limit.per_second >= limit.burst as f64
+    }
+}
+
+/// Buckets by caller key and method path, or no path for the default limit
+#[derive(Debug, Default)]
+struct Buckets {
+    buckets: HashMap<(String, Option<String>), Bucket>,
+    /// The bucket count after full buckets were last removed
+    pruned_len: usize,
+}
+
+/// A `HyperService` wrapper that limits how often each caller can call the service
+///
+/// Callers are identified by the `key` and each gets a token bucket per limit. Calls over the limit fail with a
+/// `resource_exhausted` error and a `Retry-After` of when the next call would be allowed. Calls whose caller can't be
+/// identified aren't limited. The `HyperServer` should be cloned for each connection so they share buckets.
+pub struct RateLimited<S: HyperService> {
+    /// The wrapped service
+    pub service: S,
+    /// How callers are identified
+    pub key: CallerKey,
+    /// The limit for each caller across methods without their own limit, or `None` for no limit
+    pub limit: Option<RateLimit>,
+    /// Separate limits for each caller of a method, keyed by path like `Haberdasher::MAKE_HAT_METHOD.path`
+    pub method_limits: HashMap<String, RateLimit>,
+    buckets: Mutex<Buckets>,
+}
+
+impl<S: HyperService> RateLimited<S> {
+    /// Limit the service to the given budget for each caller
+    pub fn new(service: S, key: CallerKey, limit: RateLimit) -> RateLimited<S> {
+        RateLimited { service, key, limit: Some(limit), method_limits: HashMap::new(), buckets: Default::default() }
+    }
+
+    /// Take a call from the caller's budget for the path, or return how long until one is allowed
+    fn take(&self, key: String, path: &str) -> Result<(), Duration> {
+        let (path, limit) = match self.method_limits.get(path) {
+            Some(limit) => (Some(path.to_string()), limit),
+            None => match self.limit {
+                Some(ref limit) => (None, limit),
+                None => return Ok(()),
+            },
+        };
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|err| err.into_inner());
+        let result = buckets.buckets.entry((key, path)).
+            or_insert_with(|| Bucket { tokens: limit.burst as f64, updated: now }).
+            take(limit, now);
+        // Forget full buckets once there are twice as many as last time, they are the same as new ones
+        if buckets.buckets.len() >= cmp::max(buckets.pruned_len * 2, 1024) {
+            let (default_limit, method_limits) = (self.limit, &self.method_limits);
+            buckets.buckets.retain(|(_, path), bucket| {
+                let limit = path.as_ref().and_then(|path| method_limits.get(path)).or(default_limit.as_ref());
+                !limit.is_some_and(|limit| bucket.is_full(limit, now))
+            });
+            buckets.pruned_len = buckets.buckets.len();
+        }
+        result
+    }
+}
+
+impl<S: HyperService> HyperService for RateLimited<S> {
+    fn handle(&self, req: ServiceRequest<Vec<u8>>) -> PTRes<Vec<u8>> {
+        if let Some(key) = self.key.key(&req) {
+            if let Err(retry_after) = self.take(key, req.uri.path()) {
+                let err = TwirpError::from_code("resource_exhausted", "rate limit exceeded").
+                    with_retry_after(retry_after);
+                return Box::new(future::ok(err.to_resp_raw()));
+            }
+        }
+        self.service.handle(req)
+    }
+}
@@ -1,124 +1,367 @@
-use prost_build::{Method, Service, ServiceGenerator};
+use options::{self, Policy};
+use prettyplease;
+use proc_macro2::{Ident, Span, TokenStream};
+use prost_build::{Comments, Method, Service, ServiceGenerator};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use syn;
 
 #[derive(Default)]
 pub struct TwirpServiceGenerator {
     pub embed_client: bool,
-    type_aliases_generated: bool,
+    /// Whether to format the generated code with rustfmt, which then has to be installed
+    ///
+    /// Otherwise it is only pretty printed, so the output doesn't depend on whether or which rustfmt is installed.
+    pub rustfmt: bool,
+    /// Packages that already have the type aliases, since prost-build puts all files of a package in one module
+    aliased_packages: HashSet<String>,
+    /// Whether the file being generated needs the embedded client in its module
+    embed_pending: bool,
+    /// The `prost_twirp.policy` options by qualified service and method
+    policies: HashMap<String, Policy>,
+    /// Whether `read_policies` was called
+    policies_read: bool,
+    /// Whether `prost_twirp/options.proto` was among the compiled files
+    options_imported: bool,
 }
 
 impl TwirpServiceGenerator {
     pub fn new() -> TwirpServiceGenerator { Default::default() }
 
-    fn prost_twirp_mod(&self) -> &str { if self.embed_client { "prost_twirp" } else { "::prost_twirp" } }
+    /// The include directory for importing `prost_twirp/options.proto`, to give prost-build with the others
+    pub fn options_include() -> PathBuf { options::include_dir() }
 
-    fn generate_type_aliases(&mut self, buf: &mut String) {
-        if !self.type_aliases_generated {
-            self.type_aliases_generated = true;
-            buf.push_str(&format!(
-                "\n\
-                pub type PTReq<I> = {0}::PTReq<I>;\n\
-                pub type PTRes<O> = {0}::PTRes<O>;\n",
-                self.prost_twirp_mod()));
+    /// Read the `prost_twirp.policy` method options from the protos, which should be the same as given to prost-build
+    ///
+    /// The options include directory is added to the given ones. The policies are put in the method descriptors and
+    /// checked by the generated servers. prost drops the options before the generator sees them, so **policies are
+    /// only enforced if this is called**. Generating services from protos that import `prost_twirp/options.proto`
+    /// without calling it panics, instead of serving their methods unchecked.
+    pub fn read_policies<P: AsRef<Path>>(&mut self, protos: &[P], includes: &[P]) -> io::Result<()> {
+        self.policies.extend(options::read_policies(protos, includes)?);
+        self.policies_read = true;
+        Ok(())
+    }
+
+    /// The method's policy as a `MethodPolicy` expression, if it has one
+    fn policy(&self, service: &Service, method: &Method) -> Option<TokenStream> {
+        let (pt, key) = (self.prost_twirp_mod(), format!("{}/{}", self.service_name(service), method.proto_name));
+        self.policies.get(&key).map(|policy| {
+            let (scopes, roles) = (&policy.scopes, &policy.roles);
+            quote!(#pt::MethodPolicy { scopes: &[#(#scopes),*], roles: &[#(#roles),*] })
+        })
+    }
+
+    fn prost_twirp_mod(&self) -> TokenStream {
+        if self.embed_client { quote!(prost_twirp) } else { quote!(::prost_twirp) }
+    }
+
+    fn generate_type_aliases(&mut self, service: &Service) -> TokenStream {
+        if !self.aliased_packages.insert(service.package.clone()) { return TokenStream::new(); }
+        self.embed_pending = self.embed_client;
+        let pt = self.prost_twirp_mod();
+        quote! {
+            pub type PTReq<I> = #pt::PTReq<I>;
+            pub type PTRes<O, E = #pt::ProstTwirpError> = #pt::PTRes<O, E>;
+        }
+    }
+
+    fn generate_main_trait(&self, service: &Service) -> TokenStream {
+        let (pt, name, docs) = (self.prost_twirp_mod(), rust_ident(&service.name), doc_attrs(&service.comments));
+        let methods = service.methods.iter().map(|method| {
+            let (sig, docs) = (self.method_sig(method, quote!(, E)), doc_attrs(&method.comments));
+            quote!(#(#docs)* #sig;)
+        });
+        quote! {
+            #(#docs)*
+            pub trait #name<E = #pt::ProstTwirpError> {
+                #(#methods)*
+            }
+        }
+    }
+
+    fn method_sig(&self, method: &Method, err_param: TokenStream) -> TokenStream {
+        let (pt, name) = (self.prost_twirp_mod(), rust_ident(&method.name));
+        let (input, output) = (rust_type(&method.input_type), rust_type(&method.output_type));
+        quote!(fn #name(&self, i: #pt::PTReq<#input>) -> #pt::PTRes<#output #err_param>)
+    }
+
+    fn generate_main_impl(&self, service: &Service) -> TokenStream {
+        let (pt, name) = (self.prost_twirp_mod(), rust_ident(&service.name));
+        let (client, server) = (suffixed_ident(&service.name, "Client"), suffixed_ident(&service.name, "Server"));
+        let service_name = self.service_name(service);
+        let consts = service.methods.iter().map(descriptor_ident).collect::<Vec<_>>();
+        let descriptors = service.methods.iter().zip(&consts).map(|(method, ident)| {
+            let (proto_name, path) = (&method.proto_name, self.method_path(service, method));
+            let doc = format!(" The `{}` method", proto_name);
+            let policy = self.policy(service, method).map_or_else(|| quote!(None), |policy| quote!(Some(#policy)));
+            quote! {
+                #[doc = #doc]
+                pub const #ident: #pt::MethodDescriptor = #pt::MethodDescriptor {
+                    service: #service_name, method: #proto_name, path: #path, policy: #policy,
+                };
+            }
+        });
+        // Edition 2015 reads `dyn ::hyper` as a path, so the trait objects of crate paths lead with their lifetime
+        quote! {
+            #[allow(dead_code)]
+            impl dyn #name {
+                #(#descriptors)*
+
+                /// Every method of the service
+                pub const METHODS: &'static [#pt::MethodDescriptor] = &[#(Self::#consts),*];
+
+                pub fn new_client(client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+                        root_url: &str) -> Box<dyn #name> {
+                    Box::new(#client::new(client, root_url))
+                }
+
+                /// A client whose calls get their `Authorization` header from the credential provider
+                pub fn new_client_with_credentials<C>(client: ::hyper::Client<::hyper::client::HttpConnector,
+                        ::hyper::Body>, root_url: &str, credentials: C) -> Box<dyn #name>
+                        where C: 'static + #pt::CredentialProvider {
+                    Box::new(#client::new_with_credentials(client, root_url, credentials))
+                }
+
+                pub fn new_server<T, E>(v: T) -> Box<dyn 'static + ::hyper::server::Service<Request = ::hyper::Request,
+                        Response = ::hyper::Response, Error = ::hyper::Error,
+                        Future = Box<dyn 'static + ::futures::Future<Item = ::hyper::Response,
+                            Error = ::hyper::Error>>>>
+                        where T: 'static + #name<E>, E: 'static + #pt::IntoTwirpError {
+                    let server = #server::new(v);
+                    Box::new(#pt::HyperServer::new(server))
+                }
+            }
+        }
+    }
+
+    fn generate_client_struct(&self, service: &Service) -> TokenStream {
+        let (pt, client) = (self.prost_twirp_mod(), suffixed_ident(&service.name, "Client"));
+        quote!(pub struct #client(pub #pt::HyperClient);)
+    }
+
+    fn generate_client_impl(&self, service: &Service) -> TokenStream {
+        let (pt, name, client) = (self.prost_twirp_mod(), rust_ident(&service.name),
+            suffixed_ident(&service.name, "Client"));
+        let methods = service.methods.iter().map(|method| {
+            let (sig, path) = (self.method_sig(method, TokenStream::new()), self.method_path(service, method));
+            quote!(#sig { self.0.go(#path, i) })
+        });
+        // The calls are prefixed so they never shadow the trait methods, which take a request
+        let calls = service.methods.iter().map(|method| {
+            let (method_name, path, docs) = (call_ident(method), self.method_path(service, method),
+                doc_attrs(&method.comments));
+            let (input, output) = (rust_type(&method.input_type), rust_type(&method.output_type));
+            quote! {
+                #(#docs)*
+                pub fn #method_name(&self, i: #input) -> #pt::Call<#input, #output> {
+                    #pt::Call::new(&self.0, #path, #pt::ServiceRequest::new(i))
+                }
+            }
+        });
+        quote! {
+            #[allow(dead_code)]
+            impl #client {
+                pub fn new(client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+                        root_url: &str) -> #client {
+                    #client(#pt::HyperClient::new(client, root_url))
+                }
+
+                /// A client whose calls get their `Authorization` header from the credential provider
+                pub fn new_with_credentials<C>(client: ::hyper::Client<::hyper::client::HttpConnector,
+                        ::hyper::Body>, root_url: &str, credentials: C) -> #client
+                        where C: 'static + #pt::CredentialProvider {
+                    #client(#pt::HyperClient::new(client, root_url).with_credentials(credentials))
+                }
+
+                #(#calls)*
+            }
+
+            impl #name for #client {
+                #(#methods)*
+            }
+        }
+    }
+
+    fn generate_server_struct(&self, service: &Service) -> TokenStream {
+        let (pt, name) = (self.prost_twirp_mod(), rust_ident(&service.name));
+        let server = suffixed_ident(&service.name, "Server");
+        // The error type is only used by the trait, so it shouldn't keep the server from being `Send` or `Sync`
+        quote! {
+            pub struct #server<T, E = #pt::ProstTwirpError>(::std::sync::Arc<T>, ::std::marker::PhantomData<fn() -> E>)
+                where T: 'static + #name<E>, E: 'static;
+
+            impl<T, E> #server<T, E> where T: 'static + #name<E>, E: 'static {
+                pub fn new(v: T) -> #server<T, E> {
+                    let service = ::std::sync::Arc::new(v);
+                    #server(service, ::std::marker::PhantomData)
+                }
+            }
         }
     }
 
-    fn generate_main_trait(&self, service: &Service, buf: &mut String) {
-        buf.push_str("\n");
-        service.comments.append_with_indent(0, buf);
-        buf.push_str(&format!("pub trait {} {{", service.name));
-        for method in service.methods.iter() {
-            buf.push_str("\n");
-            method.comments.append_with_indent(1, buf);
-            buf.push_str(&format!("    {};\n", self.method_sig(method)));
+    fn generate_server_impl(&self, service: &Service) -> TokenStream {
+        let (pt, name) = (self.prost_twirp_mod(), rust_ident(&service.name));
+        let server = suffixed_ident(&service.name, "Server");
+        // Make match arms for each method, checking the policy first if there is one
+        let arms = service.methods.iter().map(|method| {
+            let (path, method_name) = (self.method_path(service, method), rust_ident(&method.name));
+            let handle = quote!(req.handle_proto(move |v| service.#method_name(v)));
+            match self.policy(service, method) {
+                Some(policy) => quote! {
+                    (::hyper::Method::Post, #path) => match (#policy).check(req.principal.as_ref()) {
+                        Ok(()) => #handle,
+                        Err(err) => Box::new(::futures::future::ok(err.to_resp_raw())),
+                    },
+                },
+                None => quote!((::hyper::Method::Post, #path) => #handle,),
+            }
+        });
+        quote! {
+            impl<T, E> #pt::HyperService for #server<T, E>
+                    where T: 'static + #name<E>, E: 'static + #pt::IntoTwirpError {
+                fn handle(&self, req: #pt::ServiceRequest<Vec<u8>>) -> #pt::PTRes<Vec<u8>> {
+                    let service = self.0.clone();
+                    match (req.method.clone(), req.uri.path()) {
+                        #(#arms)*
+                        _ => {
+                            let msg = format!("no handler for path {:?}", req.uri.path());
+                            let err = #pt::TwirpError::bad_route(&msg, &req.method, req.uri.path());
+                            Box::new(::futures::future::ok(err.to_resp_raw()))
+                        }
+                    }
+                }
+            }
         }
-        buf.push_str("}\n");
-    }
-
-    fn method_sig(&self, method: &Method) -> String {
-        format!("fn {0}(&self, i: {1}::PTReq<{2}>) -> {1}::PTRes<{3}>",
-            method.name, self.prost_twirp_mod(), method.input_type, method.output_type)
-    }
-
-    fn generate_main_impl(&self, service: &Service, buf: &mut String) {
-        buf.push_str(&format!(
-            "\n\
-            impl {0} {{\n    \
-                pub fn new_client(client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>, root_url: &str) -> Box<{0}> {{\n        \
-                    Box::new({0}Client({1}::HyperClient::new(client, root_url)))\n    \
-                }}\n    \
-                pub fn new_server<T: 'static + {0}>(v: T) -> Box<::hyper::server::Service<Request=::hyper::Request,\n            \
-                        Response=::hyper::Response, Error=::hyper::Error, Future=Box<::futures::Future<Item=::hyper::Response, Error=::hyper::Error>>>> {{\n        \
-                    Box::new({1}::HyperServer::new({0}Server(::std::sync::Arc::new(v))))\n    \
-                }}\n\
-            }}\n",
-            service.name, self.prost_twirp_mod()));
-    }
-
-    fn generate_client_struct(&self, service: &Service, buf: &mut String) {
-        buf.push_str(&format!(
-            "\npub struct {}Client(pub {}::HyperClient);\n",
-            service.name, self.prost_twirp_mod()));
-    }
-
-    fn generate_client_impl(&self, service: &Service, buf: &mut String) {
-        buf.push_str(&format!("\nimpl {0} for {0}Client {{", service.name));
-        for method in service.methods.iter() {
-            buf.push_str(&format!(
-                "\n    {} {{\n        \
-                    self.0.go(\"/twirp/{}.{}/{}\", i)\n    \
-                }}\n", self.method_sig(method), service.package, service.proto_name, method.proto_name));
+    }
+
+    fn service_name(&self, service: &Service) -> String {
+        // Services without a package aren't qualified at all
+        if service.package.is_empty() {
+            service.proto_name.clone()
+        } else {
+            format!("{}.{}", service.package, service.proto_name)
         }
-        buf.push_str("}\n");
-    }
-
-    fn generate_server_struct(&self, service: &Service, buf: &mut String) {
-        buf.push_str(&format!(
-            "\npub struct {0}Server<T: 'static + {0}>(::std::sync::Arc<T>);\n",
-            service.name));
-    }
-
-    fn generate_server_impl(&self, service: &Service, buf: &mut String) {
-        buf.push_str(&format!(
-            "\n\
-            impl<T: 'static + {0}> {1}::HyperService for {0}Server<T> {{\n    \
-                fn handle(&self, req: {1}::ServiceRequest<Vec<u8>>) -> {1}::PTRes<Vec<u8>> {{\n        \
-                    use ::futures::Future;\n        \
-                    let static_service = self.0.clone();\n        \
-                    match (req.method.clone(), req.uri.path()) {{",
-            service.name, self.prost_twirp_mod()));
-        // Make match arms for each type
-        for method in service.methods.iter() {
-            buf.push_str(&format!(
-                "\n            \
-                (::hyper::Method::Post, \"/twirp/{}.{}/{}\") =>\n                \
-                    Box::new(::futures::future::result(req.to_proto()).and_then(move |v| static_service.{}(v)).and_then(|v| v.to_proto_raw())),",
-                service.package, service.proto_name, method.proto_name, method.name));
+    }
+
+    fn method_path(&self, service: &Service, method: &Method) -> String {
+        format!("/twirp/{}/{}", self.service_name(service), method.proto_name)
+    }
+
+    /// Format the generated items and append them to the buffer
+    ///
+    /// They are pretty printed, and then given to rustfmt if it was asked for.
+    fn push_items(&self, items: TokenStream, buf: &mut String) {
+        let file = syn::parse2(items).unwrap_or_else(|err| panic!("generated invalid code: {}", err));
+        let mut code = prettyplease::unparse(&file);
+        if self.rustfmt {
+            code = rustfmt(&code).unwrap_or_else(|err| panic!("rustfmt failed on the generated code: {}", err));
         }
-        // Final 404 arm and end fn
-        buf.push_str(&format!(
-            "\n            \
-                        _ => Box::new(::futures::future::ok({0}::TwirpError::new(::hyper::StatusCode::NotFound, \"not_found\", \"Not found\").to_resp_raw()))\n        \
-                    }}\n    \
-                }}\n\
-            }}",
-            self.prost_twirp_mod()));
+        if !buf.is_empty() && !buf.ends_with('\n') { buf.push('\n'); }
+        buf.push_str(&code);
+    }
+}
+
+/// Words that can't be identifiers in some edition and aren't already escaped by prost
+const RESERVED: &[&str] = &["abstract", "async", "await", "become", "box", "do", "dyn", "final", "gen", "macro",
+    "override", "priv", "try", "typeof", "unsized", "virtual", "yield"];
+
+/// An identifier for the given name, escaped as a raw identifier if it is reserved
+///
+/// Names that can't be raw identifiers, like `self`, get a trailing underscore instead the same way prost does.
+fn rust_ident(name: &str) -> Ident {
+    match name {
+        "self" | "Self" | "super" | "crate" | "extern" => Ident::new(&format!("{}_", name), Span::call_site()),
+        name if RESERVED.contains(&name) || syn::parse_str::<Ident>(name).is_err() =>
+            Ident::new_raw(name, Span::call_site()),
+        name => Ident::new(name, Span::call_site()),
     }
 }
 
+/// The identifier for a method's descriptor constant, which is never reserved and never `METHODS` with its suffix
+fn descriptor_ident(method: &Method) -> Ident {
+    Ident::new(&format!("{}_METHOD", method.name.trim_end_matches('_').to_uppercase()), Span::call_site())
+}
+
+/// Panic if two methods of the service would generate the same identifiers, like `GetX` and `get_x`
+fn check_method_names(service: &Service) {
+    let mut seen = HashMap::new();
+    for method in &service.methods {
+        if let Some(other) = seen.insert(descriptor_ident(method).to_string(), &method.proto_name) {
+            panic!("methods {:?} and {:?} of service {:?} would both be generated as {:?}", other, method.proto_name,
+                service.proto_name, method.name);
+        }
+    }
+}
+
+/// The identifier for a method's call on the client, which is never reserved with its prefix
+fn call_ident(method: &Method) -> Ident {
+    Ident::new(&format!("call_{}", method.name.trim_end_matches('_')), Span::call_site())
+}
+
+/// An identifier for the given name with a suffix, which can never be reserved
+fn suffixed_ident(name: &str, suffix: &str) -> Ident { Ident::new(&format!("{}{}", name, suffix), Span::call_site()) }
+
+/// Parse a Rust type given by prost
+fn rust_type(ty: &str) -> syn::Type {
+    syn::parse_str(ty).unwrap_or_else(|err| panic!("invalid type {:?} from prost: {}", ty, err))
+}
+
+/// Doc attributes for the leading and trailing comments
+///
+/// Detached comments are left out since they aren't docs and can't be represented in the token stream.
+fn doc_attrs(comments: &Comments) -> Vec<TokenStream> {
+    let mut lines = comments.leading.clone();
+    if !comments.leading.is_empty() && !comments.trailing.is_empty() { lines.push(String::new()); }
+    lines.extend(comments.trailing.iter().cloned());
+    lines.iter().map(|line| quote!(#[doc = #line])).collect()
+}
+
+/// Format the code with rustfmt
+///
+/// `RUSTFMT` can name the rustfmt to use, otherwise the one on the path is.
+fn rustfmt(code: &str) -> io::Result<String> {
+    let rustfmt = env::var_os("RUSTFMT").unwrap_or_else(|| "rustfmt".into());
+    let mut child = Command::new(rustfmt).args(["--edition", "2015", "--emit", "stdout", "--quiet"]).
+        stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    // rustfmt reads all of its input before writing anything
+    child.stdin.take().expect("rustfmt stdin").write_all(code.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(String::from_utf8_lossy(&output.stderr).into_owned()));
+    }
+    String::from_utf8(output.stdout).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
 impl ServiceGenerator for TwirpServiceGenerator {
     fn generate(&mut self, service: Service, buf: &mut String) {
-        self.generate_type_aliases(buf);
-        self.generate_main_trait(&service, buf);
-        self.generate_main_impl(&service, buf);
-        self.generate_client_struct(&service, buf);
-        self.generate_client_impl(&service, buf);
-        self.generate_server_struct(&service, buf);
-        self.generate_server_impl(&service, buf);
+        check_method_names(&service);
+        // protoc puts imported files first, so the options file has been finalized by now if it is used
+        if self.options_imported && !self.policies_read {
+            panic!("prost_twirp/options.proto is imported, but its policies weren't read, so the methods of {:?} \
+                would be served without checking them; call TwirpServiceGenerator::read_policies with the protos",
+                self.service_name(&service));
+        }
+        let mut items = self.generate_type_aliases(&service);
+        items.extend(self.generate_main_trait(&service));
+        items.extend(self.generate_main_impl(&service));
+        items.extend(self.generate_client_struct(&service));
+        items.extend(self.generate_client_impl(&service));
+        items.extend(self.generate_server_struct(&service));
+        items.extend(self.generate_server_impl(&service));
+        self.push_items(items, buf);
     }
 
     fn finalize(&mut self, buf: &mut String) {
-        if self.embed_client {
-            buf.push_str("\n/// Embedded module from prost_twirp source\n#[allow(dead_code)]\nmod prost_twirp {\n");
+        // The generator isn't told which file this is, so the options file is recognized by the message it generates
+        if buf.contains(options::POLICY_MESSAGE) { self.options_imported = true; }
+        // This is called for every file, including imported ones, so only embed once per module with services
+        if self.embed_pending {
+            self.embed_pending = false;
+            buf.push_str("\n/// Embedded module from prost_twirp source\n#[allow(dead_code, unexpected_cfgs)]\nmod prost_twirp {\n");
             for line in include_str!("service_run.rs").lines() {
                 buf.push_str(&format!("    {}\n", line));
             }
@@ -1,21 +1,52 @@
-use futures::{Future, Stream};
-use futures::future;
+use futures::{Future, Poll, Stream};
+use futures::future::{self, Either, Shared};
 use hyper;
-use hyper::{Body, Client, Headers, HttpVersion, Method, Request, Response, StatusCode, Uri};
+use hyper::{Body, Chunk, Client, Headers, HttpVersion, Method, Request, Response, StatusCode, Uri};
 use hyper::client::HttpConnector;
-use hyper::header::{ContentLength, ContentType};
+use hyper::header::{ContentLength, ContentType, Header, Location, Raw, RetryAfter};
 use hyper::server::Service;
 use prost::{DecodeError, EncodeError, Message};
 use serde_json;
-use std::sync::Arc;
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::cmp;
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::net::SocketAddr;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+use tokio_core::reactor::Timeout;
+#[cfg(feature = "tracing")]
+use tracing;
 
-pub type FutReq<T> = Box<Future<Item=ServiceRequest<T>, Error=ProstTwirpError>>;
+pub type FutReq<T> = Box<dyn Future<Item=ServiceRequest<T>, Error=ProstTwirpError>>;
+
+/// The meta key a retry delay is sent in, as a string of whole seconds like the `Retry-After` header
+pub const RETRY_AFTER_META: &str = "retry_after";
+
+/// The most that will be preallocated for a body based on its `Content-Length` when there is no max body size
+const MAX_BODY_PREALLOC: u64 = 64 * 1024;
+
+/// The protobuf media types accepted by servers, the first being the one used by default
+pub const PROTOBUF_MEDIA_TYPES: &[&str] = &["application/protobuf", "application/x-protobuf"];
+
+/// The media type of a `Content-Type` header value, lowercased and without any parameters
+pub fn media_type(content_type: &str) -> String {
+    content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase()
+}
 
 /// The type of every service request 
 pub type PTReq<I> = ServiceRequest<I>;
 
 /// The type of every service response
-pub type PTRes<O> = Box<Future<Item=ServiceResponse<O>, Error=ProstTwirpError>>;
+///
+/// Servers may use their own error type as long as it implements `IntoTwirpError`.
+pub type PTRes<O, E = ProstTwirpError> = Box<dyn Future<Item=ServiceResponse<O>, Error=E>>;
 
 /// A request with HTTP info and the serialized input object
 #[derive(Debug)]
@@ -34,6 +65,28 @@ pub struct ServiceRequest<T> {
     pub headers: Headers,
     // The serialized request object
     pub input: T,
+    /// The address of the caller, if known
+    ///
+    /// It is only valuable for servers.
+    pub remote_addr: Option<SocketAddr>,
+    /// The caller as verified by an authentication layer like `Authenticated`, if any
+    ///
+    /// It is only valuable for servers.
+    pub principal: Option<Principal>,
+    /// The W3C trace context of the call, if any
+    ///
+    /// Servers set it from the `traceparent` and `tracestate` headers, with a new span ID for the call. Clients send
+    /// a child of it, or of `TraceContext::current` if it isn't set, unless the headers already have one.
+    pub trace_context: Option<TraceContext>,
+    /// The ID of the request, sent in the `X-Request-Id` header, if any
+    ///
+    /// Servers set it depending on `HyperServer::request_ids`. Clients send it, or the current `CallContext`'s, unless
+    /// the headers already have one.
+    pub request_id: Option<String>,
+    /// Headers to add to the response whether the handler succeeds or fails
+    ///
+    /// This is shared by copies of the request. It is only valuable for servers.
+    pub response_headers: ResponseHeaders,
 }
 
 impl<T> ServiceRequest<T> {
@@ -47,15 +100,22 @@ impl<T> ServiceRequest<T> {
             uri: Default::default(),
             method: Method::Post,
             version: HttpVersion::default(),
-            headers: headers,
-            input
+            headers,
+            input,
+            remote_addr: None,
+            principal: None,
+            trace_context: None,
+            request_id: None,
+            response_headers: Default::default(),
         }
     }
     
     /// Copy this request with a different input value
     pub fn clone_with_input<U>(&self, input: U) -> ServiceRequest<U> {
         ServiceRequest { uri: self.uri.clone(), method: self.method.clone(), version: self.version,
-            headers: self.headers.clone(), input }
+            headers: self.headers.clone(), input, remote_addr: self.remote_addr, principal: self.principal.clone(),
+            trace_context: self.trace_context.clone(), request_id: self.request_id.clone(),
+            response_headers: self.response_headers.clone() }
     }
 }
 
@@ -66,21 +126,35 @@ impl<T: Message + Default + 'static> From<T> for ServiceRequest<T> {
 impl ServiceRequest<Vec<u8>> {
     /// Turn a hyper request to a boxed future of a byte-array service request
     pub fn from_hyper_raw(req: Request) -> FutReq<Vec<u8>> {
-        let uri = req.uri().clone();
-        let method = req.method().clone();
-        let version = req.version();
-        let headers = req.headers().clone();
-        Box::new(req.body().concat2().map_err(ProstTwirpError::HyperError).map(move |body| {
-            ServiceRequest { uri, method, version, headers, input: body.to_vec() }
-        }))
+        ServiceRequest::from_hyper_raw_limited(req, None)
+    }
+
+    /// Turn a hyper request to a boxed future of a byte-array service request, failing with a `malformed` Twirp error
+    /// if the body is larger than the given max
+    #[allow(deprecated)]
+    pub fn from_hyper_raw_limited(req: Request, max_body_size: Option<u64>) -> FutReq<Vec<u8>> {
+        // Hyper 0.11 has no other way to get the remote address
+        let remote_addr = req.remote_addr();
+        let (method, uri, version, headers, body) = req.deconstruct();
+        let len = headers.get::<ContentLength>().map(|v| v.0);
+        Box::new(body_to_vec(body, len, max_body_size).
+            map(move |input| ServiceRequest { uri, method, version, headers, input, remote_addr, principal: None,
+                trace_context: None, request_id: None, response_headers: Default::default() }))
     }
 
     /// Turn a byte-array service request into a hyper request
+    ///
+    /// This copies the body, `into_hyper_raw` should be preferred when the request is no longer needed.
     pub fn to_hyper_raw(&self) -> Request {
-        let mut req = Request::new(Method::Post, self.uri.clone());
-        req.headers_mut().clone_from(&self.headers);
+        self.clone_with_input(self.input.clone()).into_hyper_raw()
+    }
+
+    /// Turn a byte-array service request into a hyper request, moving the body without copying
+    pub fn into_hyper_raw(self) -> Request {
+        let mut req = Request::new(Method::Post, self.uri);
+        *req.headers_mut() = self.headers;
         req.headers_mut().set(ContentLength(self.input.len() as u64));
-        req.set_body(self.input.clone());
+        req.set_body(self.input);
         req
     }
 
@@ -99,12 +173,23 @@ impl ServiceRequest<Vec<u8>> {
             Err(err) => Err(self.body_err(ProstTwirpError::ProstDecodeError(err)))
         }
     }
+
+    /// Decode the request, call the handler with it, and encode the handler's response
+    ///
+    /// Used by generated servers. Handler errors are converted with `IntoTwirpError::into_prost_twirp_error`.
+    pub fn handle_proto<I, O, E, F>(&self, handler: F) -> PTRes<Vec<u8>>
+            where I: Message + Default + 'static, O: Message + Default + 'static, E: IntoTwirpError + 'static,
+                  F: 'static + FnOnce(ServiceRequest<I>) -> PTRes<O, E> {
+        Box::new(future::result(self.to_proto()).
+            and_then(move |v| handler(v).map_err(IntoTwirpError::into_prost_twirp_error)).
+            and_then(|v| v.to_proto_raw()))
+    }
 }
 
 impl<T: Message + Default + 'static> ServiceRequest<T> {
     /// Turn a protobuf service request into a byte-array service request
     pub fn to_proto_raw(&self) -> Result<ServiceRequest<Vec<u8>>, ProstTwirpError> {
-        let mut body = Vec::new();
+        let mut body = Vec::with_capacity(self.input.encoded_len());
         if let Err(err) = self.input.encode(&mut body) {
             Err(ProstTwirpError::ProstEncodeError(err))
         } else {
@@ -113,13 +198,26 @@ impl<T: Message + Default + 'static> ServiceRequest<T> {
     }
 
     /// Turn a hyper request into a protobuf service request
+    ///
+    /// The body is decoded straight from the chunks it arrived in, which are only copied together if it can't be.
+    #[allow(deprecated)]
     pub fn from_hyper_proto(req: Request) -> FutReq<T> {
-        Box::new(ServiceRequest::from_hyper_raw(req).and_then(|v| v.to_proto()))
+        let remote_addr = req.remote_addr();
+        let (method, uri, version, headers, body) = req.deconstruct();
+        let mut raw = ServiceRequest { uri, method, version, headers, input: Vec::new(), remote_addr, principal: None,
+            trace_context: None, request_id: None, response_headers: Default::default() };
+        Box::new(body_chunks(body).and_then(move |chunks| match T::decode(ChunksBuf::new(&chunks)) {
+            Ok(v) => Ok(raw.clone_with_input(v)),
+            Err(err) => {
+                raw.input = join_chunks(&chunks);
+                Err(raw.body_err(ProstTwirpError::ProstDecodeError(err)))
+            },
+        }))
     }
 
     /// Turn a protobuf service request into a hyper request
     pub fn to_hyper_proto(&self) -> Result<Request, ProstTwirpError> {
-        self.to_proto_raw().map(|v| v.to_hyper_raw())
+        self.to_proto_raw().map(|v| v.into_hyper_raw())
     }
 }
 
@@ -147,7 +245,7 @@ impl<T> ServiceResponse<T> {
         headers.set(ContentType("application/protobuf".parse().unwrap()));
         ServiceResponse {
             version: HttpVersion::default(),
-            headers: headers,
+            headers,
             status: StatusCode::Ok,
             output
         }
@@ -165,22 +263,29 @@ impl<T: Message + Default + 'static> From<T> for ServiceResponse<T> {
 
 impl ServiceResponse<Vec<u8>> {
     /// Turn a hyper response to a boxed future of a byte-array service response
-    pub fn from_hyper_raw(resp: Response) -> PTRes<Vec<u8>> {
+    pub fn from_hyper_raw(mut resp: Response) -> PTRes<Vec<u8>> {
         let version = resp.version();
-        let headers = resp.headers().clone();
         let status = resp.status();
-        Box::new(resp.body().concat2().map_err(ProstTwirpError::HyperError).map(move |body| {
-            ServiceResponse { version, headers, status, output: body.to_vec() }
-        }))
+        let headers = ::std::mem::replace(resp.headers_mut(), Headers::new());
+        let len = headers.get::<ContentLength>().map(|v| v.0);
+        Box::new(body_to_vec(resp.body(), len, None).map(move |output| ServiceResponse { version, headers, status, output }))
     }
 
     /// Turn a byte-array service response into a hyper response
+    ///
+    /// This copies the body, `into_hyper_raw` should be preferred when the response is no longer needed.
     pub fn to_hyper_raw(&self) -> Response {
+        self.clone_with_output(self.output.clone()).into_hyper_raw()
+    }
+
+    /// Turn a byte-array service response into a hyper response, moving the body without copying
+    pub fn into_hyper_raw(self) -> Response {
+        let len = self.output.len() as u64;
         Response::new().
             with_status(self.status).
-            with_headers(self.headers.clone()).
-            with_header(ContentLength(self.output.len() as u64)).
-            with_body(self.output.clone())
+            with_headers(self.headers).
+            with_header(ContentLength(len)).
+            with_body(self.output)
     }
 
     /// Turn a byte-array service response into a `AfterBodyError`-wrapped version of the given error
@@ -199,10 +304,7 @@ impl ServiceResponse<Vec<u8>> {
                 Err(err) => Err(self.body_err(ProstTwirpError::ProstDecodeError(err)))
             }
         } else {
-            match TwirpError::from_json_bytes(self.status, &self.output) {
-                Ok(err) => Err(self.body_err(ProstTwirpError::TwirpError(err))),
-                Err(err) => Err(self.body_err(ProstTwirpError::JsonDecodeError(err)))
-            }
+            Err(self.body_err(ProstTwirpError::TwirpError(TwirpError::from_resp_raw(self))))
         }
     }
 }
@@ -210,7 +312,7 @@ impl ServiceResponse<Vec<u8>> {
 impl<T: Message + Default + 'static> ServiceResponse<T> {
     /// Turn a protobuf service response into a byte-array service response
     pub fn to_proto_raw(&self) -> Result<ServiceResponse<Vec<u8>>, ProstTwirpError> {
-        let mut body = Vec::new();
+        let mut body = Vec::with_capacity(self.output.encoded_len());
         if let Err(err) = self.output.encode(&mut body) {
             Err(ProstTwirpError::ProstEncodeError(err))
         } else {
@@ -219,23 +321,178 @@ impl<T: Message + Default + 'static> ServiceResponse<T> {
     }
 
     /// Turn a hyper response into a protobuf service response
+    ///
+    /// Successful bodies are decoded straight from the chunks they arrived in. Other bodies are copied together for the
+    /// error.
     pub fn from_hyper_proto(resp: Response) -> PTRes<T> {
-        Box::new(ServiceResponse::from_hyper_raw(resp).and_then(|v| v.to_proto()))
+        Box::new(response_chunks(resp).and_then(response_from_chunks))
     }
 
     /// Turn a protobuf service response into a hyper response
     pub fn to_hyper_proto(&self) -> Result<Response, ProstTwirpError> {
-        self.to_proto_raw().map(|v| v.to_hyper_raw())
+        self.to_proto_raw().map(|v| v.into_hyper_raw())
+    }
+}
+
+/// Collect a hyper body into a byte array, preallocating for the given content length up to the max size
+///
+/// If there is a max size, a `malformed` Twirp error is returned as soon as the length or the body is over it. Without
+/// one, no more than `MAX_BODY_PREALLOC` is preallocated since the length can't be trusted.
+fn body_to_vec(body: Body, len: Option<u64>, max: Option<u64>) -> Box<dyn Future<Item=Vec<u8>, Error=ProstTwirpError>> {
+    let too_large = move || -> ProstTwirpError {
+        TwirpError::new(StatusCode::BadRequest, "malformed",
+            &format!("the request body exceeds the maximum of {} bytes", max.unwrap_or(0))).into()
+    };
+    if let (Some(len), Some(max)) = (len, max) {
+        if len > max { return Box::new(future::err(too_large())); }
+    }
+    // Don't let an untrusted length make us allocate too much up front
+    let cap = cmp::min(len.unwrap_or(0), max.unwrap_or(MAX_BODY_PREALLOC)) as usize;
+    Box::new(body.map_err(ProstTwirpError::HyperError).fold(Vec::with_capacity(cap), move |mut bytes, chunk| {
+        if max.is_some_and(|max| (bytes.len() + chunk.len()) as u64 > max) { return Err(too_large()); }
+        bytes.extend_from_slice(&chunk);
+        Ok(bytes)
+    }))
+}
+
+/// Collect the non-empty chunks of a hyper body without copying them
+fn body_chunks(body: Body) -> Box<dyn Future<Item=Vec<Chunk>, Error=ProstTwirpError>> {
+    Box::new(body.map_err(ProstTwirpError::HyperError).filter(|v| !v.is_empty()).collect())
+}
+
+/// Copy chunks together into one byte array
+fn join_chunks(chunks: &[Chunk]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(chunks.iter().map(|v| v.len()).sum());
+    for chunk in chunks { bytes.extend_from_slice(chunk); }
+    bytes
+}
+
+/// Turn a hyper response into a service response of the chunks of its body
+fn response_chunks(mut resp: Response) -> PTRes<Vec<Chunk>> {
+    let (version, status) = (resp.version(), resp.status());
+    let headers = ::std::mem::replace(resp.headers_mut(), Headers::new());
+    Box::new(body_chunks(resp.body()).map(move |output| ServiceResponse { version, headers, status, output }))
+}
+
+/// Decode a successful response straight from the chunks of its body, copying them together for errors
+fn response_from_chunks<T: Message + Default + 'static>(resp: ServiceResponse<Vec<Chunk>>)
+        -> Result<ServiceResponse<T>, ProstTwirpError> {
+    if resp.status.is_success() {
+        if let Ok(v) = T::decode(ChunksBuf::new(&resp.output)) { return Ok(resp.clone_with_output(v)); }
+    }
+    resp.clone_with_output(join_chunks(&resp.output)).to_proto()
+}
+
+/// The chunks of a body read as one buffer, so messages can be decoded from them without copying them together
+struct ChunksBuf<'a> {
+    chunks: &'a [Chunk],
+    /// How far into the first chunk has been read
+    pos: usize,
+    remaining: usize,
+}
+
+impl<'a> ChunksBuf<'a> {
+    /// A buffer over the chunks, which must all be non-empty
+    fn new(chunks: &'a [Chunk]) -> ChunksBuf<'a> {
+        ChunksBuf { chunks, pos: 0, remaining: chunks.iter().map(|v| v.len()).sum() }
+    }
+}
+
+// Not imported so embedding crates don't need an `extern crate bytes` at their root
+impl<'a> bytes::Buf for ChunksBuf<'a> {
+    fn remaining(&self) -> usize { self.remaining }
+
+    fn bytes(&self) -> &[u8] { self.chunks.first().map_or(&[], |v| &v[self.pos..]) }
+
+    fn advance(&mut self, mut cnt: usize) {
+        assert!(cnt <= self.remaining, "cannot advance past the end of the body");
+        self.remaining -= cnt;
+        while cnt > 0 {
+            let left = self.chunks[0].len() - self.pos;
+            if cnt < left {
+                self.pos += cnt;
+                return;
+            }
+            cnt -= left;
+            self.chunks = &self.chunks[1..];
+            self.pos = 0;
+        }
+    }
+}
+
+/// A caller verified by an authentication layer
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Principal {
+    /// Who the caller is, like a JWT subject or a key ID
+    pub subject: String,
+    /// What the caller may do, like OAuth scopes
+    pub scopes: Vec<String>,
+    /// Anything else known about the caller, like JWT claims
+    pub claims: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Headers a server handler adds to its response, shared between copies of the request
+///
+/// These are added to both successful and error responses, but never replace headers the response already has, like
+/// `Content-Type` or those on a `TwirpError`.
+#[derive(Clone, Debug, Default)]
+pub struct ResponseHeaders(Arc<Mutex<Headers>>);
+
+impl ResponseHeaders {
+    /// Set a typed header, replacing any existing one
+    pub fn set<H: Header>(&self, header: H) { self.lock().set(header) }
+
+    /// Set a raw header, replacing any existing one
+    pub fn set_raw<K: Into<Cow<'static, str>>, V: Into<Raw>>(&self, name: K, value: V) {
+        self.lock().set_raw(name, value)
+    }
+
+    /// Remove a header by name
+    pub fn remove_raw(&self, name: &str) { self.lock().remove_raw(name) }
+
+    /// A copy of the headers set so far
+    pub fn get_all(&self) -> Headers { self.lock().clone() }
+
+    /// Add the headers to the given ones, except those already set
+    pub fn apply_to(&self, headers: &mut Headers) { extend_headers(headers, &self.lock()) }
+
+    fn lock(&self) -> ::std::sync::MutexGuard<'_, Headers> { self.0.lock().unwrap_or_else(|err| err.into_inner()) }
+}
+
+/// Hop-by-hop headers, which only apply to a single connection
+const HOP_BY_HOP_HEADERS: &[&str] = &["Connection", "Keep-Alive", "Proxy-Authenticate", "Proxy-Authorization", "TE",
+    "Trailer", "Transfer-Encoding", "Upgrade"];
+
+/// The headers of an error response that errors made from it keep
+const ERROR_RESPONSE_HEADERS: &[&str] = &["Retry-After"];
+
+/// Set the extra headers that aren't already set on the given ones
+///
+/// `Content-Type`, `Content-Length`, hop-by-hop and `Access-Control-*` headers are never set, those are up to the
+/// server and `Cors`.
+fn extend_headers(headers: &mut Headers, extra: &Headers) {
+    for header in extra.iter() {
+        let name = header.name();
+        let skipped = header.is::<ContentType>() || header.is::<ContentLength>() ||
+            HOP_BY_HOP_HEADERS.iter().any(|v| v.eq_ignore_ascii_case(name)) ||
+            name.get(..15).is_some_and(|v| v.eq_ignore_ascii_case("Access-Control-"));
+        if !skipped && headers.get_raw(name).is_none() {
+            headers.set_raw(name.to_string(), header.raw().clone());
+        }
     }
 }
 
 /// A JSON-serializable Twirp error
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct TwirpError {
     pub status: StatusCode,
     pub error_type: String,
     pub msg: String,
     pub meta: Option<serde_json::Value>,
+    /// Extra headers for the error response, like `Retry-After` or `WWW-Authenticate`
+    ///
+    /// These never replace `Content-Type` or `Content-Length`. They are not part of the JSON body.
+    pub headers: Headers,
 }
 
 impl TwirpError {
@@ -244,20 +501,103 @@ impl TwirpError {
         TwirpError::new_meta(status, error_type, msg, None)
     }
 
+    /// Create a Twirp error with no meta whose status is derived from the Twirp error code
+    ///
+    /// See `TwirpError::status_for_code` for how the status is chosen.
+    pub fn from_code(error_type: &str, msg: &str) -> TwirpError {
+        TwirpError::new(TwirpError::status_for_code(error_type), error_type, msg)
+    }
+
+    /// The HTTP status the Twirp spec assigns to the given error code
+    ///
+    /// Unknown codes are treated as `internal`.
+    pub fn status_for_code(error_type: &str) -> StatusCode {
+        match error_type {
+            "canceled" | "deadline_exceeded" => StatusCode::RequestTimeout,
+            "invalid_argument" | "malformed" | "out_of_range" => StatusCode::BadRequest,
+            "not_found" | "bad_route" => StatusCode::NotFound,
+            "already_exists" | "aborted" => StatusCode::Conflict,
+            "permission_denied" => StatusCode::Forbidden,
+            "unauthenticated" => StatusCode::Unauthorized,
+            "resource_exhausted" => StatusCode::TooManyRequests,
+            "failed_precondition" => StatusCode::PreconditionFailed,
+            "unimplemented" => StatusCode::NotImplemented,
+            "unavailable" => StatusCode::ServiceUnavailable,
+            _ => StatusCode::InternalServerError,
+        }
+    }
+
+    /// Create a `bad_route` error for a request that could not be routed to a method
+    ///
+    /// As in other Twirp implementations, the `twirp_invalid_route` meta is set to the method and path.
+    pub fn bad_route(msg: &str, method: &Method, path: &str) -> TwirpError {
+        let mut meta = serde_json::map::Map::new();
+        meta.insert("twirp_invalid_route".to_string(), serde_json::Value::String(format!("{} {}", method, path)));
+        TwirpError::new_meta(StatusCode::NotFound, "bad_route", msg, Some(serde_json::Value::Object(meta)))
+    }
+
     /// Create a Twirp error with optional meta
     pub fn new_meta(status: StatusCode, error_type: &str, msg: &str, meta: Option<serde_json::Value>) -> TwirpError {
-        TwirpError { status, error_type: error_type.to_string(), msg: msg.to_string(), meta }
+        TwirpError { status, error_type: error_type.to_string(), msg: msg.to_string(), meta, headers: Headers::new() }
+    }
+
+    /// This error with the meta value set, unless the meta isn't an object
+    pub fn with_meta(mut self, key: &str, value: &str) -> TwirpError {
+        if let serde_json::Value::Object(ref mut meta) =
+                *self.meta.get_or_insert_with(|| serde_json::Value::Object(Default::default())) {
+            meta.insert(key.to_string(), value.into());
+        }
+        self
+    }
+
+    /// This error with a typed header set on its response
+    pub fn with_header<H: Header>(mut self, header: H) -> TwirpError {
+        self.headers.set(header);
+        self
+    }
+
+    /// This error with a raw header set on its response
+    pub fn with_header_raw<K: Into<Cow<'static, str>>, V: Into<Raw>>(mut self, name: K, value: V) -> TwirpError {
+        self.headers.set_raw(name, value);
+        self
+    }
+
+    /// This error with a delay after which the call may be retried, usually for `unavailable` or `resource_exhausted`
+    ///
+    /// The delay is rounded up to whole seconds and sent as both the `Retry-After` header and the `retry_after` meta.
+    /// The meta is left alone if it is set to something other than an object.
+    pub fn with_retry_after(mut self, delay: Duration) -> TwirpError {
+        let secs = delay.as_secs() + if delay.subsec_nanos() > 0 { 1 } else { 0 };
+        self.headers.set(RetryAfter::Delay(Duration::from_secs(secs)));
+        self.with_meta(RETRY_AFTER_META, &secs.to_string())
+    }
+
+    /// The delay after which the call may be retried, if the error has one
+    ///
+    /// This is taken from the `Retry-After` header, or the `retry_after` meta if there is no header. A `Retry-After`
+    /// date becomes the delay from now, which is zero if it has passed.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self.headers.get::<RetryAfter>() {
+            Some(&RetryAfter::Delay(delay)) => Some(delay),
+            Some(&RetryAfter::DateTime(date)) =>
+                Some(SystemTime::from(date).duration_since(SystemTime::now()).unwrap_or_default()),
+            None => self.meta.as_ref().map(|meta| &meta[RETRY_AFTER_META]).and_then(|v| match *v {
+                serde_json::Value::String(ref v) => v.trim().parse().ok(),
+                ref v => v.as_u64(),
+            }).map(Duration::from_secs),
+        }
     }
 
     /// Create a byte-array service response for this error and the given status code
     pub fn to_resp_raw(&self) -> ServiceResponse<Vec<u8>> {
         let output = self.to_json_bytes().unwrap_or_else(|_| "{}".as_bytes().to_vec());
         let mut headers = Headers::new();
+        extend_headers(&mut headers, &self.headers);
         headers.set(ContentType::json());
         headers.set(ContentLength(output.len() as u64));
         ServiceResponse {
             version: HttpVersion::default(),
-            headers: headers,
+            headers,
             status: self.status,
             output
         }
@@ -265,23 +605,74 @@ impl TwirpError {
 
     /// Create a hyper response for this error and the given status code
     pub fn to_hyper_resp(&self) -> Response {
-        let body = self.to_json_bytes().unwrap_or_else(|_| "{}".as_bytes().to_vec());
-        Response::new().
-            with_status(self.status).
-            with_header(ContentType::json()).
-            with_header(ContentLength(body.len() as u64)).
-            with_body(body)
+        self.to_resp_raw().into_hyper_raw()
+    }
+
+    /// Create the error for a non-success byte-array service response
+    ///
+    /// Responses without a Twirp error body, usually sent by proxies or load balancers, become intermediary errors as
+    /// described by `TwirpError::from_intermediary`. Of the response headers, the error only keeps `Retry-After`, so
+    /// a server passing the error on doesn't send the other server's headers with it.
+    pub fn from_resp_raw(resp: &ServiceResponse<Vec<u8>>) -> TwirpError {
+        let json = if resp.status.is_redirection() { None } else {
+            serde_json::from_slice::<serde_json::Value>(&resp.output).ok().
+                filter(|json| json["code"].is_string() || json["error_type"].is_string())
+        };
+        let mut err = match json {
+            Some(json) => TwirpError::from_json(resp.status, json),
+            None => TwirpError::from_intermediary(resp.status, &resp.headers, &resp.output),
+        };
+        for name in ERROR_RESPONSE_HEADERS {
+            if let Some(raw) = resp.headers.get_raw(name) { err.headers.set_raw(*name, raw.clone()); }
+        }
+        err
+    }
+
+    /// Create the error for a response that did not come from a Twirp server
+    ///
+    /// As the Twirp spec requires, the code is chosen based on the status and the meta has `http_error_from_intermediary`,
+    /// `status_code`, and either `location` for redirects or `body` for everything else.
+    pub fn from_intermediary(status: StatusCode, headers: &Headers, body: &[u8]) -> TwirpError {
+        let error_type = if status.is_redirection() { "internal" } else {
+            match status {
+                StatusCode::BadRequest => "internal",
+                StatusCode::Unauthorized => "unauthenticated",
+                StatusCode::Forbidden => "permission_denied",
+                StatusCode::NotFound => "bad_route",
+                StatusCode::TooManyRequests | StatusCode::BadGateway | StatusCode::ServiceUnavailable |
+                    StatusCode::GatewayTimeout => "unavailable",
+                _ => "unknown",
+            }
+        };
+        let reason = status.canonical_reason().unwrap_or("");
+        let mut meta = serde_json::map::Map::new();
+        meta.insert("http_error_from_intermediary".to_string(), serde_json::Value::String("true".to_string()));
+        meta.insert("status_code".to_string(), serde_json::Value::String(status.as_u16().to_string()));
+        let msg = if status.is_redirection() {
+            let location = headers.get::<Location>().map(|v| v.to_string()).unwrap_or_default();
+            let msg = format!("unexpected HTTP status code {} {:?} received, Location={:?}",
+                status.as_u16(), reason, location);
+            meta.insert("location".to_string(), serde_json::Value::String(location));
+            msg
+        } else {
+            meta.insert("body".to_string(), serde_json::Value::String(String::from_utf8_lossy(body).into_owned()));
+            format!("Error from intermediary with HTTP status code {} {:?}", status.as_u16(), reason)
+        };
+        TwirpError::new_meta(status, error_type, &msg, Some(serde_json::Value::Object(meta)))
     }
 
     /// Create error from Serde JSON value
+    ///
+    /// The spec's `code` field is used for the error type, falling back to `error_type` if not present.
     pub fn from_json(status: StatusCode, json: serde_json::Value) -> TwirpError {
-        let error_type = json["error_type"].as_str();
+        let error_type = json["code"].as_str().or_else(|| json["error_type"].as_str());
         TwirpError {
             status,
             error_type: error_type.unwrap_or("<no code>").to_string(),
             msg: json["msg"].as_str().unwrap_or("<no message>").to_string(),
             // Put the whole thing as meta if there was no type
-            meta: if error_type.is_some() { json.get("meta").map(|v| v.clone()) } else { Some(json.clone()) },
+            meta: if error_type.is_some() { json.get("meta").cloned() } else { Some(json.clone()) },
+            headers: Headers::new(),
         }
     }
 
@@ -293,7 +684,7 @@ impl TwirpError {
     /// Create Serde JSON value from error
     pub fn to_json(&self) -> serde_json::Value {
         let mut props = serde_json::map::Map::new();
-        props.insert("error_type".to_string(), serde_json::Value::String(self.error_type.clone()));
+        props.insert("code".to_string(), serde_json::Value::String(self.error_type.clone()));
         props.insert("msg".to_string(), serde_json::Value::String(self.msg.clone()));
         if let Some(ref meta) = self.meta { props.insert("meta".to_string(), meta.clone()); }
         serde_json::Value::Object(props)
@@ -309,6 +700,48 @@ impl From<TwirpError> for ProstTwirpError {
     fn from(v: TwirpError) -> ProstTwirpError { ProstTwirpError::TwirpError(v) }
 }
 
+/// An error that can be sent back to a caller as a Twirp error
+///
+/// Servers convert handler errors with this trait, so service implementations can return their own domain errors
+/// instead of building `TwirpError`s by hand. With the `derive` feature enabled, it can be derived for enums by
+/// annotating each variant with `#[twirp(code = "...", msg = "...")]`.
+pub trait IntoTwirpError {
+    /// Convert this error into a Twirp error
+    fn into_twirp_error(self) -> TwirpError;
+
+    /// Convert this error into a `ProstTwirpError`, by default wrapping the result of `into_twirp_error`
+    fn into_prost_twirp_error(self) -> ProstTwirpError where Self: Sized {
+        ProstTwirpError::TwirpError(self.into_twirp_error())
+    }
+}
+
+impl IntoTwirpError for TwirpError {
+    fn into_twirp_error(self) -> TwirpError { self }
+}
+
+impl IntoTwirpError for ProstTwirpError {
+    fn into_twirp_error(self) -> TwirpError {
+        match self.root_err() {
+            ProstTwirpError::TwirpError(err) => err,
+            ProstTwirpError::ProstDecodeError(_) =>
+                TwirpError::new(StatusCode::BadRequest, "malformed", "the protobuf request could not be decoded"),
+            _ => TwirpError::new(StatusCode::InternalServerError, "internal", "Internal Error"),
+        }
+    }
+
+    fn into_prost_twirp_error(self) -> ProstTwirpError { self }
+}
+
+/// What the `IntoTwirpError` derive refers to, so crates using it don't need their own hyper and serde_json
+///
+/// Nothing else uses these, so they are unused where the runtime is embedded without the derive.
+#[doc(hidden)]
+#[allow(unused_imports)]
+pub mod __derive {
+    pub use hyper::StatusCode;
+    pub use serde_json::to_value;
+}
+
 /// An error that can occur during a call to a Twirp service
 #[derive(Debug)]
 pub enum ProstTwirpError {
@@ -347,15 +780,88 @@ impl ProstTwirpError {
             _ => self
         }
     }
+
+    /// A reference to this same error, or the underlying error if it is an `AfterBodyError`
+    pub fn root_err_ref(&self) -> &ProstTwirpError {
+        match *self {
+            ProstTwirpError::AfterBodyError { ref err, .. } => err.root_err_ref(),
+            _ => self
+        }
+    }
+
+    /// Whether the error happened sending or receiving over the connection instead of in the service itself
+    pub fn is_transport(&self) -> bool {
+        matches!(*self.root_err_ref(), ProstTwirpError::HyperError(hyper::Error::Timeout) |
+            ProstTwirpError::HyperError(hyper::Error::Incomplete) | ProstTwirpError::HyperError(hyper::Error::Cancel(_)) |
+            ProstTwirpError::HyperError(hyper::Error::Closed) | ProstTwirpError::HyperError(hyper::Error::Io(_)))
+    }
+
+    /// Whether the error is due to a timeout, either of the connection or a `deadline_exceeded` Twirp error
+    pub fn is_timeout(&self) -> bool {
+        match *self.root_err_ref() {
+            ProstTwirpError::HyperError(hyper::Error::Timeout) => true,
+            ProstTwirpError::HyperError(hyper::Error::Io(ref err)) => err.kind() == io::ErrorKind::TimedOut,
+            _ => self.twirp_code() == Some("deadline_exceeded"),
+        }
+    }
+
+    /// The delay after which the call may be retried, if the Twirp error has one
+    ///
+    /// See `TwirpError::retry_after`. This includes `Retry-After` headers sent with intermediary errors by proxies.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match *self.root_err_ref() {
+            ProstTwirpError::TwirpError(ref err) => err.retry_after(),
+            _ => None,
+        }
+    }
+
+    /// Whether the same call may succeed if tried again
+    ///
+    /// This is true for transport errors and `unavailable` or `resource_exhausted` Twirp errors. Intermediary errors
+    /// for 429, 502, 503, and 504 responses from proxies are `unavailable`.
+    pub fn is_retryable(&self) -> bool {
+        self.is_transport() || matches!(self.twirp_code(), Some("unavailable") | Some("resource_exhausted"))
+    }
+
+    /// The Twirp error code if the underlying error is a `TwirpError`
+    pub fn twirp_code(&self) -> Option<&str> {
+        match *self.root_err_ref() {
+            ProstTwirpError::TwirpError(ref err) => Some(&err.error_type),
+            _ => None,
+        }
+    }
+
+    /// The HTTP status of the response the error came from, or of the underlying `TwirpError`
+    pub fn http_status(&self) -> Option<StatusCode> {
+        match *self {
+            ProstTwirpError::AfterBodyError { status: Some(status), .. } => Some(status),
+            ProstTwirpError::AfterBodyError { ref err, .. } => err.http_status(),
+            ProstTwirpError::TwirpError(ref err) => Some(err.status),
+            _ => None,
+        }
+    }
+
+    /// The raw body of the response the error came from, only present for client errors
+    pub fn response_body(&self) -> Option<&[u8]> {
+        match *self {
+            ProstTwirpError::AfterBodyError { ref body, status: Some(_), .. } => Some(body),
+            ProstTwirpError::AfterBodyError { ref err, .. } => err.response_body(),
+            _ => None,
+        }
+    }
 }
 
 /// A wrapper for a hyper client
-#[derive(Debug)]
+#[derive(Clone)]
 pub struct HyperClient {
     /// The hyper client
     pub client: Client<HttpConnector, Body>,
     /// The root URL without any path attached
     pub root_url: String,
+    /// What supplies the `Authorization` header of each call, if anything
+    pub credentials: Option<Rc<dyn CredentialProvider>>,
+    /// What is told about each call, like a `Metrics` recorder, if anything
+    pub observer: Option<Arc<dyn CallObserver + Send + Sync>>,
 }
 
 impl HyperClient {
@@ -363,28 +869,826 @@ impl HyperClient {
     pub fn new(client: Client<HttpConnector, Body>, root_url: &str) -> HyperClient {
         HyperClient {
             client,
-            root_url: root_url.trim_right_matches('/').to_string(),
+            root_url: root_url.trim_end_matches('/').to_string(),
+            credentials: None,
+            observer: None,
         }
     }
 
+    /// This client with the given provider supplying the `Authorization` header of each call
+    ///
+    /// Calls that fail with `unauthenticated` are retried once with a new value from the provider. Calls that
+    /// already have an `Authorization` header are sent as they are.
+    pub fn with_credentials<C: CredentialProvider + 'static>(mut self, credentials: C) -> HyperClient {
+        self.credentials = Some(Rc::new(credentials));
+        self
+    }
+
+    /// This client telling the given observer, like a `Metrics` recorder, about each call
+    pub fn with_observer<C: CallObserver + Send + Sync + 'static>(mut self, observer: Arc<C>) -> HyperClient {
+        self.observer = Some(observer);
+        self
+    }
+
     /// Invoke the given request for the given path and return a boxed future result
     pub fn go<I, O>(&self, path: &str, req: ServiceRequest<I>) -> PTRes<O>
             where I: Message + Default + 'static, O: Message + Default + 'static {
+        self.instrument(path, || self.go_unobserved(path, req))
+    }
+
+    /// Make the call, telling the observer if any and running it in a span if tracing is enabled
+    fn instrument<O, F>(&self, path: &str, call: F) -> PTRes<O>
+            where O: Message + Default + 'static, F: FnOnce() -> PTRes<O> {
+        let observed = self.observer.as_ref().map(|v| ObservedCall::start(v, path));
+        #[cfg(feature = "tracing")]
+        let span = call_span(false, path);
+        #[cfg(feature = "tracing")]
+        let resp = span.in_scope(call);
+        #[cfg(not(feature = "tracing"))]
+        let resp = call();
+        #[cfg(feature = "tracing")]
+        let record_span = span.clone();
+        let resp = resp.then(move |res| {
+            let code = res.as_ref().err().map(client_error_code);
+            #[cfg(feature = "tracing")]
+            record_result(&record_span, match res {
+                Ok(ref v) => Some(v.status),
+                Err(ref err) => err.http_status(),
+            }, code);
+            if let Some(observed) = observed { observed.finish(code) }
+            res
+        });
+        #[cfg(feature = "tracing")]
+        let resp = InSpan { span, inner: resp };
+        Box::new(resp)
+    }
+
+    /// Like `go` without telling the observer or tracing
+    fn go_unobserved<I, O>(&self, path: &str, req: ServiceRequest<I>) -> PTRes<O>
+            where I: Message + Default + 'static, O: Message + Default + 'static {
         // Build the URI
-        let uri = match format!("{}/{}", self.root_url, path.trim_left_matches('/')).parse() {
+        let uri = match format!("{}/{}", self.root_url, path.trim_start_matches('/')).parse() {
             Err(err) => return Box::new(future::err(ProstTwirpError::HyperError(hyper::Error::Uri(err)))),
             Ok(v) => v,
         };
         // Build the request
-        let mut hyper_req = match req.to_hyper_proto() {
+        let mut req = match req.to_proto_raw() {
             Err(err) => return Box::new(future::err(err)),
             Ok(v) => v
         };
-        hyper_req.set_uri(uri);
-        // Run the request and map the response
-        Box::new(self.client.request(hyper_req).
+        req.uri = uri;
+        let current = CallContext::current();
+        if req.headers.get_raw("traceparent").is_none() {
+            if let Some(context) = req.trace_context.as_ref().or(current.trace_context.as_ref()) {
+                context.child().set_headers(&mut req.headers);
+            }
+        }
+        if req.headers.get_raw(REQUEST_ID_HEADER).is_none() {
+            if let Some(id) = req.request_id.clone().or(current.request_id) { req.headers.set_raw(REQUEST_ID_HEADER, id); }
+        }
+        #[cfg(feature = "tracing")]
+        {
+            let request_id = req.headers.get_raw(REQUEST_ID_HEADER).and_then(|v| v.one()).map(String::from_utf8_lossy);
+            if let Some(id) = request_id { tracing::Span::current().record("request_id", &*id); }
+        }
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("request_size", req.input.len() as u64);
+        match self.credentials {
+            Some(ref credentials) if req.headers.get_raw("Authorization").is_none() =>
+                self.go_authorized(credentials.clone(), req.uri, req.headers, req.input.into(), None),
+            _ => self.send(req.into_hyper_raw()),
+        }
+    }
+
+    /// Send the request with the provider's `Authorization`, trying once more with a new one if it was not rejected
+    /// before
+    ///
+    /// The body is shared with the retry rather than copied, since most calls never need it.
+    fn go_authorized<O>(&self, credentials: Rc<dyn CredentialProvider>, uri: Uri, headers: Headers,
+            body: bytes::Bytes, rejected: Option<String>) -> PTRes<O> where O: Message + Default + 'static {
+        let client = self.clone();
+        Box::new(credentials.authorization(rejected.as_deref()).and_then(move |authorization| {
+            let retry = if rejected.is_none() { Some((uri.clone(), headers.clone(), body.clone())) } else { None };
+            let mut hyper_req = Request::new(Method::Post, uri);
+            *hyper_req.headers_mut() = headers;
+            hyper_req.headers_mut().set(ContentLength(body.len() as u64));
+            hyper_req.headers_mut().set_raw("Authorization", authorization.clone());
+            hyper_req.set_body(body);
+            client.send(hyper_req).or_else(move |err| match retry {
+                Some((uri, headers, body)) if err.twirp_code() == Some("unauthenticated") =>
+                    client.go_authorized(credentials, uri, headers, body, Some(authorization)),
+                _ => Box::new(future::err(err)),
+            })
+        }))
+    }
+
+    /// Run the request and map the response
+    fn send<O: Message + Default + 'static>(&self, req: Request) -> PTRes<O> {
+        Box::new(self.client.request(req).
             map_err(ProstTwirpError::HyperError).
-            and_then(ServiceResponse::from_hyper_proto))
+            and_then(response_chunks).
+            and_then(|v| {
+                #[cfg(feature = "tracing")]
+                tracing::Span::current().record("response_size", v.output.iter().map(|v| v.len() as u64).sum::<u64>());
+                response_from_chunks(v)
+            }))
+    }
+
+    /// Invoke the given request like `go`, failing with a `hyper::Error::Timeout` if it takes longer than the timeout
+    ///
+    /// The timer runs on the hyper client's reactor, so the client must have been created with a `Handle`.
+    #[allow(deprecated)]
+    pub fn go_with_timeout<I, O>(&self, path: &str, req: ServiceRequest<I>, timeout: Duration) -> PTRes<O>
+            where I: Message + Default + 'static, O: Message + Default + 'static {
+        let timer = match Timeout::new(timeout, self.client.handle()) {
+            Err(err) => return Box::new(future::err(ProstTwirpError::HyperError(hyper::Error::Io(err)))),
+            Ok(v) => v,
+        };
+        self.instrument(path, || Box::new(self.go_unobserved(path, req).select2(timer).then(|res| match res {
+            Ok(Either::A((resp, _))) => Ok(resp),
+            Ok(Either::B(_)) => Err(ProstTwirpError::HyperError(hyper::Error::Timeout)),
+            Err(Either::A((err, _))) => Err(err),
+            Err(Either::B((err, _))) => Err(ProstTwirpError::HyperError(hyper::Error::Io(err))),
+        })))
+    }
+}
+
+impl fmt::Debug for HyperClient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HyperClient").field("client", &self.client).field("root_url", &self.root_url).
+            field("credentials", &self.credentials.as_ref().map(|_| "..")).
+            field("observer", &self.observer.as_ref().map(|_| "..")).finish()
+    }
+}
+
+/// The Twirp code of a client error, or `deadline_exceeded` for timeouts, `unavailable` for other transport errors, and
+/// `internal` for anything else
+fn client_error_code(err: &ProstTwirpError) -> &str {
+    err.twirp_code().unwrap_or(
+        if err.is_timeout() { "deadline_exceeded" } else if err.is_transport() { "unavailable" } else { "internal" })
+}
+
+/// The service and method of a Twirp path like `/twirp/pkg.Service/Method`, if it has them
+pub fn split_path(path: &str) -> Option<(&str, &str)> {
+    let mut parts = path.rsplitn(3, '/');
+    match (parts.next(), parts.next()) {
+        (Some(method), Some(service)) if !method.is_empty() && !service.is_empty() => Some((service, method)),
+        _ => None,
+    }
+}
+
+/// A span for a call to or from a server, with fields to record as it goes
+#[cfg(feature = "tracing")]
+fn call_span(server: bool, path: &str) -> tracing::Span {
+    use tracing::field::Empty;
+    let (service, method) = split_path(path).unwrap_or(("unknown", "unknown"));
+    if server {
+        tracing::info_span!("twirp_server", service, method, status = Empty, error_code = Empty,
+            request_size = Empty, response_size = Empty, request_id = Empty)
+    } else {
+        tracing::info_span!("twirp_client", service, method, status = Empty, error_code = Empty,
+            request_size = Empty, response_size = Empty, request_id = Empty)
+    }
+}
+
+/// Record how a call ended on its span
+#[cfg(feature = "tracing")]
+fn record_result(span: &tracing::Span, status: Option<StatusCode>, code: Option<&str>) {
+    if let Some(status) = status { span.record("status", u16::from(status)); }
+    if let Some(code) = code { span.record("error_code", code); }
+}
+
+/// A future polled in a span
+#[cfg(feature = "tracing")]
+struct InSpan<F> {
+    span: tracing::Span,
+    inner: F,
+}
+
+#[cfg(feature = "tracing")]
+impl<F: Future> Future for InSpan<F> {
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<F::Item, F::Error> {
+        let _enter = self.span.enter();
+        self.inner.poll()
+    }
+}
+
+/// A boxed future `Authorization` header value
+pub type FutAuthorization = Box<dyn Future<Item=String, Error=ProstTwirpError>>;
+
+/// Supplies the `Authorization` header for client calls
+pub trait CredentialProvider {
+    /// The `Authorization` header value for a call
+    ///
+    /// `rejected` is a value that a call just failed with as `unauthenticated`, which should be replaced by a new one
+    /// rather than returned again.
+    fn authorization(&self, rejected: Option<&str>) -> FutAuthorization;
+}
+
+/// An `Authorization` header value and when it expires
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Credential {
+    /// The full header value, like `Bearer <token>`
+    pub authorization: String,
+    /// When the value stops being accepted, or `None` if it doesn't
+    pub expires: Option<Instant>,
+}
+
+impl Credential {
+    /// A bearer token that expires after the given time from now, if ever
+    pub fn bearer(token: &str, expires_in: Option<Duration>) -> Credential {
+        Credential { authorization: format!("Bearer {}", token), expires: expires_in.map(|v| Instant::now() + v) }
+    }
+}
+
+/// A boxed future `Credential`
+pub type FutCredential = Box<dyn Future<Item=Credential, Error=ProstTwirpError>>;
+
+/// A `CredentialProvider` that caches credentials from a fetch function, like one calling a token endpoint
+///
+/// A new credential is fetched when the cached one is about to expire or was rejected. Calls waiting at the same time
+/// share one fetch. Fetch errors other than Twirp errors are given to the calls as `unavailable` Twirp errors.
+pub struct CachedCredentials {
+    fetch: Box<dyn Fn() -> FutCredential>,
+    /// How long before a credential expires it is replaced. Default is 30 seconds.
+    pub refresh_before: Duration,
+    state: Rc<RefCell<CredentialState>>,
+}
+
+#[derive(Default)]
+struct CredentialState {
+    current: Option<Credential>,
+    /// The number of the fetch in progress, and the fetch
+    pending: Option<(u64, Shared<FutCredential>)>,
+    fetches: u64,
+}
+
+impl CachedCredentials {
+    /// Cache the credentials from the fetch function, which is called whenever a new one is needed
+    pub fn new<F: Fn() -> FutCredential + 'static>(fetch: F) -> CachedCredentials {
+        CachedCredentials { fetch: Box::new(fetch), refresh_before: Duration::from_secs(30), state: Default::default() }
+    }
+
+    /// The cached credential, if any
+    pub fn current(&self) -> Option<Credential> { self.state.borrow().current.clone() }
+}
+
+impl CredentialProvider for CachedCredentials {
+    fn authorization(&self, rejected: Option<&str>) -> FutAuthorization {
+        let mut state = self.state.borrow_mut();
+        if let Some(ref current) = state.current {
+            let fresh = current.expires.is_none_or(|expires| Instant::now() + self.refresh_before < expires);
+            if fresh && rejected != Some(current.authorization.as_str()) {
+                return Box::new(future::ok(current.authorization.clone()));
+            }
+        }
+        let (id, fetch) = match state.pending {
+            Some((id, ref fetch)) => (id, fetch.clone()),
+            None => {
+                let (id, fetch) = (state.fetches + 1, (self.fetch)().shared());
+                state.fetches = id;
+                state.pending = Some((id, fetch.clone()));
+                (id, fetch)
+            },
+        };
+        let state = self.state.clone();
+        Box::new(fetch.then(move |res| {
+            let mut state = state.borrow_mut();
+            // Only the first call to see the fetch finish updates the cache, later ones may be after a newer fetch
+            let latest = state.pending.as_ref().is_some_and(|&(pending, _)| pending == id);
+            match res {
+                Ok(credential) => {
+                    if latest {
+                        state.current = Some((*credential).clone());
+                        state.pending = None;
+                    }
+                    Ok(credential.authorization.clone())
+                },
+                Err(err) => {
+                    if latest { state.pending = None; }
+                    Err(match *err.root_err_ref() {
+                        ProstTwirpError::TwirpError(ref err) => ProstTwirpError::TwirpError(err.clone()),
+                        ref err => TwirpError::from_code("unavailable",
+                            &format!("fetching credentials failed: {:?}", err)).into(),
+                    })
+                },
+            }
+        }))
+    }
+}
+
+/// A client call that can be given headers and a timeout before it is sent
+///
+/// Generated clients return these from their `call_` methods. The call is sent by `send` or when it is first polled as
+/// a future, so it can be used just like the boxed future from the service trait. Changing a call after it was sent,
+/// or polling it after it finished, fails it with an `internal` error.
+pub struct Call<I, O> {
+    client: HyperClient,
+    path: String,
+    /// The request, taken once the call is sent
+    req: Option<ServiceRequest<I>>,
+    timeout: Option<Duration>,
+    /// The response once sent, taken again once it finished
+    sent: Option<PTRes<O>>,
+}
+
+impl<I: Message + Default + 'static, O: Message + Default + 'static> Call<I, O> {
+    /// Create a call of the request for the given path
+    pub fn new(client: &HyperClient, path: &str, req: ServiceRequest<I>) -> Call<I, O> {
+        Call { client: client.clone(), path: path.to_string(), req: Some(req), timeout: None, sent: None }
+    }
+
+    /// The request that will be sent, or `None` if it already was
+    pub fn request_mut(&mut self) -> Option<&mut ServiceRequest<I>> { self.req.as_mut() }
+
+    /// Set a typed header on the request, replacing any existing one
+    pub fn header<H: Header>(self, header: H) -> Call<I, O> { self.change(|req| req.headers.set(header)) }
+
+    /// Set a raw header on the request, replacing any existing one
+    pub fn header_raw<K: Into<Cow<'static, str>>, V: Into<Raw>>(self, name: K, value: V) -> Call<I, O> {
+        self.change(|req| req.headers.set_raw(name, value))
+    }
+
+    /// Fail the call with a `hyper::Error::Timeout` if the response isn't received within the timeout
+    pub fn timeout(mut self, timeout: Duration) -> Call<I, O> {
+        self.timeout = Some(timeout);
+        self.change(|_| ())
+    }
+
+    /// Send the call and return a boxed future result
+    ///
+    /// If it was already sent by polling, this is the response still to come.
+    pub fn send(mut self) -> PTRes<O> {
+        if self.req.is_some() { return self.start(); }
+        self.sent.take().unwrap_or_else(|| Box::new(future::err(call_misused("the call already finished"))))
+    }
+
+    /// Change the request if it hasn't been sent, otherwise fail the call
+    fn change<F: FnOnce(&mut ServiceRequest<I>)>(mut self, f: F) -> Call<I, O> {
+        match self.req {
+            Some(ref mut req) => f(req),
+            None => self.sent = Some(Box::new(future::err(call_misused("the call was changed after it was sent")))),
+        }
+        self
+    }
+
+    fn start(&mut self) -> PTRes<O> {
+        let req = self.req.take().expect("call not sent");
+        match self.timeout {
+            Some(timeout) => self.client.go_with_timeout(&self.path, req, timeout),
+            None => self.client.go(&self.path, req),
+        }
+    }
+}
+
+/// The error for a call used after it was sent or finished
+fn call_misused(msg: &str) -> ProstTwirpError { TwirpError::from_code("internal", msg).into() }
+
+impl<I: Message + Default + 'static, O: Message + Default + 'static> Future for Call<I, O> {
+    type Item = ServiceResponse<O>;
+    type Error = ProstTwirpError;
+
+    fn poll(&mut self) -> Poll<ServiceResponse<O>, ProstTwirpError> {
+        if self.req.is_some() { self.sent = Some(self.start()); }
+        let res = match self.sent {
+            Some(ref mut sent) => sent.poll(),
+            None => return Err(call_misused("the call already finished")),
+        };
+        if res.as_ref().map_or(true, |v| v.is_ready()) { self.sent = None; }
+        res
+    }
+}
+
+/// A description of a generated service method
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MethodDescriptor {
+    /// The fully qualified protobuf service name, like `twitch.twirp.example.Haberdasher`
+    pub service: &'static str,
+    /// The protobuf method name, like `MakeHat`
+    pub method: &'static str,
+    /// The path the method is served at
+    pub path: &'static str,
+    /// Who may call the method, from its `prost_twirp.policy` option, if any
+    pub policy: Option<MethodPolicy>,
+}
+
+/// Who may call a method, checked by generated servers before the handler is called
+///
+/// Policies only get into the generated code if the build script calls `TwirpServiceGenerator::read_policies`. The
+/// generator panics when protos import `prost_twirp/options.proto` without it, so methods aren't silently unchecked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MethodPolicy {
+    /// Scopes the principal must all have
+    pub scopes: &'static [&'static str],
+    /// Roles the principal must have at least one of, from its `roles` claim, if any are given
+    pub roles: &'static [&'static str],
+}
+
+impl MethodPolicy {
+    /// Check the principal may call the method
+    ///
+    /// Without a principal this fails with `unauthenticated`, and with one missing a scope or role it fails with
+    /// `permission_denied`.
+    pub fn check(&self, principal: Option<&Principal>) -> Result<(), TwirpError> {
+        let principal = principal.ok_or_else(|| TwirpError::from_code("unauthenticated", "missing credentials"))?;
+        if let Some(scope) = self.scopes.iter().find(|&&scope| !principal.scopes.iter().any(|v| v == scope)) {
+            return Err(TwirpError::from_code("permission_denied", &format!("missing scope {:?}", scope)));
+        }
+        let roles = principal.claims.get("roles").and_then(|v| v.as_array()).map_or(&[][..], |v| &v[..]);
+        if !self.roles.is_empty() && !self.roles.iter().any(|&role| roles.iter().any(|v| v.as_str() == Some(role))) {
+            return Err(TwirpError::from_code("permission_denied", "missing role"));
+        }
+        Ok(())
+    }
+}
+
+/// A W3C trace context, propagated between clients and servers in the `traceparent` and `tracestate` headers
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TraceContext {
+    /// The ID of the whole trace, never zero
+    pub trace_id: u128,
+    /// The ID of the span calls are made from, sent to them as their parent, never zero
+    pub span_id: u64,
+    /// The trace flags, like `TraceContext::SAMPLED`
+    pub flags: u8,
+    /// The vendor-specific `tracestate` header value, passed along as it is
+    pub state: Option<String>,
+}
+
+impl TraceContext {
+    /// The flag set when the caller may have recorded the trace
+    pub const SAMPLED: u8 = 0x01;
+
+    /// A new sampled trace with random IDs
+    pub fn start() -> TraceContext {
+        TraceContext { trace_id: u128::from(random_id()) << 64 | u128::from(random_id()), span_id: random_id(),
+            flags: TraceContext::SAMPLED, state: None }
+    }
+
+    /// Parse `traceparent` and `tracestate` header values, or return `None` if the `traceparent` is invalid
+    ///
+    /// Fields added after the flags by later versions are ignored.
+    pub fn parse(traceparent: &str, tracestate: Option<&str>) -> Option<TraceContext> {
+        fn hex(v: &str, len: usize) -> Option<&str> {
+            Some(v).filter(|v| v.len() == len && v.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b)))
+        }
+        let mut parts = traceparent.trim().splitn(5, '-');
+        let version = hex(parts.next()?, 2)?;
+        let trace_id = u128::from_str_radix(hex(parts.next()?, 32)?, 16).ok()?;
+        let span_id = u64::from_str_radix(hex(parts.next()?, 16)?, 16).ok()?;
+        let flags = u8::from_str_radix(hex(parts.next()?, 2)?, 16).ok()?;
+        if version == "ff" || (version == "00" && parts.next().is_some()) || trace_id == 0 || span_id == 0 {
+            return None;
+        }
+        let state = tracestate.map(str::trim).filter(|v| !v.is_empty()).map(String::from);
+        Some(TraceContext { trace_id, span_id, flags, state })
+    }
+
+    /// The trace context in the headers, if any and valid
+    pub fn from_headers(headers: &Headers) -> Option<TraceContext> {
+        let traceparent = String::from_utf8_lossy(headers.get_raw("traceparent")?.one()?);
+        let tracestate = headers.get_raw("tracestate").
+            map(|v| v.iter().map(String::from_utf8_lossy).collect::<Vec<_>>().join(","));
+        TraceContext::parse(&traceparent, tracestate.as_deref())
+    }
+
+    /// The `traceparent` header value
+    pub fn traceparent(&self) -> String {
+        format!("00-{:032x}-{:016x}-{:02x}", self.trace_id, self.span_id, self.flags)
+    }
+
+    /// Set the `traceparent` and `tracestate` headers
+    pub fn set_headers(&self, headers: &mut Headers) {
+        headers.set_raw("traceparent", self.traceparent());
+        match self.state {
+            Some(ref state) => headers.set_raw("tracestate", state.clone()),
+            None => headers.remove_raw("tracestate"),
+        }
+    }
+
+    /// The same trace with a new span ID, like for a call made from this span
+    pub fn child(&self) -> TraceContext { TraceContext { span_id: random_id(), ..self.clone() } }
+
+    /// Whether the caller may have recorded the trace
+    pub fn sampled(&self) -> bool { self.flags & TraceContext::SAMPLED != 0 }
+
+    /// The trace context of the call being handled on this thread, if any
+    ///
+    /// `HyperServer` sets it while the handler runs so clients called from it carry the trace on.
+    pub fn current() -> Option<TraceContext> { CallContext::current().trace_context }
+
+    /// Run the function with this as the current trace context
+    pub fn in_scope<R, F: FnOnce() -> R>(&self, f: F) -> R { self.with_current().in_scope(f) }
+
+    /// Poll the future with this as the current trace context
+    pub fn scope<F: Future>(&self, future: F) -> InCallContext<F> { self.with_current().scope(future) }
+
+    /// The current call context with this as its trace context
+    fn with_current(&self) -> CallContext {
+        CallContext { trace_context: Some(self.clone()), ..CallContext::current() }
+    }
+}
+
+/// What calls made while handling a call carry on from it
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CallContext {
+    /// The trace the call is part of, if any
+    pub trace_context: Option<TraceContext>,
+    /// The ID of the request being handled, if any
+    pub request_id: Option<String>,
+}
+
+thread_local!(static CURRENT_CALL_CONTEXT: RefCell<CallContext> =
+    const { RefCell::new(CallContext { trace_context: None, request_id: None }) });
+
+impl CallContext {
+    /// The context of the call being handled on this thread
+    ///
+    /// `HyperServer` sets it while the handler runs so clients called from it carry it on.
+    pub fn current() -> CallContext { CURRENT_CALL_CONTEXT.with(|v| v.borrow().clone()) }
+
+    /// Run the function with this as the current context
+    pub fn in_scope<R, F: FnOnce() -> R>(&self, f: F) -> R {
+        let _previous = PreviousCallContext(CURRENT_CALL_CONTEXT.with(|v| v.replace(self.clone())));
+        f()
+    }
+
+    /// Poll the future with this as the current context
+    pub fn scope<F: Future>(&self, future: F) -> InCallContext<F> {
+        InCallContext { context: self.clone(), inner: future }
+    }
+}
+
+/// Restores the call context it holds as the current one on drop
+struct PreviousCallContext(CallContext);
+
+impl Drop for PreviousCallContext {
+    fn drop(&mut self) {
+        let previous = ::std::mem::take(&mut self.0);
+        CURRENT_CALL_CONTEXT.with(|v| *v.borrow_mut() = previous);
+    }
+}
+
+/// A future polled with a current call context, from `CallContext::scope` or `TraceContext::scope`
+#[derive(Debug)]
+pub struct InCallContext<F> {
+    context: CallContext,
+    inner: F,
+}
+
+impl<F: Future> Future for InCallContext<F> {
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<F::Item, F::Error> {
+        let inner = &mut self.inner;
+        self.context.in_scope(|| inner.poll())
+    }
+}
+
+/// How a `HyperServer` handles the `X-Request-Id` header
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RequestIds {
+    /// Request IDs are ignored
+    #[default]
+    Ignore,
+    /// IDs sent by callers are put in `req.request_id` and echoed in the response
+    Echo,
+    /// Like `Echo`, but calls without a valid ID get a random one
+    Generate,
+}
+
+/// The header request IDs are sent in
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// The meta key the request ID is put in for `internal` errors
+pub const REQUEST_ID_META: &str = "request_id";
+
+/// The longest request ID accepted from callers
+pub const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Whether the request ID sent by a caller is short and only visible ASCII
+fn valid_request_id(id: &[u8]) -> bool {
+    !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.iter().all(|b| (0x21..=0x7e).contains(b))
+}
+
+/// A random nonzero ID, from the random keys of the std hasher, the time, and a count
+fn random_id() -> u64 {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    loop {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_usize(COUNT.fetch_add(1, Ordering::Relaxed));
+        if let Ok(v) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) { hasher.write_u128(v.as_nanos()); }
+        let id = hasher.finish();
+        if id != 0 { return id; }
+    }
+}
+
+/// Counts of the calls a `HyperServer` and its clones are handling
+#[derive(Debug, Default)]
+pub struct InFlight {
+    counts: Mutex<InFlightCounts>,
+    rejected: AtomicUsize,
+}
+
+#[derive(Debug, Default)]
+struct InFlightCounts {
+    total: usize,
+    /// Only paths with calls in flight are kept
+    methods: HashMap<String, usize>,
+}
+
+impl InFlight {
+    /// The number of calls being handled
+    pub fn total(&self) -> usize { self.lock().total }
+
+    /// The number of calls being handled for the given method path
+    pub fn method(&self, path: &str) -> usize { self.lock().methods.get(path).cloned().unwrap_or(0) }
+
+    /// The number of calls rejected so far for being over a limit
+    pub fn rejected(&self) -> usize { self.rejected.load(Ordering::Relaxed) }
+
+    fn lock(&self) -> ::std::sync::MutexGuard<'_, InFlightCounts> {
+        self.counts.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Count a call for the path unless it would go over a limit, returning a guard that stops counting it on drop
+    fn start(in_flight: &Arc<InFlight>, path: &str, max: Option<usize>, method_max: Option<usize>)
+            -> Option<InFlightGuard> {
+        let mut counts = in_flight.lock();
+        let method_count = counts.methods.get(path).cloned().unwrap_or(0);
+        if max.is_some_and(|max| counts.total >= max) || method_max.is_some_and(|max| method_count >= max) {
+            in_flight.rejected.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+        counts.total += 1;
+        counts.methods.insert(path.to_string(), method_count + 1);
+        Some(InFlightGuard { in_flight: in_flight.clone(), path: path.to_string() })
+    }
+}
+
+struct InFlightGuard {
+    in_flight: Arc<InFlight>,
+    path: String,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        let mut counts = self.in_flight.lock();
+        counts.total -= 1;
+        let remaining = counts.methods.get_mut(&self.path).map(|count| { *count -= 1; *count });
+        if remaining == Some(0) { counts.methods.remove(&self.path); }
+    }
+}
+
+/// Something told about each call a `HyperServer` or `HyperClient` makes, like the `Metrics` recorder
+pub trait CallObserver {
+    /// A call for the path started
+    fn started(&self, path: &str);
+
+    /// A call for the path finished after the elapsed time, with the Twirp error code if it failed
+    ///
+    /// Calls dropped before they finish, like when the caller goes away, are reported as `canceled`.
+    fn finished(&self, path: &str, elapsed: Duration, code: Option<&str>);
+}
+
+/// A call being reported to an observer, reported as `canceled` if dropped before it finishes
+struct ObservedCall {
+    observer: Arc<dyn CallObserver + Send + Sync>,
+    path: String,
+    start: Instant,
+    done: bool,
+}
+
+impl ObservedCall {
+    fn start(observer: &Arc<dyn CallObserver + Send + Sync>, path: &str) -> ObservedCall {
+        observer.started(path);
+        ObservedCall { observer: observer.clone(), path: path.to_string(), start: Instant::now(), done: false }
+    }
+
+    fn finish(mut self, code: Option<&str>) {
+        self.done = true;
+        self.observer.finished(&self.path, self.start.elapsed(), code);
+    }
+}
+
+impl Drop for ObservedCall {
+    fn drop(&mut self) {
+        if !self.done { self.observer.finished(&self.path, self.start.elapsed(), Some("canceled")); }
+    }
+}
+
+/// What a `HyperServer` served for one call, for access logs
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccessRecord {
+    /// When the call started
+    pub timestamp: SystemTime,
+    /// The address the call came from, if known
+    pub remote_addr: Option<SocketAddr>,
+    /// The service called, or `unknown` if the path has none
+    pub service: String,
+    /// The method called, or `unknown` if the path has none
+    pub method: String,
+    /// The HTTP status answered
+    pub status: StatusCode,
+    /// The Twirp error code if the call failed
+    pub code: Option<String>,
+    /// The request body size in bytes, or 0 if the call failed before it was read
+    pub request_size: u64,
+    /// The response body size in bytes
+    pub response_size: u64,
+    /// How long the call took until its response was ready
+    pub duration: Duration,
+    /// The ID of the request, if `HyperServer::request_ids` gave it one
+    pub request_id: Option<String>,
+}
+
+/// Something given a record of each call a `HyperServer` answers, like the `AccessLogger`
+pub trait AccessLog {
+    /// Log the call
+    fn log(&self, record: &AccessRecord);
+}
+
+/// Cross-origin settings letting browsers call a `HyperServer` from other origins
+///
+/// Preflight `OPTIONS` requests are answered directly, with a `permission_denied` error if what they ask for isn't
+/// allowed. Responses to calls from allowed origins, including errors, get the CORS headers. Calls from other origins
+/// are handled as if there were no CORS settings.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Cors {
+    /// The origins allowed to call, like `https://example.com`, or `*` for any
+    pub allowed_origins: Vec<String>,
+    /// The request headers browsers may send besides `Content-Type`, which is always allowed, like `Authorization`
+    pub allowed_headers: Vec<String>,
+    /// The response headers browsers may read besides the simple ones, like `Retry-After`
+    pub exposed_headers: Vec<String>,
+    /// How long browsers may cache preflight answers, or `None` for their default
+    pub max_age: Option<Duration>,
+    /// Whether browsers may send cookies and other credentials. Only origins listed in `allowed_origins` get them, and
+    /// are sent back instead of `*`. Origins only allowed by `*` are answered without credentials.
+    pub allow_credentials: bool,
+}
+
+impl Cors {
+    /// Allow calls from the given origins
+    pub fn new(allowed_origins: &[&str]) -> Cors {
+        Cors { allowed_origins: allowed_origins.iter().map(|v| v.to_string()).collect(), ..Default::default() }
+    }
+
+    /// Whether the origin may call
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        self.allowed_origins.iter().any(|v| v == "*" || v == origin)
+    }
+
+    /// Whether the request header may be sent, ignoring case
+    pub fn allows_header(&self, name: &str) -> bool {
+        name.eq_ignore_ascii_case("Content-Type") || self.allowed_headers.iter().any(|v| v.eq_ignore_ascii_case(name))
+    }
+
+    /// The answer to a preflight request from the origin, or a `permission_denied` error if what it asks for isn't
+    /// allowed
+    pub fn preflight(&self, origin: &str, req_headers: &Headers) -> Response {
+        let header = |name| req_headers.get_raw(name).and_then(|v| v.one()).map(String::from_utf8_lossy);
+        let method = header("Access-Control-Request-Method").unwrap_or_default();
+        let requested = header("Access-Control-Request-Headers").unwrap_or_default();
+        let denied = requested.split(',').map(str::trim).find(|v| !v.is_empty() && !self.allows_header(v));
+        let err = if !self.allows_origin(origin) {
+            Some(format!("origin {:?} is not allowed", origin))
+        } else if method != "POST" {
+            Some(format!("method {:?} is not allowed (only POST is allowed)", method))
+        } else {
+            denied.map(|v| format!("header {:?} is not allowed", v))
+        };
+        if let Some(msg) = err { return TwirpError::from_code("permission_denied", &msg).to_hyper_resp(); }
+        let mut resp = Response::new().with_status(StatusCode::NoContent);
+        self.add_headers(origin, resp.headers_mut());
+        resp.headers_mut().set_raw("Access-Control-Allow-Methods", "POST");
+        if !requested.trim().is_empty() {
+            resp.headers_mut().set_raw("Access-Control-Allow-Headers", requested.trim().to_string());
+        }
+        if let Some(max_age) = self.max_age {
+            resp.headers_mut().set_raw("Access-Control-Max-Age", max_age.as_secs().to_string());
+        }
+        resp
+    }
+
+    /// Add the headers for a response to the origin, if it is allowed
+    pub fn add_headers(&self, origin: &str, headers: &mut Headers) {
+        if !self.allows_origin(origin) { return; }
+        let credentials = self.allow_credentials && self.allowed_origins.iter().any(|v| v == origin);
+        if credentials || !self.allowed_origins.iter().any(|v| v == "*") {
+            headers.set_raw("Access-Control-Allow-Origin", origin.to_string());
+            headers.append_raw("Vary", "Origin");
+        } else {
+            headers.set_raw("Access-Control-Allow-Origin", "*");
+        }
+        if credentials { headers.set_raw("Access-Control-Allow-Credentials", "true"); }
+        if !self.exposed_headers.is_empty() {
+            headers.set_raw("Access-Control-Expose-Headers", self.exposed_headers.join(", "));
+        }
     }
 }
 
@@ -399,45 +1703,198 @@ pub struct HyperServer<T: 'static + HyperService> {
     /// The `Arc` version of the service
     /// 
     /// Needed because of [hyper Service lifetimes](https://github.com/tokio-rs/tokio-service/issues/9)
-    pub service: Arc<T>
+    pub service: Arc<T>,
+    /// The largest request body in bytes that will be read, or `None` for no limit
+    ///
+    /// Larger requests fail with a `malformed` error. Default is `None`.
+    pub max_body_size: Option<u64>,
+    /// The most calls that will be handled at once, or `None` for no limit
+    ///
+    /// Calls over the limit fail right away with an `unavailable` error instead of waiting. Default is `None`.
+    pub max_in_flight: Option<usize>,
+    /// The most calls handled at once for each method, keyed by path like `Haberdasher::MAKE_HAT_METHOD.path`
+    ///
+    /// These work like `max_in_flight`. Default is empty.
+    pub method_max_in_flight: HashMap<String, usize>,
+    /// The calls being handled, shared with clones of this server
+    ///
+    /// Hyper creates a service per connection, so the server should be cloned for the limits to span connections.
+    pub in_flight: Arc<InFlight>,
+    /// The settings for browser calls from other origins, or `None` to not allow them. Default is `None`.
+    pub cors: Option<Cors>,
+    /// What is told about each call, like a `Metrics` recorder, or `None`. Default is `None`.
+    ///
+    /// CORS preflights aren't calls and aren't reported.
+    pub observer: Option<Arc<dyn CallObserver + Send + Sync>>,
+    /// How the `X-Request-Id` header is handled. Default is `RequestIds::Ignore`.
+    pub request_ids: RequestIds,
+    /// What is given a record of each call answered, like an `AccessLogger`, or `None`. Default is `None`.
+    ///
+    /// CORS preflights aren't calls and aren't logged, nor are calls dropped before they are answered.
+    pub access_log: Option<Arc<dyn AccessLog + Send + Sync>>,
 }
 
 impl<T: 'static + HyperService> HyperServer<T> {
     /// Create a new service wrapper for the given impl
-    pub fn new(service: T) -> HyperServer<T> { HyperServer { service: Arc::new(service) } }
+    pub fn new(service: T) -> HyperServer<T> {
+        HyperServer { service: Arc::new(service), max_body_size: None, max_in_flight: None,
+            method_max_in_flight: HashMap::new(), in_flight: Default::default(), cors: None, observer: None,
+            request_ids: RequestIds::Ignore, access_log: None }
+    }
+}
+
+impl<T: 'static + HyperService> Clone for HyperServer<T> {
+    fn clone(&self) -> HyperServer<T> {
+        HyperServer { service: self.service.clone(), max_body_size: self.max_body_size,
+            max_in_flight: self.max_in_flight, method_max_in_flight: self.method_max_in_flight.clone(),
+            in_flight: self.in_flight.clone(), cors: self.cors.clone(), observer: self.observer.clone(),
+            request_ids: self.request_ids, access_log: self.access_log.clone() }
+    }
 }
 
 impl<T: 'static + HyperService> Service for HyperServer<T> {
     type Request = Request;
     type Response = Response;
     type Error = hyper::Error;
-    type Future = Box<Future<Item = Self::Response, Error = Self::Error>>;
+    type Future = Box<dyn Future<Item = Self::Response, Error = Self::Error>>;
 
     fn call(&self, req: Request) -> Self::Future {
+        let origin = req.headers().get_raw("Origin").and_then(|v| v.one()).
+            map(|v| String::from_utf8_lossy(v).into_owned());
+        match (self.cors.clone(), origin) {
+            (Some(cors), Some(origin)) => {
+                let preflight = req.headers().get_raw("Access-Control-Request-Method").is_some();
+                if req.method() == &Method::Options && preflight {
+                    return Box::new(future::ok(cors.preflight(&origin, req.headers())));
+                }
+                Box::new(self.call_observed(req).map(move |mut resp| {
+                    cors.add_headers(&origin, resp.headers_mut());
+                    resp
+                }))
+            },
+            _ => self.call_observed(req),
+        }
+    }
+}
+
+impl<T: 'static + HyperService> HyperServer<T> {
+    /// Handle the request as a Twirp call, telling the observer and access log if any and running it in a span if
+    /// tracing is enabled
+    #[allow(deprecated)]
+    fn call_observed(&self, req: Request) -> Box<dyn Future<Item = Response, Error = hyper::Error>> {
+        let observed = self.observer.as_ref().map(|v| ObservedCall::start(v, req.path()));
+        let request_id = self.request_id(&req);
+        let (timestamp, start) = (SystemTime::now(), Instant::now());
+        let (access_log, remote_addr, path) = (self.access_log.clone(), req.remote_addr(), req.path().to_string());
+        let request_size = Rc::new(Cell::new(0));
+        #[cfg(feature = "tracing")]
+        let span = call_span(true, req.path());
+        #[cfg(feature = "tracing")]
+        {
+            if let Some(ref id) = request_id { span.record("request_id", id.as_str()); }
+        }
+        #[cfg(feature = "tracing")]
+        let record_span = span.clone();
+        let resp = self.call_twirp(req, request_id.clone(), request_size.clone()).map(move |mut resp| {
+            if let Some(ref id) = request_id { resp.headers.set_raw(REQUEST_ID_HEADER, id.as_str()); }
+            let code = if resp.status.is_success() { None } else { Some(TwirpError::from_resp_raw(&resp).error_type) };
+            #[cfg(feature = "tracing")]
+            {
+                record_result(&record_span, Some(resp.status), code.as_deref());
+                record_span.record("response_size", resp.output.len() as u64);
+            }
+            if let Some(observed) = observed { observed.finish(code.as_deref()) }
+            if let Some(access_log) = access_log {
+                let (service, method) = split_path(&path).unwrap_or(("unknown", "unknown"));
+                access_log.log(&AccessRecord { timestamp, remote_addr, service: service.to_string(),
+                    method: method.to_string(), status: resp.status, code, request_size: request_size.get(),
+                    response_size: resp.output.len() as u64, duration: start.elapsed(), request_id });
+            }
+            resp.into_hyper_raw()
+        });
+        #[cfg(feature = "tracing")]
+        let resp = InSpan { span, inner: resp };
+        Box::new(resp)
+    }
+
+    /// The ID of the request, from its header or generated depending on `request_ids`
+    fn request_id(&self, req: &Request) -> Option<String> {
+        if self.request_ids == RequestIds::Ignore { return None; }
+        let sent = req.headers().get_raw(REQUEST_ID_HEADER).and_then(|v| v.one()).filter(|v| valid_request_id(v)).
+            map(|v| String::from_utf8_lossy(v).into_owned());
+        match self.request_ids {
+            RequestIds::Generate => Some(sent.unwrap_or_else(|| format!("{:016x}{:016x}", random_id(), random_id()))),
+            _ => sent,
+        }
+    }
+
+    /// Handle the request as a Twirp call with the given request ID
+    fn call_twirp(&self, req: Request, request_id: Option<String>, request_size: Rc<Cell<u64>>)
+            -> Box<dyn Future<Item = ServiceResponse<Vec<u8>>, Error = hyper::Error>> {
+        let content_type = req.headers().get_raw("Content-Type").and_then(|v| v.one()).
+            map(|v| String::from_utf8_lossy(v).into_owned()).unwrap_or_default();
+        let req_media_type = media_type(&content_type);
         if req.method() != &Method::Post {
-            Box::new(future::ok(TwirpError::new(StatusCode::MethodNotAllowed, "bad_method",
-                "Method must be POST").to_hyper_resp()))
-        } else if req.headers().get::<ContentType>().map(|v| format!("{}", v) == "application/protobuf") != Some(true) {
-            Box::new(future::ok(TwirpError::new(StatusCode::UnsupportedMediaType,
-                "bad_content_type", "Content type must be application/protobuf").to_hyper_resp()))
+            let msg = format!("unsupported method {:?} (only POST is allowed)", req.method().to_string());
+            Box::new(future::ok(TwirpError::bad_route(&msg, req.method(), req.path()).to_resp_raw()))
+        } else if !PROTOBUF_MEDIA_TYPES.contains(&req_media_type.as_str()) {
+            let msg = if req_media_type == "application/json" {
+                format!("unexpected Content-Type: {:?} (JSON is not supported, use {})",
+                    content_type, PROTOBUF_MEDIA_TYPES[0])
+            } else {
+                format!("unexpected Content-Type: {:?} (supported: {})", content_type, PROTOBUF_MEDIA_TYPES.join(", "))
+            };
+            Box::new(future::ok(TwirpError::bad_route(&msg, req.method(), req.path()).to_resp_raw()))
         } else {
+            // Shed load before reading the body
+            let method_max = self.method_max_in_flight.get(req.path()).cloned();
+            let guard = match InFlight::start(&self.in_flight, req.path(), self.max_in_flight, method_max) {
+                Some(guard) => guard,
+                None => {
+                    let msg = format!("too many calls in flight for {}", req.path());
+                    return Box::new(future::ok(TwirpError::from_code("unavailable", &msg).to_resp_raw()));
+                },
+            };
             // Ug: https://github.com/tokio-rs/tokio-service/issues/9
             let service = self.service.clone();
-            Box::new(ServiceRequest::from_hyper_raw(req).
-                and_then(move |v| service.handle(v)).
-                map(|v| v.to_hyper_raw()).
-                or_else(|err| match err.root_err() {
-                    ProstTwirpError::ProstDecodeError(_) =>
-                        Ok(TwirpError::new(StatusCode::BadRequest, "protobuf_decode_err", "Invalid protobuf body").
-                            to_hyper_resp()),
-                    ProstTwirpError::TwirpError(err) =>
-                        Ok(err.to_hyper_resp()),
+            let response_headers = ResponseHeaders::default();
+            let handler_headers = response_headers.clone();
+            let error_request_id = request_id.clone();
+            Box::new(ServiceRequest::from_hyper_raw_limited(req, self.max_body_size).
+                and_then(move |mut v| {
+                    #[cfg(feature = "tracing")]
+                    tracing::Span::current().record("request_size", v.input.len() as u64);
+                    request_size.set(v.input.len() as u64);
+                    v.response_headers = handler_headers;
+                    v.trace_context = TraceContext::from_headers(&v.headers).map(|v| v.child());
+                    v.request_id = request_id;
+                    let context = CallContext { trace_context: v.trace_context.clone(), request_id: v.request_id.clone() };
+                    context.scope(context.in_scope(|| service.handle(v)))
+                }).
+                map(move |mut v| {
+                    // Answer protobuf with the same media type the request used
+                    let resp_media_type = v.headers.get_raw("Content-Type").and_then(|v| v.one()).
+                        map(|v| media_type(&String::from_utf8_lossy(v)));
+                    if resp_media_type.as_ref().is_some_and(|v| PROTOBUF_MEDIA_TYPES.contains(&v.as_str())) {
+                        v.headers.set_raw("Content-Type", req_media_type);
+                    }
+                    v
+                }).
+                or_else(move |err| match err.root_err() {
                     // Just propagate hyper errors
-                    ProstTwirpError::HyperError(err) =>
-                        Err(err),
-                    _ =>
-                        Ok(TwirpError::new(StatusCode::InternalServerError, "internal_err", "Internal Error").
-                            to_hyper_resp()),
+                    ProstTwirpError::HyperError(err) => Err(err),
+                    err => {
+                        let err = err.into_twirp_error();
+                        Ok(match error_request_id {
+                            Some(ref id) if err.error_type == "internal" => err.with_meta(REQUEST_ID_META, id),
+                            _ => err,
+                        }.to_resp_raw())
+                    },
+                }).
+                map(move |mut v| {
+                    drop(guard);
+                    response_headers.apply_to(&mut v.headers);
+                    v
                 }))
         }
     }
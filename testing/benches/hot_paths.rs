@@ -0,0 +1,134 @@
+//! Baseline measurements of the encode/decode, server call, and generated dispatch paths.
+//!
+//! JSON bodies aren't supported by Prost Twirp, so the JSON cases measure Twirp error bodies which are always JSON.
+
+#[macro_use]
+extern crate criterion;
+extern crate futures;
+extern crate hyper;
+extern crate prost_twirp;
+extern crate prost_twirp_testing;
+#[macro_use]
+extern crate serde_json;
+extern crate tokio_core;
+
+use criterion::{BenchmarkId, Criterion, Throughput};
+use futures::Future;
+use hyper::{Client, Method, Request, StatusCode};
+use hyper::server::Service;
+use prost_twirp::{ServiceRequest, ServiceResponse, TwirpError};
+use prost_twirp_testing::{EchoService, LoopbackServer, WideService};
+use prost_twirp_testing::bench::{Blob, Echo, Wide};
+use prost_twirp_testing::haberdasher::{Hat, Size};
+use tokio_core::reactor::Core;
+
+const SIZES: &[(&str, usize)] = &[("small", 16), ("large", 1024 * 1024)];
+
+fn blob(size: usize) -> Blob { Blob { data: vec![7; size] } }
+
+fn hyper_req(path: &str, input: Blob) -> Request {
+    let mut req = ServiceRequest::new(input).to_proto_raw().unwrap();
+    req.uri = path.parse().unwrap();
+    req.into_hyper_raw()
+}
+
+fn error() -> TwirpError {
+    TwirpError::new_meta(StatusCode::BadRequest, "invalid_argument", "Size too small",
+        Some(json!({ "min": 1, "max": 10 })))
+}
+
+fn encode_decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode_decode");
+    let hat = ServiceResponse::new(Hat { size: 12, color: "blue".to_string(), name: "fedora".to_string() });
+    group.bench_function("response_to_proto_raw/hat", |b| b.iter(|| hat.to_proto_raw().unwrap()));
+    let hat_raw = hat.to_proto_raw().unwrap();
+    group.bench_function("response_to_proto/hat", |b| b.iter(|| hat_raw.to_proto::<Hat>().unwrap()));
+    let size = ServiceRequest::new(Size { inches: 12 }).to_proto_raw().unwrap();
+    group.bench_function("request_to_proto/size", |b| b.iter(|| size.to_proto::<Size>().unwrap()));
+    for &(name, size) in SIZES {
+        group.throughput(Throughput::Bytes(size as u64));
+        let req = ServiceRequest::new(blob(size));
+        group.bench_with_input(BenchmarkId::new("request_to_proto_raw", name), &req,
+            |b, req| b.iter(|| req.to_proto_raw().unwrap()));
+        let raw = req.to_proto_raw().unwrap();
+        group.bench_with_input(BenchmarkId::new("request_to_proto", name), &raw,
+            |b, raw| b.iter(|| raw.to_proto::<Blob>().unwrap()));
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("error_json");
+    let err = error();
+    group.bench_function("to_json_bytes", |b| b.iter(|| err.to_json_bytes().unwrap()));
+    let err_bytes = err.to_json_bytes().unwrap();
+    group.bench_function("from_json_bytes", |b| {
+        b.iter(|| TwirpError::from_json_bytes(StatusCode::BadRequest, &err_bytes).unwrap())
+    });
+    let err_resp = err.to_resp_raw();
+    group.bench_function("response_to_proto", |b| b.iter(|| err_resp.to_proto::<Blob>().unwrap_err()));
+    group.finish();
+}
+
+fn server_call(c: &mut Criterion) {
+    let mut group = c.benchmark_group("server_call");
+    let echo = <dyn Echo>::new_server(EchoService);
+    for &(name, size) in SIZES {
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_function(BenchmarkId::new("echo", name), |b| {
+            b.iter_batched(|| hyper_req("/twirp/prost_twirp.bench.Echo/Echo", blob(size)),
+                |req| echo.call(req).wait().unwrap(), criterion::BatchSize::SmallInput)
+        });
+    }
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("handler_error", |b| {
+        b.iter_batched(|| hyper_req("/twirp/prost_twirp.bench.Echo/Fail", blob(16)),
+            |req| echo.call(req).wait().unwrap(), criterion::BatchSize::SmallInput)
+    });
+    group.bench_function("bad_route", |b| {
+        b.iter_batched(|| hyper_req("/twirp/prost_twirp.bench.Echo/Missing", blob(16)),
+            |req| echo.call(req).wait().unwrap(), criterion::BatchSize::SmallInput)
+    });
+    group.bench_function("bad_body", |b| {
+        b.iter_batched(|| {
+            let mut req = ServiceRequest::new(vec![0xff; 16]);
+            req.uri = "/twirp/prost_twirp.bench.Echo/Echo".parse().unwrap();
+            req.into_hyper_raw()
+        }, |req| echo.call(req).wait().unwrap(), criterion::BatchSize::SmallInput)
+    });
+    group.bench_function("bad_method", |b| {
+        b.iter_batched(|| Request::new(Method::Get, "/twirp/prost_twirp.bench.Echo/Echo".parse().unwrap()),
+            |req| echo.call(req).wait().unwrap(), criterion::BatchSize::SmallInput)
+    });
+    group.finish();
+
+    // The generated server matches on the path, so later methods may cost more
+    let mut group = c.benchmark_group("dispatch");
+    let wide = <dyn Wide>::new_server(WideService);
+    for &method in &["Method00", "Method15", "Method31"] {
+        let path = format!("/twirp/prost_twirp.bench.Wide/{}", method);
+        group.bench_function(method, |b| {
+            b.iter_batched(|| hyper_req(&path, blob(16)), |req| wide.call(req).wait().unwrap(),
+                criterion::BatchSize::SmallInput)
+        });
+    }
+    group.finish();
+}
+
+fn loopback(c: &mut Criterion) {
+    let server = LoopbackServer::start(|| <dyn Echo>::new_server(EchoService));
+    let mut core = Core::new().unwrap();
+    let client = <dyn Echo>::new_client(Client::new(&core.handle()), &server.root_url());
+    let mut group = c.benchmark_group("loopback");
+    for &(name, size) in SIZES {
+        group.throughput(Throughput::Bytes(size as u64));
+        let input = blob(size);
+        group.bench_function(BenchmarkId::new("echo", name), |b| {
+            b.iter(|| core.run(client.echo(input.clone().into())).unwrap())
+        });
+    }
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("handler_error", |b| b.iter(|| core.run(client.fail(blob(16).into())).unwrap_err()));
+    group.finish();
+}
+
+criterion_group!(benches, encode_decode, server_call, loopback);
+criterion_main!(benches);
@@ -0,0 +1,87 @@
+#[macro_use]
+extern crate criterion;
+extern crate futures;
+extern crate hyper;
+extern crate prost_twirp;
+extern crate prost_twirp_testing;
+
+use criterion::{BenchmarkId, Criterion, Throughput};
+use futures::{Future, Stream};
+use hyper::{Request, Response};
+use prost_twirp::{ServiceRequest, ServiceResponse};
+use prost_twirp_testing::bench::Blob;
+
+const SIZES: &[usize] = &[1024, 1024 * 1024, 8 * 1024 * 1024];
+
+fn blob(size: usize) -> Blob { Blob { data: vec![7; size] } }
+
+fn raw_req(size: usize) -> ServiceRequest<Vec<u8>> { ServiceRequest::new(blob(size)).to_proto_raw().unwrap() }
+
+fn hyper_req(size: usize) -> Request {
+    let mut req = raw_req(size).into_hyper_raw();
+    req.set_uri("/twirp/bench.Blobs/Echo".parse().unwrap());
+    req
+}
+
+fn to_hyper(c: &mut Criterion) {
+    let mut group = c.benchmark_group("to_hyper_raw");
+    for &size in SIZES {
+        group.throughput(Throughput::Bytes(size as u64));
+        // The copying version, as this was always done before
+        group.bench_with_input(BenchmarkId::new("request_copy", size), &size, |b, &size| {
+            b.iter_batched(|| raw_req(size), |req| req.to_hyper_raw(), criterion::BatchSize::LargeInput)
+        });
+        group.bench_with_input(BenchmarkId::new("request_move", size), &size, |b, &size| {
+            b.iter_batched(|| raw_req(size), |req| req.into_hyper_raw(), criterion::BatchSize::LargeInput)
+        });
+        group.bench_with_input(BenchmarkId::new("response_copy", size), &size, |b, &size| {
+            b.iter_batched(|| ServiceResponse::new(blob(size)).to_proto_raw().unwrap(),
+                |resp| resp.to_hyper_raw(), criterion::BatchSize::LargeInput)
+        });
+        group.bench_with_input(BenchmarkId::new("response_move", size), &size, |b, &size| {
+            b.iter_batched(|| ServiceResponse::new(blob(size)).to_proto_raw().unwrap(),
+                |resp| resp.into_hyper_raw(), criterion::BatchSize::LargeInput)
+        });
+    }
+    group.finish();
+}
+
+fn from_hyper(c: &mut Criterion) {
+    let mut group = c.benchmark_group("from_hyper_raw");
+    for &size in SIZES {
+        group.throughput(Throughput::Bytes(size as u64));
+        // How bodies were collected before, concatenated and then copied into a vec
+        group.bench_with_input(BenchmarkId::new("request_concat", size), &size, |b, &size| {
+            b.iter_batched(|| hyper_req(size),
+                |req| req.body().concat2().map(|v| v.to_vec()).wait().unwrap(), criterion::BatchSize::LargeInput)
+        });
+        group.bench_with_input(BenchmarkId::new("request", size), &size, |b, &size| {
+            b.iter_batched(|| hyper_req(size),
+                |req| ServiceRequest::from_hyper_raw(req).wait().unwrap(), criterion::BatchSize::LargeInput)
+        });
+        // How protobuf responses were decoded before, copied together into a vec first
+        group.bench_with_input(BenchmarkId::new("response_raw_then_proto", size), &size, |b, &size| {
+            b.iter_batched(|| -> Response { ServiceResponse::new(blob(size)).to_hyper_proto().unwrap() },
+                |resp| ServiceResponse::from_hyper_raw(resp).wait().unwrap().to_proto::<Blob>().unwrap(),
+                criterion::BatchSize::LargeInput)
+        });
+        group.bench_with_input(BenchmarkId::new("response_proto", size), &size, |b, &size| {
+            b.iter_batched(|| -> Response { ServiceResponse::new(blob(size)).to_hyper_proto().unwrap() },
+                |resp| ServiceResponse::<Blob>::from_hyper_proto(resp).wait().unwrap(), criterion::BatchSize::LargeInput)
+        });
+    }
+    group.finish();
+}
+
+fn to_proto_raw(c: &mut Criterion) {
+    let mut group = c.benchmark_group("to_proto_raw");
+    for &size in SIZES {
+        group.throughput(Throughput::Bytes(size as u64));
+        let req = ServiceRequest::new(blob(size));
+        group.bench_with_input(BenchmarkId::new("request", size), &req, |b, req| b.iter(|| req.to_proto_raw().unwrap()));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, to_hyper, from_hyper, to_proto_raw);
+criterion_main!(benches);
@@ -0,0 +1,27 @@
+extern crate prost_build;
+extern crate prost_twirp;
+
+use prost_twirp::TwirpServiceGenerator;
+use std::path::{Path, PathBuf};
+
+fn main() {
+    // One config per package, each service generator only emits its type aliases once
+    let protos = &[("service.proto", "../examples"), ("bench.proto", "protos"), ("clientcompat.proto", "protos"),
+        ("vault.proto", "protos")];
+    for &(proto, include) in protos {
+        let protos = [Path::new(include).join(proto)];
+        let includes: [PathBuf; 2] = [include.into(), TwirpServiceGenerator::options_include()];
+        let mut gen = TwirpServiceGenerator::new();
+        gen.read_policies(&protos, &includes).unwrap();
+        let mut conf = prost_build::Config::new();
+        conf.service_generator(Box::new(gen));
+        conf.compile_protos(&protos, &includes).unwrap();
+    }
+    // The messages of the golden corpus, which the `golden_compile` tests build the expected service code with
+    let golden = ["comments", "cross_package", "long_names", "method_names",
+        "multi_service", "nested_package", "policies",
+        "reserved_words"].iter().map(|name| Path::new("tests/golden").join(format!("{}.proto", name))).
+        collect::<Vec<_>>();
+    let includes: [PathBuf; 2] = ["tests/golden".into(), TwirpServiceGenerator::options_include()];
+    prost_build::compile_protos(&golden, &includes).unwrap();
+}
@@ -0,0 +1,60 @@
+//! A client binary for the Twirp clientcompat suite.
+//!
+//! Reads a `ClientCompatMessage` from stdin and calls the requested method on the service at the given address. On
+//! success the serialized response is written to stdout, on a Twirp error the error code is written to stderr. Any
+//! other failure exits with a non-zero status.
+
+extern crate futures;
+extern crate hyper;
+extern crate prost;
+extern crate prost_twirp;
+extern crate prost_twirp_testing;
+extern crate tokio_core;
+
+use futures::Future;
+use hyper::Client;
+use prost::Message;
+use prost_twirp::{ProstTwirpError, PTRes};
+use prost_twirp_testing::clientcompat::{ClientCompatMessage, CompatService, Empty, Req};
+use prost_twirp_testing::clientcompat::client_compat_message::CompatServiceMethod;
+use std::io::{self, Read, Write};
+use std::process;
+use tokio_core::reactor::Core;
+
+fn main() {
+    let mut input = Vec::new();
+    io::stdin().read_to_end(&mut input).unwrap();
+    let msg = ClientCompatMessage::decode(&input).unwrap_or_else(|err| fail(&format!("Invalid message: {}", err)));
+
+    let mut core = Core::new().unwrap();
+    let client = <dyn CompatService>::new_client(Client::new(&core.handle()), &msg.service_address);
+    let res = if msg.method == CompatServiceMethod::Noop as i32 {
+        encode(&mut core, Empty::decode(&msg.request).map(|v| client.noop_method(v.into())))
+    } else if msg.method == CompatServiceMethod::Method as i32 {
+        encode(&mut core, Req::decode(&msg.request).map(|v| client.method(v.into())))
+    } else {
+        fail(&format!("Unknown method {}", msg.method))
+    };
+
+    match res {
+        Ok(output) => io::stdout().write_all(&output).unwrap(),
+        Err(err) => match err.twirp_code() {
+            Some(code) => io::stderr().write_all(code.as_bytes()).unwrap(),
+            None => fail(&format!("Call failed: {:?}", err)),
+        },
+    }
+}
+
+fn encode<O: Message>(core: &mut Core, call: Result<PTRes<O>, prost::DecodeError>) -> Result<Vec<u8>, ProstTwirpError> {
+    let call = call.unwrap_or_else(|err| fail(&format!("Invalid request: {}", err)));
+    core.run(call.map(|resp| {
+        let mut output = Vec::new();
+        resp.output.encode(&mut output).unwrap();
+        output
+    }))
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("{}", msg);
+    process::exit(1)
+}
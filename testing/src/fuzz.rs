@@ -0,0 +1,113 @@
+//! Fuzzing harnesses for code that handles untrusted bytes.
+//!
+//! Each function takes arbitrary bytes, must never panic, and asserts the invariants that should hold for any input.
+//! They are run by the cargo-fuzz targets in `fuzz/` and by the property tests under `cargo test`.
+
+use futures::{Future, Stream};
+use hyper::header::{ContentLength, ContentType};
+use hyper::server::Service;
+use hyper::{Headers, Method, Request, StatusCode};
+use prost_twirp::{media_type, HyperServer, ServiceResponse, TwirpError, PROTOBUF_MEDIA_TYPES};
+use {bench, haberdasher, EchoService, HaberdasherService, WideService};
+
+/// The paths requests are sent to, picked by the first byte of a request body input
+pub const PATHS: &[&str] = &[
+    "/twirp/prost_twirp.bench.Echo/Echo",
+    "/twirp/prost_twirp.bench.Echo/Fail",
+    "/twirp/prost_twirp.bench.Wide/Method00",
+    "/twirp/prost_twirp.bench.Wide/Method31",
+    "/twirp/twitch.twirp.example.Haberdasher/MakeHat",
+    "/twirp/twitch.twirp.example.Haberdasher/Missing",
+];
+
+/// The largest body the servers under test will read
+pub const MAX_BODY_SIZE: u64 = 64 * 1024;
+
+/// Decode the bytes as a Twirp error body, both directly and as a non-success response
+///
+/// The first two bytes pick the status. Anything that decodes must survive a round trip through JSON with the same
+/// code, message, and presence of meta. The meta itself isn't compared since serde_json doesn't round trip floats
+/// exactly.
+pub fn error_json(data: &[u8]) {
+    if data.len() < 2 { return; }
+    let status = StatusCode::try_from(u16::from(data[0]) << 8 | u16::from(data[1])).
+        unwrap_or(StatusCode::InternalServerError);
+    let body = &data[2..];
+    if let Ok(err) = TwirpError::from_json_bytes(status, body) {
+        let again = TwirpError::from_json_bytes(status, &err.to_json_bytes().unwrap()).unwrap();
+        assert_eq!((&again.error_type, &again.msg, again.meta.is_some()),
+            (&err.error_type, &err.msg, err.meta.is_some()));
+    }
+    let resp = ServiceResponse { version: Default::default(), headers: Headers::new(), status, output: body.to_vec() };
+    let err = TwirpError::from_resp_raw(&resp);
+    assert_eq!(err.status, status);
+}
+
+/// Send the bytes as a request body through the generated dispatch of the test services
+///
+/// The first byte picks the path from `PATHS`. If the low bit of the second byte is set, the next eight bytes are sent
+/// as the `Content-Length` regardless of the real length, and if the next bit is set the body size is limited to
+/// `MAX_BODY_SIZE`. Every response must be protobuf or a Twirp error whose status matches its code.
+pub fn request_body(data: &[u8]) {
+    if data.len() < 2 { return; }
+    let path = PATHS[data[0] as usize % PATHS.len()];
+    let flags = data[1];
+    let (len, body) = if flags & 1 == 1 && data.len() >= 10 {
+        let mut len = [0; 8];
+        len.copy_from_slice(&data[2..10]);
+        (u64::from_be_bytes(len), &data[10..])
+    } else {
+        (data.len() as u64 - 2, &data[2..])
+    };
+    let max_body_size = if flags & 2 == 2 { Some(MAX_BODY_SIZE) } else { None };
+    let mut req = Request::new(Method::Post, path.parse().unwrap());
+    req.headers_mut().set_raw("Content-Type", "application/protobuf");
+    req.headers_mut().set(ContentLength(len));
+    req.set_body(body.to_vec());
+    let (status, content_type, body) = if path.contains("Haberdasher") {
+        let server = haberdasher::HaberdasherServer::new(HaberdasherService);
+        call(HyperServer { max_body_size, ..HyperServer::new(server) }, req)
+    } else if path.contains("Wide") {
+        call(HyperServer { max_body_size, ..HyperServer::new(bench::WideServer::new(WideService)) }, req)
+    } else {
+        call(HyperServer { max_body_size, ..HyperServer::new(bench::EchoServer::new(EchoService)) }, req)
+    };
+    assert_response(status, content_type, &body);
+}
+
+/// Send a request with the bytes as its `Content-Type` header
+///
+/// The request must be dispatched if and only if the parsed media type is one of the protobuf media types.
+pub fn content_type(data: &[u8]) {
+    let parsed = media_type(&String::from_utf8_lossy(data));
+    assert!(!parsed.contains(';'));
+    assert_eq!(media_type(&parsed), parsed);
+    let mut req = Request::new(Method::Post, PATHS[0].parse().unwrap());
+    req.headers_mut().set_raw("Content-Type", data.to_vec());
+    let (status, content_type, body) = call(HyperServer::new(bench::EchoServer::new(EchoService)), req);
+    if PROTOBUF_MEDIA_TYPES.contains(&parsed.as_str()) {
+        assert_eq!(status, StatusCode::Ok);
+        assert_eq!(content_type.as_deref(), Some(parsed.as_str()));
+    } else {
+        assert_eq!(status, StatusCode::NotFound);
+        assert_response(status, content_type, &body);
+    }
+}
+
+fn call<S: Service<Request=Request, Response=hyper::Response, Error=hyper::Error>>(server: S, req: Request)
+        -> (StatusCode, Option<String>, Vec<u8>) {
+    let resp = server.call(req).wait().unwrap();
+    let status = resp.status();
+    let content_type = resp.headers().get::<ContentType>().map(|v| v.to_string());
+    (status, content_type, resp.body().concat2().wait().unwrap().to_vec())
+}
+
+fn assert_response(status: StatusCode, content_type: Option<String>, body: &[u8]) {
+    if status == StatusCode::Ok {
+        assert!(content_type.is_some_and(|v| PROTOBUF_MEDIA_TYPES.contains(&v.as_str())));
+        return;
+    }
+    assert_eq!(content_type.as_deref(), Some("application/json"));
+    let err = TwirpError::from_json_bytes(status, body).unwrap();
+    assert_eq!(TwirpError::status_for_code(&err.error_type), status, "status for {:?}", err);
+}
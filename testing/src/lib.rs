@@ -0,0 +1,136 @@
+//! Generated services and helpers shared by the Prost Twirp benchmarks and tests.
+
+extern crate bytes;
+extern crate futures;
+extern crate hyper;
+extern crate prost;
+#[macro_use]
+extern crate prost_derive;
+extern crate prost_twirp;
+extern crate serde_json;
+extern crate tokio_core;
+
+use futures::future;
+use futures::sync::oneshot;
+use futures::Future;
+use hyper::header::ContentLength;
+use hyper::server::{Http, Service};
+use hyper::{Method, Request, Response, StatusCode};
+use prost::Message;
+use prost_twirp::TwirpError;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+
+pub mod fuzz;
+
+pub mod haberdasher {
+    include!(concat!(env!("OUT_DIR"), "/twitch.twirp.example.rs"));
+}
+
+pub mod bench {
+    include!(concat!(env!("OUT_DIR"), "/prost_twirp.bench.rs"));
+}
+
+pub mod clientcompat {
+    include!(concat!(env!("OUT_DIR"), "/twirp.clientcompat.rs"));
+}
+
+pub mod vault {
+    include!(concat!(env!("OUT_DIR"), "/prost_twirp.vault.rs"));
+}
+
+/// Makes hats like the examples, rejecting sizes outside of 1 to 10 inches
+pub struct HaberdasherService;
+impl haberdasher::Haberdasher for HaberdasherService {
+    fn make_hat(&self, i: haberdasher::PTReq<haberdasher::Size>) -> haberdasher::PTRes<haberdasher::Hat> {
+        Box::new(future::result(
+            if i.input.inches < 1 || i.input.inches > 10 {
+                Err(TwirpError::new(StatusCode::BadRequest, "invalid_argument", "Size out of range").into())
+            } else {
+                Ok(haberdasher::Hat { size: i.input.inches, color: "blue".to_string(), name: "fedora".to_string() }.
+                    into())
+            }
+        ))
+    }
+}
+
+/// Echoes blobs back, or fails with `invalid_argument`
+pub struct EchoService;
+impl bench::Echo for EchoService {
+    fn echo(&self, i: bench::PTReq<bench::Blob>) -> bench::PTRes<bench::Blob> {
+        Box::new(future::ok(i.input.into()))
+    }
+
+    fn fail(&self, _: bench::PTReq<bench::Blob>) -> bench::PTRes<bench::Blob> {
+        Box::new(future::err(TwirpError::new(StatusCode::BadRequest, "invalid_argument", "Always fails").into()))
+    }
+}
+
+macro_rules! wide_methods {
+    ($($name:ident),*) => {
+        $(
+            fn $name(&self, i: bench::PTReq<bench::Blob>) -> bench::PTRes<bench::Blob> {
+                Box::new(future::ok(i.input.into()))
+            }
+        )*
+    };
+}
+
+/// Echoes blobs back from every method
+pub struct WideService;
+impl bench::Wide for WideService {
+    wide_methods!(method00, method01, method02, method03, method04, method05, method06, method07,
+        method08, method09, method10, method11, method12, method13, method14, method15,
+        method16, method17, method18, method19, method20, method21, method22, method23,
+        method24, method25, method26, method27, method28, method29, method30, method31);
+}
+
+/// A hyper server running on a background thread bound to a random loopback port
+///
+/// The server is shut down and its thread joined on drop.
+pub struct LoopbackServer {
+    /// The address the server is listening on
+    pub addr: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl LoopbackServer {
+    /// Start a server that uses the given function to create a service for each connection
+    pub fn start<F, S>(new_service: F) -> LoopbackServer
+            where F: 'static + Send + Fn() -> S,
+                  S: 'static + Service<Request=Request, Response=Response, Error=hyper::Error> {
+        let (shutdown_send, shutdown_recv) = oneshot::channel();
+        let (addr_send, addr_recv) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            let addr = "127.0.0.1:0".parse().unwrap();
+            let server = Http::new().bind(&addr, move || Ok::<_, io::Error>(new_service())).unwrap();
+            addr_send.send(server.local_addr().unwrap()).unwrap();
+            server.run_until(shutdown_recv.map_err(|_| ())).unwrap();
+        });
+        LoopbackServer { addr: addr_recv.recv().unwrap(), shutdown: Some(shutdown_send), thread: Some(thread) }
+    }
+
+    /// The root URL to give to clients
+    pub fn root_url(&self) -> String { format!("http://{}", self.addr) }
+}
+
+impl Drop for LoopbackServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() { let _ = shutdown.send(()); }
+        if let Some(thread) = self.thread.take() { let _ = thread.join(); }
+    }
+}
+
+/// A protobuf request for the method at the path, like a generated client sends, to give to a server directly
+pub fn proto_request<M: Message>(path: &str, input: &M) -> Request {
+    let mut body = Vec::with_capacity(input.encoded_len());
+    input.encode(&mut body).unwrap();
+    let mut req = Request::new(Method::Post, path.parse().unwrap());
+    req.headers_mut().set_raw("Content-Type", "application/protobuf");
+    req.headers_mut().set(ContentLength(body.len() as u64));
+    req.set_body(body);
+    req
+}
@@ -0,0 +1,131 @@
+//! Tests for the access log records, formats and sinks of servers.
+
+extern crate hyper;
+extern crate log;
+extern crate prost_twirp;
+extern crate prost_twirp_testing;
+extern crate tokio_core;
+
+use hyper::{Client, Method, Request, StatusCode};
+use prost_twirp::{AccessLog, AccessLogFormat, AccessLogger, AccessRecord, HyperServer, LogSink, RequestIds, WriterSink};
+use prost_twirp_testing::{EchoService, LoopbackServer};
+use prost_twirp_testing::bench::{Blob, EchoClient, EchoServer};
+use std::fs::{self, File};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+use tokio_core::reactor::Core;
+
+/// Keeps every record
+#[derive(Clone, Default)]
+struct Records(Arc<Mutex<Vec<AccessRecord>>>);
+
+impl AccessLog for Records {
+    fn log(&self, record: &AccessRecord) { self.0.lock().unwrap().push(record.clone()); }
+}
+
+fn record() -> AccessRecord {
+    AccessRecord { timestamp: UNIX_EPOCH + Duration::from_millis(1_541_040_245_678), remote_addr:
+        Some("127.0.0.1:5000".parse().unwrap()), service: "pkg.Service".to_string(), method: "Method".to_string(),
+        status: StatusCode::BadRequest, code: Some("invalid_argument".to_string()), request_size: 7,
+        response_size: 52, duration: Duration::from_micros(1_500), request_id: Some("abc".to_string()) }
+}
+
+#[test]
+fn formats() {
+    let record = record();
+    assert_eq!(AccessLogFormat::JsonLines.format(&record), "{\"ts\":\"2018-11-01T02:44:05.678Z\",\
+        \"remote_addr\":\"127.0.0.1:5000\",\"service\":\"pkg.Service\",\"method\":\"Method\",\"status\":400,\
+        \"code\":\"invalid_argument\",\"request_size\":7,\"response_size\":52,\"duration_ms\":1.5,\"request_id\":\"abc\"}");
+    assert_eq!(AccessLogFormat::Logfmt.format(&record), "ts=2018-11-01T02:44:05.678Z remote_addr=127.0.0.1:5000 \
+        service=pkg.Service method=Method status=400 code=invalid_argument request_size=7 response_size=52 \
+        duration_ms=1.500 request_id=abc");
+
+    // Missing values are null or left out, and logfmt quotes what it must
+    let record = AccessRecord { timestamp: UNIX_EPOCH, remote_addr: None, service: "unknown".to_string(),
+        method: "has \"quotes\" =\n".to_string(), status: StatusCode::Ok, code: None, request_id: None, ..record };
+    assert_eq!(AccessLogFormat::JsonLines.format(&record), "{\"ts\":\"1970-01-01T00:00:00.000Z\",\
+        \"remote_addr\":null,\"service\":\"unknown\",\"method\":\"has \\\"quotes\\\" =\\n\",\"status\":200,\
+        \"code\":null,\"request_size\":7,\"response_size\":52,\"duration_ms\":1.5,\"request_id\":null}");
+    assert_eq!(AccessLogFormat::Logfmt.format(&record), "ts=1970-01-01T00:00:00.000Z service=unknown \
+        method=\"has \\\"quotes\\\" =\\n\" status=200 request_size=7 response_size=52 duration_ms=1.500");
+}
+
+#[test]
+fn server_calls() {
+    let records = Records::default();
+    let log = records.clone();
+    let server = LoopbackServer::start(move || {
+        let mut server = HyperServer::new(EchoServer::new(EchoService));
+        server.request_ids = RequestIds::Echo;
+        server.access_log = Some(Arc::new(log.clone()));
+        server
+    });
+    let mut core = Core::new().unwrap();
+    let hyper_client = Client::new(&core.handle());
+    let client = EchoClient::new(hyper_client.clone(), &server.root_url());
+    core.run(client.call_echo(Blob { data: b"hello".to_vec() }).header_raw("X-Request-Id", "abc")).unwrap();
+    assert!(core.run(client.call_fail(Blob { data: b"hi".to_vec() })).is_err());
+    let req = Request::new(Method::Get, format!("{}/nope", server.root_url()).parse().unwrap());
+    assert_eq!(core.run(hyper_client.request(req)).unwrap().status(), StatusCode::NotFound);
+
+    let records: Vec<_> = records.0.lock().unwrap().drain(..).collect();
+    assert_eq!(records.len(), 3);
+    let echo = &records[0];
+    assert_eq!(echo.remote_addr.map(|v| v.ip()), Some("127.0.0.1".parse().unwrap()));
+    assert_eq!((echo.service.as_str(), echo.method.as_str()), ("prost_twirp.bench.Echo", "Echo"));
+    assert_eq!((echo.status, echo.code.as_deref()), (StatusCode::Ok, None));
+    assert_eq!((echo.request_size, echo.response_size), (7, 7));
+    assert_eq!(echo.request_id.as_deref(), Some("abc"));
+    let fail = &records[1];
+    assert_eq!((fail.method.as_str(), fail.status), ("Fail", StatusCode::BadRequest));
+    assert_eq!(fail.code.as_deref(), Some("invalid_argument"));
+    assert_eq!(fail.request_size, 4);
+    assert!(fail.response_size > 0);
+    // Requests rejected before the body is read have no request size
+    let nope = &records[2];
+    assert_eq!((nope.service.as_str(), nope.method.as_str()), ("unknown", "unknown"));
+    assert_eq!((nope.status, nope.code.as_deref(), nope.request_size), (StatusCode::NotFound, Some("bad_route"), 0));
+}
+
+#[test]
+fn file_sink() {
+    let path = std::env::temp_dir().join(format!("prost-twirp-access-{}.log", std::process::id()));
+    let logger = AccessLogger::new(AccessLogFormat::Logfmt, WriterSink::new(File::create(&path).unwrap()));
+    logger.log(&record());
+    logger.log(&AccessRecord { request_id: Some("def".to_string()), ..record() });
+    let written = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    let lines: Vec<_> = written.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].ends_with(" request_id=abc") && lines[1].ends_with(" request_id=def"), "{}", written);
+    assert!(written.ends_with('\n'));
+}
+
+static LOGGED: Mutex<Vec<(String, log::Level, String)>> = Mutex::new(Vec::new());
+
+/// Keeps every log record
+struct Logged;
+
+impl log::Log for Logged {
+    fn enabled(&self, _: &log::Metadata) -> bool { true }
+
+    fn log(&self, record: &log::Record) {
+        LOGGED.lock().unwrap().push((record.target().to_string(), record.level(), record.args().to_string()));
+    }
+
+    fn flush(&self) {}
+}
+
+#[test]
+fn log_sink() {
+    log::set_logger(&Logged).unwrap();
+    log::set_max_level(log::LevelFilter::Trace);
+    AccessLogger::new(AccessLogFormat::JsonLines, LogSink::default()).log(&record());
+    let sink = LogSink { target: "access".to_string(), level: log::Level::Debug };
+    AccessLogger::new(AccessLogFormat::Logfmt, sink).log(&record());
+    let logged: Vec<_> = LOGGED.lock().unwrap().drain(..).collect();
+    assert_eq!(logged, vec![
+        ("prost_twirp::access".to_string(), log::Level::Info, AccessLogFormat::JsonLines.format(&record())),
+        ("access".to_string(), log::Level::Debug, AccessLogFormat::Logfmt.format(&record())),
+    ]);
+}
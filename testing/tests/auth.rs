@@ -0,0 +1,303 @@
+//! Tests for authenticating calls to generated servers.
+
+extern crate futures;
+extern crate hyper;
+extern crate jsonwebtoken;
+extern crate prost;
+extern crate prost_twirp;
+extern crate prost_twirp_testing;
+#[macro_use]
+extern crate serde_json;
+extern crate tokio_core;
+
+use futures::{future, Future, Stream};
+use hyper::server::Service;
+use hyper::{Client, Request, StatusCode};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use prost::Message;
+use prost_twirp::{hmac_authorization, Authenticated, CallerKey, HmacVerifier, HyperServer, JwtVerifier, MethodPolicy,
+    Principal, RateLimit, RateLimited, StaticTokens, TwirpError, Verifier};
+use prost_twirp_testing::{proto_request, LoopbackServer};
+use prost_twirp_testing::bench::{Blob, Echo, EchoServer, PTReq, PTRes};
+use prost_twirp_testing::vault::{self, Secret, Vault, VaultClient, VaultServer};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio_core::reactor::Core;
+
+const ECHO_BODY: &[u8] = b"hi";
+const SECRET: &[u8] = b"jwt secret";
+
+/// Echoes the principal's subject and scopes
+struct PrincipalService;
+impl Echo for PrincipalService {
+    fn echo(&self, i: PTReq<Blob>) -> PTRes<Blob> {
+        let data = match i.principal {
+            Some(ref p) => format!("{}:{}", p.subject, p.scopes.join(",")),
+            None => "anonymous".to_string(),
+        };
+        Box::new(future::ok(Blob { data: data.into_bytes() }.into()))
+    }
+
+    fn fail(&self, i: PTReq<Blob>) -> PTRes<Blob> { self.echo(i) }
+}
+
+/// Fails every call, `Echo` as unauthenticated and `Fail` as permission denied
+struct RejectingService;
+impl Echo for RejectingService {
+    fn echo(&self, _: PTReq<Blob>) -> PTRes<Blob> {
+        Box::new(future::err(TwirpError::new(StatusCode::Unauthorized, "unauthenticated", "session expired").into()))
+    }
+
+    fn fail(&self, _: PTReq<Blob>) -> PTRes<Blob> {
+        Box::new(future::err(TwirpError::new(StatusCode::Forbidden, "permission_denied", "not yours").into()))
+    }
+}
+
+fn authenticated(verifiers: Vec<Box<dyn Verifier>>) -> Authenticated<EchoServer<PrincipalService>> {
+    Authenticated::new(EchoServer::new(PrincipalService), verifiers)
+}
+
+/// The encoded body of the calls, which HMAC signatures are made over
+fn body() -> Vec<u8> {
+    let mut body = Vec::new();
+    Blob { data: ECHO_BODY.to_vec() }.encode(&mut body).unwrap();
+    body
+}
+
+/// Call `Echo` with the given `Authorization`, returning what it echoed or the error and `WWW-Authenticate` header
+fn call<S>(server: &S, authorization: Option<&str>) -> Result<String, (StatusCode, String, Option<String>)>
+        where S: Service<Request=Request, Response=hyper::Response, Error=hyper::Error> {
+    call_path(server, <dyn Echo>::ECHO_METHOD.path, authorization)
+}
+
+/// Like `call`, for any method whose input and output are a single string or bytes field
+fn call_path<S>(server: &S, path: &str, authorization: Option<&str>)
+        -> Result<String, (StatusCode, String, Option<String>)>
+        where S: Service<Request=Request, Response=hyper::Response, Error=hyper::Error> {
+    let mut req = proto_request(path, &Blob { data: ECHO_BODY.to_vec() });
+    if let Some(authorization) = authorization { req.headers_mut().set_raw("Authorization", authorization); }
+    let resp = server.call(req).wait().unwrap();
+    let status = resp.status();
+    let challenge = resp.headers().get_raw("WWW-Authenticate").and_then(|v| v.one()).
+        map(|v| String::from_utf8_lossy(v).into_owned());
+    let body = resp.body().concat2().wait().unwrap();
+    if status == StatusCode::Ok {
+        Ok(String::from_utf8(Blob::decode(body.to_vec()).unwrap().data).unwrap())
+    } else {
+        let err = TwirpError::from_json_bytes(status, &body).unwrap();
+        Err((status, format!("{}: {}", err.error_type, err.msg), challenge))
+    }
+}
+
+fn unauthenticated(msg: &str) -> Result<String, (StatusCode, String, Option<String>)> {
+    Err((StatusCode::Unauthorized, format!("unauthenticated: {}", msg), Some("Bearer".to_string())))
+}
+
+fn now() -> u64 { SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() }
+
+fn principal(subject: &str, scopes: &[&str]) -> Principal {
+    Principal { subject: subject.to_string(), scopes: scopes.iter().map(|v| v.to_string()).collect(),
+        ..Default::default() }
+}
+
+fn static_tokens() -> StaticTokens {
+    StaticTokens::new().with_token("alice-token", principal("alice", &["hats.read"])).
+        with_token("bob-token", principal("bob", &[]))
+}
+
+fn hs256(claims: serde_json::Value, secret: &[u8]) -> String {
+    let token = jsonwebtoken::encode(&Header::new(Algorithm::HS256), &claims, &EncodingKey::from_secret(secret));
+    format!("Bearer {}", token.unwrap())
+}
+
+#[test]
+fn static_bearer_tokens() {
+    let server = HyperServer::new(authenticated(vec![Box::new(static_tokens())]));
+    assert_eq!(call(&server, Some("Bearer alice-token")), Ok("alice:hats.read".to_string()));
+    assert_eq!(call(&server, Some("bearer  bob-token ")), Ok("bob:".to_string()));
+    assert_eq!(call(&server, Some("Bearer alice-tokem")), unauthenticated("invalid token"));
+    assert_eq!(call(&server, Some("Basic YWxpY2U6")), unauthenticated("missing credentials"));
+    assert_eq!(call(&server, None), unauthenticated("missing credentials"));
+}
+
+#[test]
+fn allow_anonymous() {
+    let mut auth = authenticated(vec![Box::new(static_tokens())]);
+    auth.allow_anonymous = true;
+    auth.challenge = "Bearer realm=\"hats\"".to_string();
+    let server = HyperServer::new(auth);
+    assert_eq!(call(&server, None), Ok("anonymous".to_string()));
+    assert_eq!(call(&server, Some("Bearer bob-token")), Ok("bob:".to_string()));
+    // Bad credentials are still rejected
+    assert_eq!(call(&server, Some("Bearer nope")), Err((StatusCode::Unauthorized,
+        "unauthenticated: invalid token".to_string(), Some("Bearer realm=\"hats\"".to_string()))));
+}
+
+#[test]
+fn jwt_hs256() {
+    let server = HyperServer::new(authenticated(vec![Box::new(JwtVerifier::hs256(SECRET))]));
+    let exp = now() + 600;
+    assert_eq!(call(&server, Some(&hs256(json!({ "sub": "carol", "scope": "a b", "exp": exp }), SECRET))),
+        Ok("carol:a,b".to_string()));
+    assert_eq!(call(&server, Some(&hs256(json!({ "sub": "carol", "scp": ["c"], "exp": exp }), SECRET))),
+        Ok("carol:c".to_string()));
+    assert_eq!(call(&server, Some(&hs256(json!({ "sub": "carol", "exp": now() - 600 }), SECRET))),
+        unauthenticated("token expired"));
+    assert_eq!(call(&server, Some(&hs256(json!({ "sub": "carol", "exp": exp }), b"other secret"))),
+        unauthenticated("invalid token"));
+    // The expiry is required
+    assert_eq!(call(&server, Some(&hs256(json!({ "sub": "carol" }), SECRET))), unauthenticated("invalid token"));
+    assert_eq!(call(&server, Some("Bearer not.a.jwt")), unauthenticated("invalid token"));
+}
+
+#[test]
+fn jwt_audience() {
+    let mut verifier = JwtVerifier::hs256(SECRET);
+    verifier.validation.set_audience(&["hats"]);
+    let server = HyperServer::new(authenticated(vec![Box::new(verifier)]));
+    let exp = now() + 600;
+    assert_eq!(call(&server, Some(&hs256(json!({ "sub": "dan", "aud": "hats", "exp": exp }), SECRET))),
+        Ok("dan:".to_string()));
+    assert_eq!(call(&server, Some(&hs256(json!({ "sub": "dan", "aud": "shoes", "exp": exp }), SECRET))),
+        Err((StatusCode::Forbidden, "permission_denied: token not valid for this service".to_string(), None)));
+}
+
+#[test]
+fn jwt_rs256() {
+    let public_key = include_bytes!("auth/rsa_public.pem");
+    let private_key = EncodingKey::from_rsa_pem(include_bytes!("auth/rsa_private.pem")).unwrap();
+    let server = HyperServer::new(authenticated(vec![Box::new(JwtVerifier::rs256_pem(public_key).unwrap())]));
+    let claims = json!({ "sub": "erin", "exp": now() + 600 });
+    let token = jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &private_key).unwrap();
+    assert_eq!(call(&server, Some(&format!("Bearer {}", token))), Ok("erin:".to_string()));
+    // An HS256 token can't pass as RS256
+    assert_eq!(call(&server, Some(&hs256(claims, public_key))), unauthenticated("invalid token"));
+}
+
+#[test]
+fn hmac_signed() {
+    let verifier = HmacVerifier::new().with_key("service-a", b"a secret").with_key("service:b", b"b secret");
+    let server = HyperServer::new(authenticated(vec![Box::new(verifier)]));
+    let path = <dyn Echo>::ECHO_METHOD.path;
+    let sign = |key_id, secret, timestamp, body: &[u8]| hmac_authorization(key_id, secret, timestamp, path, body);
+    assert_eq!(call(&server, Some(&sign("service-a", b"a secret", now(), &body()))), Ok("service-a:".to_string()));
+    assert_eq!(call(&server, Some(&sign("service:b", b"b secret", now() + 60, &body()))), Ok("service:b:".to_string()));
+    assert_eq!(call(&server, Some(&sign("service-a", b"b secret", now(), &body()))),
+        unauthenticated("invalid signature"));
+    assert_eq!(call(&server, Some(&sign("service-a", b"a secret", now(), b"other body"))),
+        unauthenticated("invalid signature"));
+    assert_eq!(call(&server, Some(&sign("service-a", b"a secret", now() - 600, &body()))),
+        unauthenticated("signature expired"));
+    assert_eq!(call(&server, Some(&sign("service-c", b"a secret", now(), &body()))), unauthenticated("unknown key"));
+    assert_eq!(call(&server, Some("HMAC-SHA256 service-a:abc:00")), unauthenticated("malformed signature"));
+    assert_eq!(call(&server, Some("HMAC-SHA256 service-a")), unauthenticated("malformed signature"));
+}
+
+#[test]
+fn verifiers_tried_in_order() {
+    let server = HyperServer::new(authenticated(vec![Box::new(static_tokens()), Box::new(JwtVerifier::hs256(SECRET))]));
+    assert_eq!(call(&server, Some("Bearer alice-token")), Ok("alice:hats.read".to_string()));
+    assert_eq!(call(&server, Some(&hs256(json!({ "sub": "carol", "exp": now() + 600 }), SECRET))),
+        Ok("carol:".to_string()));
+    // The first verifier's error is returned
+    assert_eq!(call(&server, Some("Bearer nope")), unauthenticated("invalid token"));
+}
+
+#[test]
+fn rate_limited_by_principal() {
+    let limited = RateLimited::new(EchoServer::new(PrincipalService), CallerKey::Principal,
+        RateLimit { burst: 1, per_second: 0.01 });
+    let server = HyperServer::new(Authenticated::new(limited, vec![Box::new(static_tokens())]));
+    assert_eq!(call(&server, Some("Bearer alice-token")), Ok("alice:hats.read".to_string()));
+    assert_eq!(call(&server, Some("Bearer alice-token")).unwrap_err().1,
+        "resource_exhausted: rate limit exceeded");
+    assert_eq!(call(&server, Some("Bearer bob-token")), Ok("bob:".to_string()));
+}
+
+/// Answers every method with the principal's subject
+struct VaultService;
+impl VaultService {
+    fn whoami(i: vault::PTReq<Secret>) -> vault::PTRes<Secret> {
+        let name = i.principal.map_or_else(|| "anonymous".to_string(), |v| v.subject);
+        Box::new(future::ok(Secret { name }.into()))
+    }
+}
+
+impl Vault for VaultService {
+    fn read(&self, i: vault::PTReq<Secret>) -> vault::PTRes<Secret> { VaultService::whoami(i) }
+    fn write(&self, i: vault::PTReq<Secret>) -> vault::PTRes<Secret> { VaultService::whoami(i) }
+    fn whoami(&self, i: vault::PTReq<Secret>) -> vault::PTRes<Secret> { VaultService::whoami(i) }
+    fn health(&self, i: vault::PTReq<Secret>) -> vault::PTRes<Secret> { VaultService::whoami(i) }
+}
+
+#[test]
+fn policy_challenge() {
+    let mut auth = Authenticated::new(VaultServer::new(VaultService), vec![Box::new(static_tokens())]);
+    auth.allow_anonymous = true;
+    auth.challenge = "Bearer realm=\"vault\"".to_string();
+    let server = HyperServer::new(auth);
+    // Policy rejections are challenged the same way as the auth layer's own
+    assert_eq!(call_path(&server, <dyn Vault>::READ_METHOD.path, None), Err((StatusCode::Unauthorized,
+        "unauthenticated: missing credentials".to_string(), Some("Bearer realm=\"vault\"".to_string()))));
+    assert_eq!(call_path(&server, <dyn Vault>::READ_METHOD.path, Some("Bearer nope")), Err((StatusCode::Unauthorized,
+        "unauthenticated: invalid token".to_string(), Some("Bearer realm=\"vault\"".to_string()))));
+    assert_eq!(call_path(&server, <dyn Vault>::WRITE_METHOD.path, Some("Bearer alice-token")), Err((
+        StatusCode::Forbidden, "permission_denied: missing scope \"secrets.read\"".to_string(), None)));
+}
+
+#[test]
+fn handler_error_challenge() {
+    let server = HyperServer::new(Authenticated::new(EchoServer::new(RejectingService),
+        vec![Box::new(static_tokens())]));
+    // Handlers failing as unauthenticated are challenged like the auth layer's own errors, but not other failures
+    assert_eq!(call(&server, Some("Bearer alice-token")), unauthenticated("session expired"));
+    assert_eq!(call_path(&server, <dyn Echo>::FAIL_METHOD.path, Some("Bearer alice-token")),
+        Err((StatusCode::Forbidden, "permission_denied: not yours".to_string(), None)));
+}
+
+#[test]
+fn policy_descriptors() {
+    assert_eq!(<dyn Vault>::READ_METHOD.policy, Some(MethodPolicy { scopes: &["secrets.read"], roles: &[] }));
+    assert_eq!(<dyn Vault>::WRITE_METHOD.policy,
+        Some(MethodPolicy { scopes: &["secrets.read", "secrets.write"], roles: &["admin", "owner"] }));
+    assert_eq!(<dyn Vault>::WHOAMI_METHOD.policy, Some(MethodPolicy { scopes: &[], roles: &[] }));
+    assert_eq!(<dyn Vault>::HEALTH_METHOD.policy, None);
+}
+
+#[test]
+fn policies_enforced() {
+    let mut owner = principal("owner", &["secrets.read", "secrets.write"]);
+    owner.claims.insert("roles".to_string(), json!(["owner"]));
+    let mut guest = principal("guest", &["secrets.read", "secrets.write"]);
+    guest.claims.insert("roles".to_string(), json!(["guest"]));
+    let tokens = StaticTokens::new().with_token("reader", principal("reader", &["secrets.read"])).
+        with_token("owner", owner).with_token("guest", guest);
+    let server = LoopbackServer::start(move || {
+        let mut auth = Authenticated::new(VaultServer::new(VaultService), vec![Box::new(tokens.clone())]);
+        auth.allow_anonymous = true;
+        HyperServer::new(auth)
+    });
+    let mut core = Core::new().unwrap();
+    let client = VaultClient::new(Client::new(&core.handle()), &server.root_url());
+    let mut call = |method: &str, token: Option<&str>| {
+        let mut call = match method {
+            "read" => client.call_read(Secret::default()),
+            "write" => client.call_write(Secret::default()),
+            "whoami" => client.call_whoami(Secret::default()),
+            _ => client.call_health(Secret::default()),
+        };
+        if let Some(token) = token { call = call.header_raw("Authorization", format!("Bearer {}", token)); }
+        core.run(call).map(|v| v.output.name).map_err(|err| match err.root_err() {
+            prost_twirp::ProstTwirpError::TwirpError(err) => format!("{}: {}", err.error_type, err.msg),
+            err => panic!("expected Twirp error, got {:?}", err),
+        })
+    };
+    // Methods without a policy are open, the rest need a principal
+    assert_eq!(call("health", None), Ok("anonymous".to_string()));
+    assert_eq!(call("whoami", None), Err("unauthenticated: missing credentials".to_string()));
+    assert_eq!(call("read", None), Err("unauthenticated: missing credentials".to_string()));
+    assert_eq!(call("whoami", Some("reader")), Ok("reader".to_string()));
+    assert_eq!(call("read", Some("reader")), Ok("reader".to_string()));
+    assert_eq!(call("write", Some("reader")), Err("permission_denied: missing scope \"secrets.write\"".to_string()));
+    assert_eq!(call("write", Some("owner")), Ok("owner".to_string()));
+    assert_eq!(call("write", Some("guest")), Err("permission_denied: missing role".to_string()));
+}
@@ -0,0 +1,76 @@
+//! Tests for decoding protobuf bodies straight from the chunks they arrive in.
+
+extern crate futures;
+extern crate hyper;
+extern crate prost;
+extern crate prost_twirp;
+extern crate prost_twirp_testing;
+
+use futures::{Future, Sink};
+use futures::stream;
+use futures::sync::mpsc::SendError;
+use hyper::header::ContentLength;
+use hyper::{Body, Chunk, Method, Request, Response, StatusCode};
+use prost::Message;
+use prost_twirp::{ProstTwirpError, ServiceRequest, ServiceResponse};
+use prost_twirp_testing::bench::Blob;
+use std::thread;
+
+fn blob() -> Blob { Blob { data: (0..=255).collect() } }
+
+fn blob_bytes() -> Vec<u8> {
+    let mut bytes = Vec::new();
+    blob().encode(&mut bytes).unwrap();
+    bytes
+}
+
+/// A body sent in chunks of the given size, including empty ones
+fn chunked(bytes: Vec<u8>, size: usize) -> Body {
+    let (sender, body) = Body::pair();
+    thread::spawn(move || {
+        let mut chunks: Vec<Result<Chunk, hyper::Error>> = vec![Ok(Chunk::from(Vec::new()))];
+        chunks.extend(bytes.chunks(size).map(|v| Ok(v.to_vec().into())));
+        chunks.push(Ok(Chunk::from(Vec::new())));
+        sender.send_all(stream::iter_ok::<_, SendError<_>>(chunks)).map(drop).wait().unwrap();
+    });
+    body
+}
+
+#[test]
+fn requests() {
+    // Splits land in the middle of tags and lengths as well as data
+    for &size in &[1, 2, 3, 100, 1000] {
+        let mut req = Request::new(Method::Post, "/twirp/prost_twirp.bench.Echo/Echo".parse().unwrap());
+        req.set_body(chunked(blob_bytes(), size));
+        let req = ServiceRequest::<Blob>::from_hyper_proto(req).wait().unwrap();
+        assert_eq!(req.input, blob(), "{}", size);
+    }
+    // Bodies that can't be decoded are kept whole for the error
+    let mut bad = blob_bytes();
+    bad.truncate(100);
+    let mut req = Request::new(Method::Post, "/twirp/prost_twirp.bench.Echo/Echo".parse().unwrap());
+    req.set_body(chunked(bad.clone(), 7));
+    let err = ServiceRequest::<Blob>::from_hyper_proto(req).wait().unwrap_err();
+    match err {
+        ProstTwirpError::AfterBodyError { body, .. } => assert_eq!(body, bad),
+        err => panic!("unexpected error {:?}", err),
+    }
+}
+
+#[test]
+fn responses() {
+    for &size in &[1, 3, 1000] {
+        let resp = Response::new().with_body(chunked(blob_bytes(), size));
+        let resp = ServiceResponse::<Blob>::from_hyper_proto(resp).wait().unwrap();
+        assert_eq!((resp.status, resp.output), (StatusCode::Ok, blob()));
+    }
+    // Errors keep the whole body
+    let body = br#"{"code":"not_found","msg":"no hat"}"#.to_vec();
+    let resp = Response::new().with_status(StatusCode::NotFound).with_header(ContentLength(body.len() as u64)).
+        with_body(chunked(body.clone(), 4));
+    let err = ServiceResponse::<Blob>::from_hyper_proto(resp).wait().unwrap_err();
+    assert_eq!((err.twirp_code(), err.response_body()), (Some("not_found"), Some(&body[..])));
+    let resp = Response::new().with_body(chunked(vec![0xff, 0xff], 1));
+    let err = ServiceResponse::<Blob>::from_hyper_proto(resp).wait().unwrap_err();
+    assert_eq!(err.response_body(), Some(&[0xff, 0xff][..]));
+}
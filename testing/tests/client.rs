@@ -0,0 +1,182 @@
+//! Tests for generated clients against a loopback server.
+
+extern crate futures;
+extern crate hyper;
+extern crate prost_twirp;
+extern crate prost_twirp_testing;
+#[macro_use]
+extern crate serde_json;
+extern crate tokio_core;
+
+use futures::future;
+use futures::Future;
+use hyper::{Client, Response, StatusCode};
+use hyper::header::{HttpDate, RetryAfter, UserAgent};
+use hyper::server::service_fn;
+use prost_twirp::{ProstTwirpError, TwirpError};
+use prost_twirp_testing::LoopbackServer;
+use prost_twirp_testing::bench::{self, Blob, Echo, EchoClient, PTReq, PTRes};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use tokio_core::reactor::Core;
+
+/// Echoes the headers it was sent as the blob, and is slow to fail
+struct HeaderService;
+impl Echo for HeaderService {
+    fn echo(&self, i: PTReq<Blob>) -> PTRes<Blob> {
+        let mut headers = i.headers.iter().
+            filter(|h| h.name().starts_with("X-") || h.name() == "User-Agent").
+            map(|h| format!("{}: {}", h.name(), h.value_string())).collect::<Vec<_>>();
+        headers.sort();
+        Box::new(future::ok(Blob { data: headers.join("\n").into_bytes() }.into()))
+    }
+
+    fn fail(&self, i: PTReq<Blob>) -> PTRes<Blob> {
+        thread::sleep(Duration::from_millis(500));
+        Box::new(future::ok(i.input.into()))
+    }
+}
+
+fn client(core: &Core, server: &LoopbackServer) -> EchoClient {
+    EchoClient::new(Client::new(&core.handle()), &server.root_url())
+}
+
+fn blob() -> Blob { Blob { data: b"hello".to_vec() } }
+
+#[test]
+fn request_into_still_works() {
+    let server = LoopbackServer::start(|| <dyn Echo>::new_server(HeaderService));
+    let mut core = Core::new().unwrap();
+    let client = <dyn Echo>::new_client(Client::new(&core.handle()), &server.root_url());
+    let resp = core.run(client.echo(blob().into()).map(|resp| resp.output)).unwrap();
+    assert_eq!(resp.data, b"");
+    let mut req = PTReq::new(blob());
+    req.headers.set_raw("X-Trait", "yes");
+    let resp = core.run(client.echo(req)).unwrap();
+    assert_eq!(resp.output.data, b"X-Trait: yes");
+}
+
+#[test]
+fn headers() {
+    let server = LoopbackServer::start(|| <dyn Echo>::new_server(HeaderService));
+    let mut core = Core::new().unwrap();
+    let client = client(&core, &server);
+    let call = client.call_echo(blob()).
+        header(UserAgent::new("prost-twirp-test")).
+        header_raw("X-Request-Id", "abc").
+        header_raw("X-Request-Id", "def").
+        header_raw("X-Other", "1");
+    let resp = core.run(call.send()).unwrap();
+    assert_eq!(String::from_utf8(resp.output.data).unwrap(),
+        "User-Agent: prost-twirp-test\nX-Other: 1\nX-Request-Id: def");
+}
+
+#[test]
+fn timeout() {
+    let server = LoopbackServer::start(|| <dyn Echo>::new_server(HeaderService));
+    let mut core = Core::new().unwrap();
+    let client = client(&core, &server);
+    let err = core.run(client.call_fail(blob()).timeout(Duration::from_millis(50))).unwrap_err();
+    assert!(err.is_timeout(), "expected timeout, got {:?}", err);
+    let resp = core.run(client.call_fail(blob()).timeout(Duration::from_secs(10)).send()).unwrap();
+    assert_eq!(resp.output, blob());
+}
+
+#[test]
+fn used_after_sent() {
+    let server = LoopbackServer::start(|| <dyn Echo>::new_server(HeaderService));
+    let mut core = Core::new().unwrap();
+    let client = client(&core, &server);
+    let mut call = client.call_echo(blob());
+    assert_eq!(core.run(&mut call).unwrap().output.data, b"");
+    // Polling after it finished and changing it after it was sent fail the call
+    let misused = |err: ProstTwirpError| match err {
+        ProstTwirpError::TwirpError(err) => (err.error_type, err.msg),
+        err => panic!("expected a Twirp error, got {:?}", err),
+    };
+    let finished = ("internal".to_string(), "the call already finished".to_string());
+    assert_eq!(misused(core.run(&mut call).unwrap_err()), finished);
+    let changed = ("internal".to_string(), "the call was changed after it was sent".to_string());
+    assert_eq!(misused(core.run(call.header_raw("X-Late", "1")).unwrap_err()), changed);
+    let mut call = client.call_echo(blob()).header_raw("X-Early", "1");
+    core.run(future::lazy(|| call.poll().map(drop).map_err(drop))).unwrap();
+    assert_eq!(misused(core.run(call.timeout(Duration::from_secs(1))).unwrap_err()), changed);
+    // Sending one that is already being polled gives its response
+    let mut call = client.call_echo(blob()).header_raw("X-Early", "1");
+    core.run(future::lazy(|| call.poll().map(drop).map_err(drop))).unwrap();
+    assert_eq!(core.run(call.send()).unwrap().output.data, b"X-Early: 1");
+}
+
+#[test]
+fn boxed_trait_object() {
+    // Clients still come as the service trait, and the concrete client implements it too
+    let server = LoopbackServer::start(|| <dyn Echo>::new_server(HeaderService));
+    let mut core = Core::new().unwrap();
+    let boxed: Box<dyn bench::Echo> = <dyn Echo>::new_client(Client::new(&core.handle()), &server.root_url());
+    assert_eq!(core.run(boxed.echo(PTReq::new(blob()))).unwrap().output.data, b"");
+    let boxed: Box<dyn bench::Echo> = Box::new(client(&core, &server));
+    assert_eq!(core.run(boxed.echo(PTReq::new(blob()))).unwrap().output.data, b"");
+}
+
+/// Asks to be retried later
+struct BusyService;
+impl Echo for BusyService {
+    fn echo(&self, _: PTReq<Blob>) -> PTRes<Blob> {
+        Box::new(future::err(TwirpError::from_code("resource_exhausted", "Slow down").
+            with_retry_after(Duration::from_millis(2500)).into()))
+    }
+
+    fn fail(&self, _: PTReq<Blob>) -> PTRes<Blob> {
+        Box::new(future::err(TwirpError::from_code("unavailable", "Down").into()))
+    }
+}
+
+#[test]
+fn retry_after() {
+    let server = LoopbackServer::start(|| <dyn Echo>::new_server(BusyService));
+    let mut core = Core::new().unwrap();
+    let client = client(&core, &server);
+    let err = core.run(client.call_echo(blob())).unwrap_err();
+    assert!(err.is_retryable());
+    assert_eq!(err.retry_after(), Some(Duration::from_secs(3)));
+    match err.root_err() {
+        prost_twirp::ProstTwirpError::TwirpError(err) => {
+            assert_eq!(err.meta, Some(json!({ "retry_after": "3" })));
+            assert_eq!(err.headers.get::<RetryAfter>(), Some(&RetryAfter::Delay(Duration::from_secs(3))));
+        },
+        err => panic!("expected Twirp error, got {:?}", err),
+    }
+    assert_eq!(core.run(client.call_fail(blob())).unwrap_err().retry_after(), None);
+}
+
+#[test]
+fn retry_after_from_intermediary() {
+    let server = LoopbackServer::start(|| service_fn(|_| Ok(Response::new().
+        with_status(StatusCode::ServiceUnavailable).
+        with_header(RetryAfter::Delay(Duration::from_secs(120))).
+        with_body("upstream overloaded"))));
+    let mut core = Core::new().unwrap();
+    let err = core.run(client(&core, &server).call_echo(blob())).unwrap_err();
+    assert_eq!(err.twirp_code(), Some("unavailable"));
+    assert_eq!(err.retry_after(), Some(Duration::from_secs(120)));
+}
+
+#[test]
+fn retry_after_sources() {
+    let err = TwirpError::from_code("unavailable", "Down");
+    assert_eq!(err.retry_after(), None);
+    // Meta is used without a header, as a string or number
+    let meta = |v| TwirpError::new_meta(StatusCode::ServiceUnavailable, "unavailable", "Down", Some(v));
+    assert_eq!(meta(json!({ "retry_after": "7" })).retry_after(), Some(Duration::from_secs(7)));
+    assert_eq!(meta(json!({ "retry_after": 7 })).retry_after(), Some(Duration::from_secs(7)));
+    assert_eq!(meta(json!({ "retry_after": "soon" })).retry_after(), None);
+    // Non-object meta is kept
+    assert_eq!(meta(json!("text")).with_retry_after(Duration::from_secs(1)).meta, Some(json!("text")));
+    // Dates are relative to now
+    let past = err.with_header(RetryAfter::DateTime(HttpDate::from(SystemTime::now() - Duration::from_secs(60))));
+    assert_eq!(past.retry_after(), Some(Duration::from_secs(0)));
+    let future = TwirpError::from_code("unavailable", "Down").
+        with_header(RetryAfter::DateTime(HttpDate::from(SystemTime::now() + Duration::from_secs(600))));
+    let delay = future.retry_after().unwrap();
+    assert!(delay > Duration::from_secs(590) && delay <= Duration::from_secs(600), "{:?}", delay);
+}
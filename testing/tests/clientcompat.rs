@@ -0,0 +1,232 @@
+//! Runs the clientcompat binary against a stand-in for the Twirp clientcompat server.
+//!
+//! Each case starts a server that checks the request is what the spec requires and replies with a canned response,
+//! then asserts the binary reports what the Twirp clientcompat suite expects.
+
+extern crate futures;
+extern crate hyper;
+extern crate prost;
+extern crate prost_twirp;
+extern crate prost_twirp_testing;
+
+use futures::{Future, Stream};
+use hyper::header::{ContentType, Location};
+use hyper::server::Service;
+use hyper::{Headers, Method, Request, Response, StatusCode};
+use prost::Message;
+use prost_twirp::TwirpError;
+use prost_twirp_testing::LoopbackServer;
+use prost_twirp_testing::clientcompat::{ClientCompatMessage, Empty, Req, Resp};
+use prost_twirp_testing::clientcompat::client_compat_message::CompatServiceMethod;
+use std::io::Write;
+use std::net::TcpListener;
+use std::process::{Command, Output, Stdio};
+use std::sync::{Arc, Mutex};
+
+/// What the stand-in server expects and how it responds
+struct Canned {
+    path: &'static str,
+    body: Vec<u8>,
+    status: StatusCode,
+    headers: Mutex<Headers>,
+    resp_body: Vec<u8>,
+    /// Set if the request was not what was expected
+    mismatch: Mutex<Option<String>>,
+}
+
+#[derive(Clone)]
+struct StandIn(Arc<Canned>);
+
+impl Service for StandIn {
+    type Request = Request;
+    type Response = Response;
+    type Error = hyper::Error;
+    type Future = Box<dyn Future<Item = Response, Error = hyper::Error>>;
+
+    fn call(&self, req: Request) -> Self::Future {
+        let canned = self.0.clone();
+        let mut mismatches = Vec::new();
+        if req.method() != &Method::Post { mismatches.push(format!("method {}", req.method())); }
+        if req.path() != canned.path { mismatches.push(format!("path {}", req.path())); }
+        match req.headers().get::<ContentType>() {
+            Some(v) if v.to_string() == "application/protobuf" => (),
+            v => mismatches.push(format!("content type {:?}", v)),
+        }
+        Box::new(req.body().concat2().map(move |body| {
+            if body.as_ref() != canned.body.as_slice() { mismatches.push(format!("body {:?}", body.as_ref())); }
+            if !mismatches.is_empty() { *canned.mismatch.lock().unwrap() = Some(mismatches.join(", ")); }
+            Response::new().
+                with_status(canned.status).
+                with_headers(canned.headers.lock().unwrap().clone()).
+                with_body(canned.resp_body.clone())
+        }))
+    }
+}
+
+/// Run the client binary for the given call against a stand-in server and return the output
+fn run_client<M: Message>(method: CompatServiceMethod, request: M, status: StatusCode, headers: Headers,
+        resp_body: Vec<u8>) -> Output {
+    let path = match method {
+        CompatServiceMethod::Noop => "/twirp/twirp.clientcompat.CompatService/NoopMethod",
+        CompatServiceMethod::Method => "/twirp/twirp.clientcompat.CompatService/Method",
+    };
+    let mut body = Vec::new();
+    request.encode(&mut body).unwrap();
+    let canned = Arc::new(Canned {
+        path, body: body.clone(), status, headers: Mutex::new(headers), resp_body, mismatch: Mutex::new(None),
+    });
+    let stand_in = StandIn(canned.clone());
+    let server = LoopbackServer::start(move || stand_in.clone());
+
+    let output = run_binary(ClientCompatMessage {
+        service_address: server.root_url(), method: method as i32, request: body,
+    });
+    assert_eq!(*canned.mismatch.lock().unwrap(), None, "unexpected request");
+    output
+}
+
+/// Run the client binary with the given message on stdin
+fn run_binary(msg: ClientCompatMessage) -> Output {
+    let mut input = Vec::new();
+    msg.encode(&mut input).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_clientcompat")).
+        stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).
+        spawn().unwrap();
+    child.stdin.take().unwrap().write_all(&input).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn protobuf_headers() -> Headers {
+    let mut headers = Headers::new();
+    headers.set(ContentType("application/protobuf".parse().unwrap()));
+    headers
+}
+
+fn json_headers() -> Headers {
+    let mut headers = Headers::new();
+    headers.set(ContentType::json());
+    headers
+}
+
+/// Assert the client succeeded with the given response
+fn assert_response<M: Message>(output: &Output, expected: M) {
+    let mut expected_bytes = Vec::new();
+    expected.encode(&mut expected_bytes).unwrap();
+    assert!(output.status.success(), "client failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    assert_eq!(output.stdout, expected_bytes);
+}
+
+/// Assert the client reported the given Twirp error code
+fn assert_error_code(output: &Output, code: &str) {
+    assert!(output.status.success(), "client failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(output.stdout, Vec::<u8>::new());
+    assert_eq!(String::from_utf8_lossy(&output.stderr), code);
+}
+
+const CODES: &[&str] = &["canceled", "unknown", "invalid_argument", "malformed", "deadline_exceeded", "not_found",
+    "bad_route", "already_exists", "permission_denied", "unauthenticated", "resource_exhausted", "failed_precondition",
+    "aborted", "out_of_range", "unimplemented", "internal", "unavailable", "dataloss"];
+
+#[test]
+fn noop_empty_response() {
+    let output = run_client(CompatServiceMethod::Noop, Empty {}, StatusCode::Ok, protobuf_headers(), Vec::new());
+    assert_response(&output, Empty {});
+}
+
+#[test]
+fn method_response() {
+    let mut resp_body = Vec::new();
+    Resp { v: 42 }.encode(&mut resp_body).unwrap();
+    let output = run_client(CompatServiceMethod::Method, Req { v: "hello".to_string() }, StatusCode::Ok,
+        protobuf_headers(), resp_body);
+    assert_response(&output, Resp { v: 42 });
+}
+
+#[test]
+fn twirp_errors() {
+    for &code in CODES {
+        let body = format!(r#"{{"code":"{}","msg":"Failed with {}"}}"#, code, code).into_bytes();
+        let output = run_client(CompatServiceMethod::Method, Req { v: code.to_string() },
+            TwirpError::status_for_code(code), json_headers(), body);
+        assert_error_code(&output, code);
+    }
+}
+
+#[test]
+fn twirp_error_with_meta() {
+    let body = br#"{"code":"invalid_argument","msg":"Bad v","meta":{"argument":"v"}}"#.to_vec();
+    let output = run_client(CompatServiceMethod::Method, Req { v: "meta".to_string() }, StatusCode::BadRequest,
+        json_headers(), body);
+    assert_error_code(&output, "invalid_argument");
+}
+
+#[test]
+fn intermediary_errors() {
+    let cases = &[
+        (StatusCode::BadRequest, "internal"),
+        (StatusCode::Unauthorized, "unauthenticated"),
+        (StatusCode::Forbidden, "permission_denied"),
+        (StatusCode::NotFound, "bad_route"),
+        (StatusCode::TooManyRequests, "unavailable"),
+        (StatusCode::InternalServerError, "unknown"),
+        (StatusCode::BadGateway, "unavailable"),
+        (StatusCode::ServiceUnavailable, "unavailable"),
+        (StatusCode::GatewayTimeout, "unavailable"),
+        (StatusCode::ImATeapot, "unknown"),
+    ];
+    for &(status, code) in cases {
+        let mut headers = Headers::new();
+        headers.set(ContentType::plaintext());
+        let output = run_client(CompatServiceMethod::Method, Req { v: status.to_string() }, status, headers,
+            b"Something went wrong".to_vec());
+        assert_error_code(&output, code);
+    }
+}
+
+#[test]
+fn intermediary_json_without_code() {
+    let output = run_client(CompatServiceMethod::Method, Req { v: "json".to_string() },
+        StatusCode::ServiceUnavailable, json_headers(), br#"{"error":"overloaded"}"#.to_vec());
+    assert_error_code(&output, "unavailable");
+}
+
+#[test]
+fn intermediary_redirect() {
+    let mut headers = Headers::new();
+    headers.set(Location::new("http://example.com/elsewhere"));
+    let output = run_client(CompatServiceMethod::Method, Req { v: "redirect".to_string() }, StatusCode::Found,
+        headers, Vec::new());
+    assert_error_code(&output, "internal");
+}
+
+#[test]
+fn intermediary_error_meta() {
+    let resp = prost_twirp::ServiceResponse {
+        version: Default::default(), headers: Headers::new(), status: StatusCode::BadGateway,
+        output: b"upstream down".to_vec(),
+    };
+    let err = resp.to_proto::<Resp>().unwrap_err();
+    assert_eq!(err.twirp_code(), Some("unavailable"));
+    assert!(err.is_retryable());
+    match err.root_err() {
+        prost_twirp::ProstTwirpError::TwirpError(err) => {
+            let meta = err.meta.unwrap();
+            assert_eq!(meta["http_error_from_intermediary"], "true");
+            assert_eq!(meta["status_code"], "502");
+            assert_eq!(meta["body"], "upstream down");
+        }
+        err => panic!("unexpected error {:?}", err),
+    }
+}
+
+#[test]
+fn transport_error() {
+    // Take a free port and release it so nothing is listening there
+    let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let output = run_binary(ClientCompatMessage {
+        service_address: format!("http://{}", addr), method: CompatServiceMethod::Noop as i32, request: Vec::new(),
+    });
+    assert!(!output.status.success());
+    assert_eq!(output.stdout, Vec::<u8>::new());
+}
@@ -0,0 +1,186 @@
+//! Tests for client credential providers against a loopback server.
+
+extern crate futures;
+extern crate hyper;
+extern crate prost_twirp;
+extern crate prost_twirp_testing;
+extern crate tokio_core;
+
+use futures::{future, Future};
+use hyper::Client;
+use prost_twirp::{authorization, Authenticated, CachedCredentials, Credential, CredentialProvider, FutCredential,
+    HyperServer, Principal, ServiceRequest, TwirpError, Verifier};
+use prost_twirp_testing::{EchoService, LoopbackServer};
+use prost_twirp_testing::bench::{Blob, EchoClient, EchoServer};
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_core::reactor::{Core, Handle, Timeout};
+
+/// Accepts the bearer tokens in the list, recording every token it sees
+#[derive(Clone, Default)]
+struct Tokens {
+    accepted: Arc<Mutex<Vec<String>>>,
+    seen: Arc<Mutex<Vec<String>>>,
+}
+
+impl Tokens {
+    fn accept(&self, tokens: &[&str]) {
+        *self.accepted.lock().unwrap() = tokens.iter().map(|v| v.to_string()).collect();
+    }
+
+    fn seen(&self) -> Vec<String> { self.seen.lock().unwrap().drain(..).collect() }
+}
+
+impl Verifier for Tokens {
+    fn verify(&self, req: &ServiceRequest<Vec<u8>>) -> Option<Result<Principal, TwirpError>> {
+        let token = authorization(req, "Bearer")?;
+        self.seen.lock().unwrap().push(token.to_string());
+        if self.accepted.lock().unwrap().iter().any(|v| v == token) {
+            Some(Ok(Principal { subject: token.to_string(), ..Default::default() }))
+        } else {
+            Some(Err(prost_twirp::unauthenticated("invalid token")))
+        }
+    }
+}
+
+fn server(tokens: &Tokens) -> LoopbackServer {
+    let tokens = tokens.clone();
+    LoopbackServer::start(move || {
+        HyperServer::new(Authenticated::new(EchoServer::new(EchoService), vec![Box::new(tokens.clone())]))
+    })
+}
+
+/// Fetches `token-1`, `token-2`, and so on, counting the fetches, after the delay if any
+fn fetcher(fetches: &Rc<Cell<u32>>, expires_in: Duration, delay: Option<(Handle, Duration)>)
+        -> impl Fn() -> FutCredential {
+    let fetches = fetches.clone();
+    move || {
+        fetches.set(fetches.get() + 1);
+        let credential = Credential::bearer(&format!("token-{}", fetches.get()), Some(expires_in));
+        match delay {
+            Some((ref handle, delay)) =>
+                Box::new(Timeout::new(delay, handle).unwrap().map(|_| credential).map_err(|err| panic!("{}", err))),
+            None => Box::new(future::ok(credential)),
+        }
+    }
+}
+
+fn client(core: &Core, server: &LoopbackServer, credentials: CachedCredentials) -> EchoClient {
+    EchoClient::new_with_credentials(Client::new(&core.handle()), &server.root_url(), credentials)
+}
+
+fn blob() -> Blob { Blob { data: b"hello".to_vec() } }
+
+#[test]
+fn cached_until_about_to_expire() {
+    let (tokens, fetches) = (Tokens::default(), Rc::new(Cell::new(0)));
+    tokens.accept(&["token-1", "token-2", "token-3"]);
+    let server = server(&tokens);
+    let mut core = Core::new().unwrap();
+    let credentials = CachedCredentials::new(fetcher(&fetches, Duration::from_secs(60), None));
+    let client = client(&core, &server, credentials);
+    for _ in 0..3 { core.run(client.call_echo(blob())).unwrap(); }
+    assert_eq!(fetches.get(), 1);
+    assert_eq!(tokens.seen(), vec!["token-1", "token-1", "token-1"]);
+    // Credentials within the refresh window are replaced before being used
+    let mut credentials = CachedCredentials::new(fetcher(&fetches, Duration::from_secs(60), None));
+    credentials.refresh_before = Duration::from_secs(90);
+    let client = EchoClient(client.0.with_credentials(credentials));
+    for _ in 0..2 { core.run(client.call_echo(blob())).unwrap(); }
+    assert_eq!(fetches.get(), 3);
+    assert_eq!(tokens.seen(), vec!["token-2", "token-3"]);
+}
+
+#[test]
+fn retried_once_after_rejection() {
+    let (tokens, fetches) = (Tokens::default(), Rc::new(Cell::new(0)));
+    tokens.accept(&["token-2"]);
+    let server = server(&tokens);
+    let mut core = Core::new().unwrap();
+    let client = client(&core, &server, CachedCredentials::new(fetcher(&fetches, Duration::from_secs(60), None)));
+    core.run(client.call_echo(blob())).unwrap();
+    assert_eq!(tokens.seen(), vec!["token-1", "token-2"]);
+    // The new token is cached
+    core.run(client.call_echo(blob())).unwrap();
+    assert_eq!(tokens.seen(), vec!["token-2"]);
+    // A second rejection is returned
+    tokens.accept(&[]);
+    let err = core.run(client.call_echo(blob())).unwrap_err();
+    assert_eq!(err.twirp_code(), Some("unauthenticated"));
+    assert_eq!(tokens.seen(), vec!["token-2", "token-3"]);
+    assert_eq!(fetches.get(), 3);
+}
+
+#[test]
+fn concurrent_calls_share_fetch() {
+    let (tokens, fetches) = (Tokens::default(), Rc::new(Cell::new(0)));
+    tokens.accept(&["token-1"]);
+    let server = server(&tokens);
+    let mut core = Core::new().unwrap();
+    let fetch = fetcher(&fetches, Duration::from_secs(60), Some((core.handle(), Duration::from_millis(50))));
+    let client = client(&core, &server, CachedCredentials::new(fetch));
+    let calls = future::join_all(vec![client.call_echo(blob()), client.call_echo(blob()), client.call_echo(blob())]);
+    assert_eq!(core.run(calls).unwrap().len(), 3);
+    assert_eq!(fetches.get(), 1);
+    // Calls rejected together only refresh once
+    tokens.accept(&["token-2"]);
+    let calls = future::join_all(vec![client.call_echo(blob()), client.call_echo(blob())]);
+    assert_eq!(core.run(calls).unwrap().len(), 2);
+    assert_eq!(fetches.get(), 2);
+}
+
+#[test]
+fn fetch_errors() {
+    let tokens = Tokens::default();
+    tokens.accept(&["token-1"]);
+    let server = server(&tokens);
+    let mut core = Core::new().unwrap();
+    let fails = Rc::new(Cell::new(true));
+    let fetch_fails = fails.clone();
+    let credentials = CachedCredentials::new(move || -> FutCredential {
+        if fetch_fails.get() {
+            Box::new(future::err(TwirpError::from_code("unavailable", "token endpoint down").into()))
+        } else {
+            Box::new(future::ok(Credential::bearer("token-1", None)))
+        }
+    });
+    let client = client(&core, &server, credentials);
+    let err = core.run(client.call_echo(blob())).unwrap_err();
+    assert_eq!(err.twirp_code(), Some("unavailable"));
+    assert!(tokens.seen().is_empty());
+    // Failures aren't cached
+    fails.set(false);
+    core.run(client.call_echo(blob())).unwrap();
+    assert_eq!(tokens.seen(), vec!["token-1"]);
+}
+
+#[test]
+fn explicit_authorization_kept() {
+    let (tokens, fetches) = (Tokens::default(), Rc::new(Cell::new(0)));
+    tokens.accept(&["mine"]);
+    let server = server(&tokens);
+    let mut core = Core::new().unwrap();
+    let client = client(&core, &server, CachedCredentials::new(fetcher(&fetches, Duration::from_secs(60), None)));
+    core.run(client.call_echo(blob()).header_raw("Authorization", "Bearer mine")).unwrap();
+    assert_eq!(tokens.seen(), vec!["mine"]);
+    assert_eq!(fetches.get(), 0);
+}
+
+#[test]
+fn custom_provider() {
+    struct Fixed;
+    impl CredentialProvider for Fixed {
+        fn authorization(&self, rejected: Option<&str>) -> prost_twirp::FutAuthorization {
+            Box::new(future::ok(if rejected.is_some() { "Bearer second" } else { "Bearer first" }.to_string()))
+        }
+    }
+    let tokens = Tokens::default();
+    tokens.accept(&["second"]);
+    let server = server(&tokens);
+    let mut core = Core::new().unwrap();
+    let client = EchoClient::new_with_credentials(Client::new(&core.handle()), &server.root_url(), Fixed);
+    core.run(client.call_echo(blob())).unwrap();
+    assert_eq!(tokens.seen(), vec!["first", "second"]);
+}
@@ -0,0 +1,73 @@
+//! Tests for the `IntoTwirpError` derive.
+
+extern crate hyper;
+extern crate prost_twirp;
+#[macro_use]
+extern crate serde_json;
+extern crate trybuild;
+
+use hyper::StatusCode;
+use prost_twirp::{IntoTwirpError, ProstTwirpError, TwirpError};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(IntoTwirpError, Debug)]
+enum HatError {
+    #[twirp(code = "unavailable", msg = "Out of hats, {{try}} later")]
+    OutOfHats,
+    #[twirp(code = "too_small", msg = "Size {0} too small for {1:?}", status = 400)]
+    TooSmall(i32, String),
+    #[twirp(code = "invalid_argument", msg = "Unknown color {color}")]
+    BadColor { color: String, #[twirp(meta)] allowed: HashMap<String, String> },
+    #[twirp(code = "failed_precondition", msg = "Size {0} can't be made")]
+    Unmakeable(i32, #[twirp(meta)] BTreeMap<&'static str, String>),
+}
+
+/// Where the runtime might be re-exported
+mod runtime {
+    pub use prost_twirp::*;
+}
+
+#[derive(IntoTwirpError, Debug)]
+#[twirp(crate = "::runtime")]
+enum Reexported {
+    #[twirp(code = "not_found", msg = "No hat {0}")]
+    Missing(u32),
+}
+
+fn error(v: TwirpError) -> (StatusCode, String, String, Option<serde_json::Value>) {
+    (v.status, v.error_type, v.msg, v.meta)
+}
+
+#[test]
+fn variants() {
+    assert_eq!(error(HatError::OutOfHats.into_twirp_error()),
+        (StatusCode::ServiceUnavailable, "unavailable".to_string(), "Out of hats, {try} later".to_string(), None));
+    assert_eq!(error(HatError::TooSmall(3, "fedora".to_string()).into_twirp_error()),
+        (StatusCode::BadRequest, "too_small".to_string(), "Size 3 too small for \"fedora\"".to_string(), None));
+    let allowed = vec![("colors".to_string(), "blue".to_string())].into_iter().collect();
+    let bad_color = HatError::BadColor { color: "plaid".to_string(), allowed };
+    assert_eq!(error(bad_color.into_twirp_error()), (StatusCode::BadRequest, "invalid_argument".to_string(),
+        "Unknown color plaid".to_string(), Some(json!({ "colors": "blue" }))));
+    let min = vec![("min", "1".to_string())].into_iter().collect();
+    assert_eq!(error(HatError::Unmakeable(0, min).into_twirp_error()),
+        (StatusCode::PreconditionFailed, "failed_precondition".to_string(), "Size 0 can't be made".to_string(),
+            Some(json!({ "min": "1" }))));
+    // The error converts to the handler error type
+    match ProstTwirpError::from(HatError::OutOfHats) {
+        ProstTwirpError::TwirpError(err) => assert_eq!(err.error_type, "unavailable"),
+        err => panic!("unexpected error {:?}", err),
+    }
+}
+
+#[test]
+fn crate_path() {
+    assert_eq!(error(Reexported::Missing(7).into_twirp_error()),
+        (StatusCode::NotFound, "not_found".to_string(), "No hat 7".to_string(), None));
+}
+
+#[test]
+fn compile_errors() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/derive/pass_*.rs");
+    cases.compile_fail("tests/derive/fail_*.rs");
+}
@@ -0,0 +1,45 @@
+extern crate prost_twirp;
+
+use prost_twirp::IntoTwirpError;
+use std::collections::HashMap;
+
+#[derive(IntoTwirpError)]
+struct NotEnum;
+
+#[derive(IntoTwirpError)]
+enum NoMsg {
+    #[twirp(code = "internal")]
+    Broken,
+}
+
+#[derive(IntoTwirpError)]
+enum BadStatus {
+    #[twirp(code = "internal", msg = "Broken", status = 600)]
+    Broken,
+}
+
+#[derive(IntoTwirpError)]
+enum TwoMeta {
+    #[twirp(code = "internal", msg = "Broken")]
+    Broken(#[twirp(meta)] HashMap<String, String>, #[twirp(meta)] HashMap<String, String>),
+}
+
+#[derive(IntoTwirpError)]
+enum NumberMeta {
+    #[twirp(code = "internal", msg = "Broken")]
+    Broken(#[twirp(meta)] HashMap<String, i32>),
+}
+
+#[derive(IntoTwirpError)]
+enum ListMeta {
+    #[twirp(code = "internal", msg = "Broken")]
+    Broken { #[twirp(meta)] details: Vec<String> },
+}
+
+#[derive(IntoTwirpError)]
+enum Unsupported {
+    #[twirp(code = "internal", msg = "Broken", level = "warn")]
+    Broken,
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+extern crate prost_twirp;
+
+use prost_twirp::IntoTwirpError;
+
+#[derive(IntoTwirpError)]
+enum Unknown {
+    #[twirp(code = "too_small", msg = "Too small")]
+    TooSmall,
+}
+
+#[derive(IntoTwirpError)]
+enum Empty {
+    #[twirp(code = "", msg = "Nothing", status = 400)]
+    Nothing,
+}
+
+fn main() {}
@@ -0,0 +1,25 @@
+//! Crates using the derive through a re-export need no hyper or serde_json of their own.
+
+extern crate prost_twirp;
+
+mod runtime {
+    pub use prost_twirp::*;
+}
+
+use runtime::IntoTwirpError;
+use std::collections::BTreeMap;
+
+#[derive(IntoTwirpError, Debug)]
+#[twirp(crate = "::runtime")]
+enum HatError {
+    #[twirp(code = "too_small", msg = "Size {0} too small", status = 400)]
+    TooSmall(i32, #[twirp(meta)] BTreeMap<String, String>),
+}
+
+fn main() {
+    let bounds = vec![("min".to_string(), "1".to_string())].into_iter().collect();
+    let err = HatError::TooSmall(0, bounds).into_twirp_error();
+    assert_eq!(u16::from(err.status), 400);
+    assert_eq!((err.error_type.as_str(), err.msg.as_str()), ("too_small", "Size 0 too small"));
+    assert_eq!(err.meta.map(|v| v.to_string()).as_deref(), Some(r#"{"min":"1"}"#));
+}
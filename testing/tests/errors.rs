@@ -0,0 +1,161 @@
+//! Tests for the classification helpers of `ProstTwirpError`.
+
+extern crate hyper;
+extern crate prost;
+extern crate prost_twirp;
+extern crate prost_twirp_testing;
+extern crate serde_json;
+
+use hyper::header::{Location, RetryAfter};
+use hyper::{Headers, HttpVersion, Method, StatusCode, Uri};
+use prost::Message;
+use prost_twirp::{ProstTwirpError, ServiceRequest, ServiceResponse, TwirpError};
+use prost_twirp_testing::bench::Blob;
+use std::io;
+use std::time::Duration;
+
+/// What the helpers say about an error
+#[derive(Debug, PartialEq)]
+struct Class {
+    transport: bool,
+    timeout: bool,
+    retryable: bool,
+    code: Option<String>,
+    status: Option<StatusCode>,
+    body: Option<Vec<u8>>,
+    retry_after: Option<Duration>,
+}
+
+fn class(err: &ProstTwirpError) -> Class {
+    Class { transport: err.is_transport(), timeout: err.is_timeout(), retryable: err.is_retryable(),
+        code: err.twirp_code().map(String::from), status: err.http_status(), body: err.response_body().map(Vec::from),
+        retry_after: err.retry_after() }
+}
+
+/// The class of an error that is none of the others
+fn plain() -> Class {
+    Class { transport: false, timeout: false, retryable: false, code: None, status: None, body: None,
+        retry_after: None }
+}
+
+/// The class of a Twirp error with the code and status that didn't come from a response
+fn twirp(code: &str, status: StatusCode) -> Class {
+    Class { code: Some(code.to_string()), status: Some(status), ..plain() }
+}
+
+fn hyper(err: hyper::Error) -> ProstTwirpError { ProstTwirpError::HyperError(err) }
+
+/// The error a client gets for the response
+fn response_err(status: StatusCode, headers: Headers, body: &[u8]) -> ProstTwirpError {
+    let resp = ServiceResponse { version: HttpVersion::Http11, headers, status, output: body.to_vec() };
+    resp.to_proto::<Blob>().unwrap_err()
+}
+
+#[test]
+fn hyper_errors() {
+    let transport = || Class { transport: true, retryable: true, ..plain() };
+    assert_eq!(class(&hyper(hyper::Error::Timeout)), Class { timeout: true, ..transport() });
+    assert_eq!(class(&hyper(hyper::Error::Incomplete)), transport());
+    assert_eq!(class(&hyper(hyper::Error::Closed)), transport());
+    let refused = io::Error::new(io::ErrorKind::ConnectionRefused, "refused");
+    assert_eq!(class(&hyper(hyper::Error::Io(refused))), transport());
+    let timed_out = io::Error::new(io::ErrorKind::TimedOut, "timed out");
+    assert_eq!(class(&hyper(hyper::Error::Io(timed_out))), Class { timeout: true, ..transport() });
+    // Canceled errors can only be made inside hyper; the rest are not transport errors
+    let uri = hyper::Error::from("http://[::1".parse::<Uri>().unwrap_err());
+    for err in [hyper::Error::Method, uri, hyper::Error::Version, hyper::Error::Header, hyper::Error::TooLarge,
+            hyper::Error::Status, hyper::Error::Upgrade] {
+        assert_eq!(class(&hyper(err)), plain());
+    }
+}
+
+#[test]
+fn encoding_errors() {
+    let json = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+    assert_eq!(class(&ProstTwirpError::JsonDecodeError(json)), plain());
+    let encode = Blob { data: b"hello".to_vec() }.encode(&mut io::Cursor::new(&mut [0u8; 2][..])).unwrap_err();
+    assert_eq!(class(&ProstTwirpError::ProstEncodeError(encode)), plain());
+    let decode = Blob::decode(&[0xff][..]).unwrap_err();
+    assert_eq!(class(&ProstTwirpError::ProstDecodeError(decode)), plain());
+    // Servers have no response status or body
+    let server = ServiceRequest::new(vec![0xff]).to_proto::<Blob>().unwrap_err();
+    assert_eq!(class(&server), plain());
+    // Bad responses keep their status and body
+    let client = response_err(StatusCode::Ok, Headers::new(), &[0xff]);
+    assert_eq!(class(&client), Class { status: Some(StatusCode::Ok), body: Some(vec![0xff]), ..plain() });
+}
+
+#[test]
+fn twirp_errors() {
+    assert_eq!(class(&TwirpError::from_code("internal", "boom").into()),
+        twirp("internal", StatusCode::InternalServerError));
+    assert_eq!(class(&TwirpError::from_code("deadline_exceeded", "slow").into()),
+        Class { timeout: true, ..twirp("deadline_exceeded", StatusCode::RequestTimeout) });
+    for &(code, status) in &[("unavailable", StatusCode::ServiceUnavailable),
+            ("resource_exhausted", StatusCode::TooManyRequests)] {
+        let err = TwirpError::from_code(code, "later").into();
+        assert_eq!(class(&err), Class { retryable: true, ..twirp(code, status) });
+    }
+    let later = TwirpError::from_code("unavailable", "later").with_retry_after(Duration::from_secs(3));
+    assert_eq!(class(&later.into()).retry_after, Some(Duration::from_secs(3)));
+
+    // From a Twirp server, the response status is kept even if it doesn't match the code
+    let body = br#"{"code":"unavailable","msg":"later","meta":{"retry_after":"2"}}"#;
+    let err = response_err(StatusCode::InternalServerError, Headers::new(), body);
+    assert_eq!(class(&err), Class { retryable: true, body: Some(body.to_vec()),
+        retry_after: Some(Duration::from_secs(2)), ..twirp("unavailable", StatusCode::InternalServerError) });
+    let body = br#"{"code":"deadline_exceeded","msg":"slow"}"#;
+    let err = response_err(StatusCode::RequestTimeout, Headers::new(), body);
+    assert_eq!(class(&err), Class { timeout: true, body: Some(body.to_vec()),
+        ..twirp("deadline_exceeded", StatusCode::RequestTimeout) });
+}
+
+#[test]
+fn intermediary_errors() {
+    let html = b"<html>Bad Gateway</html>";
+    for &(status, code, retryable) in &[
+        (StatusCode::BadGateway, "unavailable", true),
+        (StatusCode::ServiceUnavailable, "unavailable", true),
+        (StatusCode::GatewayTimeout, "unavailable", true),
+        (StatusCode::TooManyRequests, "unavailable", true),
+        (StatusCode::BadRequest, "internal", false),
+        (StatusCode::Unauthorized, "unauthenticated", false),
+        (StatusCode::Forbidden, "permission_denied", false),
+        (StatusCode::NotFound, "bad_route", false),
+        (StatusCode::InternalServerError, "unknown", false),
+    ] {
+        let err = response_err(status, Headers::new(), html);
+        assert_eq!(class(&err), Class { retryable, body: Some(html.to_vec()), ..twirp(code, status) }, "{}", status);
+    }
+    let mut headers = Headers::new();
+    headers.set(RetryAfter::Delay(Duration::from_secs(5)));
+    let err = response_err(StatusCode::ServiceUnavailable, headers, b"");
+    assert_eq!(err.retry_after(), Some(Duration::from_secs(5)));
+    // Non-Twirp JSON bodies and redirects are intermediary errors too
+    let err = response_err(StatusCode::BadGateway, Headers::new(), br#"{"error":"upstream"}"#);
+    assert_eq!(err.twirp_code(), Some("unavailable"));
+    let mut headers = Headers::new();
+    headers.set(Location::new("https://elsewhere/"));
+    let err = response_err(StatusCode::Found, headers, b"");
+    assert_eq!(class(&err), Class { body: Some(Vec::new()), ..twirp("internal", StatusCode::Found) });
+}
+
+#[test]
+fn nested_after_body_errors() {
+    // The outermost status and body win
+    let inner = response_err(StatusCode::BadGateway, Headers::new(), b"inner");
+    let outer = ProstTwirpError::AfterBodyError { body: b"outer".to_vec(), method: None, version: HttpVersion::Http11,
+        headers: Headers::new(), status: Some(StatusCode::GatewayTimeout), err: Box::new(inner) };
+    assert_eq!(class(&outer), Class { retryable: true, body: Some(b"outer".to_vec()),
+        ..twirp("unavailable", StatusCode::GatewayTimeout) });
+    // Server wrappers defer to what they wrap
+    let inner = response_err(StatusCode::BadGateway, Headers::new(), b"inner");
+    let outer = ProstTwirpError::AfterBodyError { body: b"request".to_vec(), method: Some(Method::Post),
+        version: HttpVersion::Http11, headers: Headers::new(), status: None, err: Box::new(inner) };
+    assert_eq!(class(&outer), Class { retryable: true, body: Some(b"inner".to_vec()),
+        ..twirp("unavailable", StatusCode::BadGateway) });
+    let timeout = ProstTwirpError::AfterBodyError { body: Vec::new(), method: Some(Method::Post),
+        version: HttpVersion::Http11, headers: Headers::new(), status: None,
+        err: Box::new(hyper(hyper::Error::Timeout)) };
+    assert_eq!(class(&timeout), Class { transport: true, timeout: true, retryable: true, ..plain() });
+}
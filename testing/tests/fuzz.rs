@@ -0,0 +1,104 @@
+//! Property tests running the fuzzing harnesses under `cargo test`.
+//!
+//! These cover the same targets as `fuzz/` for when cargo-fuzz and a nightly toolchain aren't available. Besides random
+//! bytes, inputs are generated close to valid so the deeper paths are reached too.
+
+extern crate prost;
+extern crate proptest;
+extern crate prost_twirp_testing;
+extern crate serde_json;
+
+use prost::Message;
+use proptest::collection::{btree_map, vec};
+use proptest::prelude::*;
+use prost_twirp_testing::{bench, fuzz, haberdasher};
+use serde_json::Value;
+
+const CODES: &[&str] = &["canceled", "unknown", "invalid_argument", "malformed", "deadline_exceeded", "not_found",
+    "bad_route", "already_exists", "permission_denied", "unauthenticated", "resource_exhausted", "failed_precondition",
+    "aborted", "out_of_range", "unimplemented", "internal", "unavailable", "dataloss"];
+
+fn json_value() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        any::<f64>().prop_map(Value::from),
+        ".*".prop_map(Value::from),
+    ];
+    leaf.prop_recursive(3, 32, 4, |inner| prop_oneof![
+        vec(inner.clone(), 0..4).prop_map(Value::from),
+        btree_map(".*", inner, 0..4).prop_map(|v| Value::Object(v.into_iter().collect())),
+    ])
+}
+
+/// A status followed by a JSON object that may look like a Twirp error
+fn error_json_input() -> impl Strategy<Value = Vec<u8>> {
+    let code = prop_oneof![prop::sample::select(CODES).prop_map(Value::from), json_value()];
+    (any::<u16>(), prop::option::of(code), prop::option::of(json_value()), prop::option::of(json_value()),
+        any::<bool>()).
+        prop_map(|(status, code, msg, meta, legacy)| {
+            let mut obj = serde_json::Map::new();
+            if let Some(code) = code { obj.insert(if legacy { "error_type" } else { "code" }.to_string(), code); }
+            if let Some(msg) = msg { obj.insert("msg".to_string(), msg); }
+            if let Some(meta) = meta { obj.insert("meta".to_string(), meta); }
+            let mut data = status.to_be_bytes().to_vec();
+            data.extend(serde_json::to_vec(&Value::Object(obj)).unwrap());
+            data
+        })
+}
+
+/// A path and flags followed by an optional length and a body that may be a valid message
+fn request_body_input() -> impl Strategy<Value = Vec<u8>> {
+    let body = prop_oneof![
+        vec(any::<u8>(), 0..256),
+        vec(any::<u8>(), 0..256).prop_map(|data| encode(&bench::Blob { data })),
+        any::<i32>().prop_map(|inches| encode(&haberdasher::Size { inches })),
+    ];
+    (any::<u8>(), any::<u8>(), prop_oneof![Just(None), any::<u64>().prop_map(Some), (0u64..256).prop_map(Some)],
+        body).
+        prop_map(|(path, flags, len, body)| {
+            let mut data = vec![path, if len.is_some() { flags | 1 } else { flags & !1 }];
+            if let Some(len) = len { data.extend(len.to_be_bytes().iter()); }
+            data.extend(body);
+            data
+        })
+}
+
+/// A known media type in mixed case with optional whitespace and parameters
+fn content_type_input() -> impl Strategy<Value = Vec<u8>> {
+    let media_types = &["application/protobuf", "application/x-protobuf", "application/json", "text/plain", ""];
+    (prop::sample::select(&media_types[..]), vec(any::<bool>(), 32), "[ \t]{0,2}", "[ \t]{0,2}",
+        "(;[ \t]?[a-zA-Z]{1,8}=[a-zA-Z0-9\"]{0,8}){0,3}").
+        prop_map(|(media_type, upper, before, after, params)| {
+            let media_type = media_type.chars().zip(upper.into_iter().chain(std::iter::repeat(false))).
+                map(|(c, upper)| if upper { c.to_ascii_uppercase() } else { c }).collect::<String>();
+            format!("{}{}{}{}", before, media_type, after, params).into_bytes()
+        })
+}
+
+fn encode<M: Message>(msg: &M) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    msg.encode(&mut bytes).unwrap();
+    bytes
+}
+
+proptest! {
+    #[test]
+    fn error_json_bytes(data in vec(any::<u8>(), 0..256)) { fuzz::error_json(&data); }
+
+    #[test]
+    fn error_json_objects(data in error_json_input()) { fuzz::error_json(&data); }
+
+    #[test]
+    fn request_body_bytes(data in vec(any::<u8>(), 0..512)) { fuzz::request_body(&data); }
+
+    #[test]
+    fn request_body_messages(data in request_body_input()) { fuzz::request_body(&data); }
+
+    #[test]
+    fn content_type_bytes(data in vec(any::<u8>(), 0..64)) { fuzz::content_type(&data); }
+
+    #[test]
+    fn content_type_media_types(data in content_type_input()) { fuzz::content_type(&data); }
+}
@@ -0,0 +1,271 @@
+//! Golden tests for the code generated by `TwirpServiceGenerator`.
+//!
+//! Each corpus file in `tests/golden` is compiled with prost-build and the service generator's output is compared to
+//! the `.rs` file next to it. The expected files are the output formatted with rustfmt, which must leave them as they
+//! are, and the default pretty printed output must format to the same code. Only the service code is compared, not
+//! prost's messages or the embedded runtime. To update the expected files after an intended change, run with
+//! `UPDATE_GOLDEN=1`. The `golden_compile` tests build the expected files.
+
+extern crate prost_build;
+extern crate prost_twirp;
+extern crate prost_types;
+
+use prost_build::{Comments, Method, Service, ServiceGenerator};
+use prost_twirp::TwirpServiceGenerator;
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::rc::Rc;
+
+/// The corpus file names without extension and whether the client is embedded
+const CORPUS: &[(&str, bool)] = &[
+    ("comments", false),
+    ("cross_package", false),
+    ("long_names", false),
+    ("method_names", false),
+    ("multi_service", false),
+    ("multi_service", true),
+    ("nested_package", false),
+    ("policies", false),
+    ("policies", true),
+    ("reserved_words", false),
+];
+
+/// Wraps the generator to record only what it generates for services
+struct Recording {
+    inner: TwirpServiceGenerator,
+    out: Rc<RefCell<String>>,
+}
+
+impl ServiceGenerator for Recording {
+    fn generate(&mut self, service: Service, buf: &mut String) {
+        let mut generated = String::new();
+        self.inner.generate(service, &mut generated);
+        self.out.borrow_mut().push_str(&generated);
+        buf.push_str(&generated);
+    }
+
+    fn finalize(&mut self, buf: &mut String) { self.inner.finalize(buf); }
+}
+
+fn golden_dir() -> PathBuf { Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden") }
+
+fn golden_path(name: &str, embed_client: bool) -> PathBuf {
+    golden_dir().join(if embed_client { format!("{}.embed.rs", name) } else { format!("{}.rs", name) })
+}
+
+/// Compile the corpus files together into a fresh directory, returning the service code and the directory
+///
+/// prost-build always writes to `OUT_DIR`, so this is done by `generate_in_child` in a process of its own with it set.
+fn compile(dir_name: &str, names: &[&str], embed_client: bool, rustfmt: bool) -> (String, PathBuf) {
+    let (mut child, services, out_dir) = child_command(dir_name, names, embed_client);
+    if rustfmt { child.env("GOLDEN_RUSTFMT", "1"); }
+    let output = child.output().unwrap();
+    assert!(output.status.success(), "generating {} failed:\n{}", dir_name, String::from_utf8_lossy(&output.stdout));
+    (fs::read_to_string(&services).unwrap(), out_dir)
+}
+
+/// The command running `generate_in_child` into a fresh directory, with the services file and the directory
+fn child_command(dir_name: &str, names: &[&str], embed_client: bool) -> (Command, PathBuf, PathBuf) {
+    let out_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden").join(dir_name);
+    let _ = fs::remove_dir_all(&out_dir);
+    fs::create_dir_all(&out_dir).unwrap();
+    let services = out_dir.with_extension("services.rs");
+    let mut child = Command::new(env::current_exe().unwrap());
+    child.args(["generate_in_child", "--exact", "--ignored", "--nocapture", "--test-threads=1"]).
+        env("OUT_DIR", &out_dir).env("GOLDEN_PROTOS", names.join(",")).env("GOLDEN_SERVICES", &services).
+        env("GOLDEN_EMBED_CLIENT", embed_client.to_string());
+    (child, services, out_dir)
+}
+
+#[test]
+#[ignore = "run by `compile` in a child process"]
+fn generate_in_child() {
+    let services = match env::var_os("GOLDEN_SERVICES") {
+        Some(services) => services,
+        None => return,
+    };
+    let protos = env::var("GOLDEN_PROTOS").unwrap().split(',').
+        map(|name| golden_dir().join(format!("{}.proto", name))).collect::<Vec<_>>();
+    let out = Rc::new(RefCell::new(String::new()));
+    let mut inner = TwirpServiceGenerator::new();
+    inner.embed_client = env::var("GOLDEN_EMBED_CLIENT").unwrap() == "true";
+    inner.rustfmt = env::var_os("GOLDEN_RUSTFMT").is_some();
+    let includes = [golden_dir(), TwirpServiceGenerator::options_include()];
+    if env::var_os("GOLDEN_SKIP_POLICIES").is_none() { inner.read_policies(&protos, &includes).unwrap(); }
+    let mut conf = prost_build::Config::new();
+    conf.service_generator(Box::new(Recording { inner, out: out.clone() }));
+    conf.compile_protos(&protos, &includes).unwrap();
+    fs::write(services, out.borrow().as_bytes()).unwrap();
+}
+
+/// Run the generator over the corpus file and return the service code
+fn generate(name: &str, embed_client: bool, rustfmt: bool) -> String {
+    let dir_name =
+        format!("{}{}{}", name, if embed_client { "_embed" } else { "" }, if rustfmt { "_rustfmt" } else { "" });
+    compile(&dir_name, &[name], embed_client, rustfmt).0
+}
+
+fn method(name: &str, proto_name: &str, input_type: &str, output_type: &str) -> Method {
+    Method {
+        name: name.to_string(),
+        proto_name: proto_name.to_string(),
+        comments: Comments { leading_detached: vec![], leading: vec![], trailing: vec![] },
+        input_type: input_type.to_string(),
+        output_type: output_type.to_string(),
+        input_proto_type: format!(".{}", input_type),
+        output_proto_type: format!(".{}", output_type),
+        options: Default::default(),
+        client_streaming: false,
+        server_streaming: false,
+    }
+}
+
+/// Run the generator over a service without a package
+///
+/// prost-build can't compile protos without a package, but other generators pass them along.
+fn generate_no_package(mut gen: TwirpServiceGenerator) -> String {
+    let service = Service {
+        name: "Greeter".to_string(),
+        proto_name: "Greeter".to_string(),
+        package: String::new(),
+        comments: Comments { leading_detached: vec![], leading: vec![" Greets without a package".to_string()],
+            trailing: vec![] },
+        methods: vec![
+            method("say_hello", "SayHello", "HelloRequest", "HelloResponse"),
+            method("say_goodbye", "SayGoodbye", "HelloRequest", "HelloResponse"),
+        ],
+        options: Default::default(),
+    };
+    let mut generated = String::new();
+    gen.generate(service, &mut generated);
+    gen.finalize(&mut generated);
+    generated
+}
+
+#[test]
+#[should_panic(expected = "methods \"GetX\" and \"get_x\" of service \"Greeter\" would both be generated as \"get_x\"")]
+fn duplicate_method_names() {
+    let service = Service {
+        name: "Greeter".to_string(),
+        proto_name: "Greeter".to_string(),
+        package: "golden.duplicate".to_string(),
+        comments: Comments { leading_detached: vec![], leading: vec![], trailing: vec![] },
+        methods: vec![
+            method("get_x", "GetX", "HelloRequest", "HelloResponse"),
+            method("get_x", "get_x", "HelloRequest", "HelloResponse"),
+        ],
+        options: Default::default(),
+    };
+    TwirpServiceGenerator::new().generate(service, &mut String::new());
+}
+
+#[test]
+fn policies_not_read() {
+    // Methods with policies must not be served unchecked because the build script forgot to read them
+    let (mut child, _, _) = child_command("policies_not_read", &["policies"], false);
+    let output = child.env("GOLDEN_SKIP_POLICIES", "1").output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("prost_twirp/options.proto is imported, but its policies weren't read"), "{}", stderr);
+}
+
+/// The expected file, the generated code formatted with rustfmt, and the default generated code for every case
+fn cases() -> Vec<(PathBuf, String, String)> {
+    let mut cases = CORPUS.iter().map(|&(name, embed_client)| {
+        (golden_path(name, embed_client), generate(name, embed_client, true), generate(name, embed_client, false))
+    }).collect::<Vec<_>>();
+    let mut gen = TwirpServiceGenerator::new();
+    gen.rustfmt = true;
+    cases.push((golden_path("no_package", false), generate_no_package(gen),
+        generate_no_package(TwirpServiceGenerator::new())));
+    cases
+}
+
+/// Run rustfmt over the code with the arguments, returning its exit code and output
+fn rustfmt(args: &[&str], code: &str) -> (Option<i32>, String) {
+    let mut child = Command::new(env::var_os("RUSTFMT").unwrap_or_else(|| "rustfmt".into())).
+        args(["--edition", "2015"]).args(args).
+        stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().
+        expect("rustfmt is needed for this test, install it with `rustup component add rustfmt`");
+    child.stdin.take().unwrap().write_all(code.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    match output.status.code() {
+        Some(0) | Some(1) => (output.status.code(), String::from_utf8(output.stdout).unwrap()),
+        _ => panic!("rustfmt failed: {}", String::from_utf8_lossy(&output.stderr)),
+    }
+}
+
+/// Check the code with `rustfmt --check`, returning the diff it prints if it isn't clean
+fn rustfmt_check(code: &str) -> Result<(), String> {
+    match rustfmt(&["--check", "--color", "never"], code) {
+        (Some(0), _) => Ok(()),
+        (_, diff) => Err(diff),
+    }
+}
+
+/// Format the code with rustfmt
+fn rustfmt_format(code: &str) -> String { rustfmt(&["--emit", "stdout", "--quiet"], code).1 }
+
+/// Describe the first line that differs between the expected and actual code
+fn first_difference(expected: &str, actual: &str) -> String {
+    let (mut expected_lines, mut actual_lines) = (expected.lines(), actual.lines());
+    for line in 1.. {
+        match (expected_lines.next(), actual_lines.next()) {
+            (None, None) => break,
+            (expected, actual) if expected != actual =>
+                return format!("line {}:\n  expected: {:?}\n    actual: {:?}", line, expected, actual),
+            _ => (),
+        }
+    }
+    "only line endings differ".to_string()
+}
+
+#[test]
+fn golden() {
+    let update = env::var_os("UPDATE_GOLDEN").is_some();
+    let mut failures = Vec::new();
+    for (path, formatted, generated) in cases() {
+        if update {
+            fs::write(&path, &formatted).unwrap();
+            continue;
+        }
+        let expected = fs::read_to_string(&path).unwrap_or_default();
+        if expected != formatted {
+            failures.push(format!("{} differs at {}", path.display(), first_difference(&expected, &formatted)));
+        }
+        if let Err(diff) = rustfmt_check(&expected) {
+            failures.push(format!("rustfmt would change {}:\n{}", path.display(), diff));
+        }
+        // The default output is only pretty printed, but must be the same code
+        let generated = rustfmt_format(&generated);
+        if expected != generated {
+            failures.push(format!("{} differs from the default output at {}", path.display(),
+                first_difference(&expected, &generated)));
+        }
+    }
+    assert!(failures.is_empty(), "generated code changed, rerun with UPDATE_GOLDEN=1 if intended:\n{}",
+        failures.join("\n"));
+}
+
+#[test]
+fn one_runtime_per_module() {
+    // Files of the same package share a module and imported files without services get their own
+    let names = &["multi_service", "multi_service_more", "cross_package"];
+    for &embed_client in &[false, true] {
+        let (generated, out_dir) =
+            compile(if embed_client { "modules_embed" } else { "modules" }, names, embed_client, false);
+        for &(module, services) in &[("golden.multi", 3), ("golden.cross", 1), ("golden.shared.types", 0)] {
+            let code = fs::read_to_string(out_dir.join(format!("{}.rs", module))).unwrap();
+            let has_services = services > 0;
+            assert_eq!(code.matches("\npub trait ").count(), services, "services in {}", module);
+            assert_eq!(code.matches("\npub type PTReq<").count(), has_services as usize, "aliases in {}", module);
+            assert_eq!(code.matches("\nmod prost_twirp {").count(), (has_services && embed_client) as usize,
+                "embedded client in {}", module);
+        }
+        assert_eq!(generated.matches("\npub trait ").count(), 4);
+    }
+}
@@ -0,0 +1,214 @@
+pub type PTReq<I> = ::prost_twirp::PTReq<I>;
+pub type PTRes<O, E = ::prost_twirp::ProstTwirpError> = ::prost_twirp::PTRes<O, E>;
+/// Commented has comments on the service and its methods.
+///
+/// The comments span multiple lines, with a blank one in between.
+pub trait Commented<E = ::prost_twirp::ProstTwirpError> {
+    /// Documented has a leading comment.
+    fn documented(&self, i: ::prost_twirp::PTReq<Empty>) -> ::prost_twirp::PTRes<Empty, E>;
+    /// Trailing has a trailing comment.
+    fn trailing(&self, i: ::prost_twirp::PTReq<Empty>) -> ::prost_twirp::PTRes<Empty, E>;
+    /// Both has a leading comment,
+    ///
+    /// and a trailing one.
+    fn both(&self, i: ::prost_twirp::PTReq<Empty>) -> ::prost_twirp::PTRes<Empty, E>;
+    fn undocumented(&self, i: ::prost_twirp::PTReq<Empty>) -> ::prost_twirp::PTRes<Empty, E>;
+}
+#[allow(dead_code)]
+impl dyn Commented {
+    /// The `Documented` method
+    pub const DOCUMENTED_METHOD: ::prost_twirp::MethodDescriptor =
+        ::prost_twirp::MethodDescriptor {
+            service: "golden.comments.Commented",
+            method: "Documented",
+            path: "/twirp/golden.comments.Commented/Documented",
+            policy: None,
+        };
+    /// The `Trailing` method
+    pub const TRAILING_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "golden.comments.Commented",
+        method: "Trailing",
+        path: "/twirp/golden.comments.Commented/Trailing",
+        policy: None,
+    };
+    /// The `Both` method
+    pub const BOTH_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "golden.comments.Commented",
+        method: "Both",
+        path: "/twirp/golden.comments.Commented/Both",
+        policy: None,
+    };
+    /// The `Undocumented` method
+    pub const UNDOCUMENTED_METHOD: ::prost_twirp::MethodDescriptor =
+        ::prost_twirp::MethodDescriptor {
+            service: "golden.comments.Commented",
+            method: "Undocumented",
+            path: "/twirp/golden.comments.Commented/Undocumented",
+            policy: None,
+        };
+    /// Every method of the service
+    pub const METHODS: &'static [::prost_twirp::MethodDescriptor] = &[
+        Self::DOCUMENTED_METHOD,
+        Self::TRAILING_METHOD,
+        Self::BOTH_METHOD,
+        Self::UNDOCUMENTED_METHOD,
+    ];
+    pub fn new_client(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+    ) -> Box<dyn Commented> {
+        Box::new(CommentedClient::new(client, root_url))
+    }
+    /// A client whose calls get their `Authorization` header from the credential provider
+    pub fn new_client_with_credentials<C>(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+        credentials: C,
+    ) -> Box<dyn Commented>
+    where
+        C: 'static + ::prost_twirp::CredentialProvider,
+    {
+        Box::new(CommentedClient::new_with_credentials(
+            client,
+            root_url,
+            credentials,
+        ))
+    }
+    pub fn new_server<T, E>(
+        v: T,
+    ) -> Box<
+        dyn 'static
+            + ::hyper::server::Service<
+                Request = ::hyper::Request,
+                Response = ::hyper::Response,
+                Error = ::hyper::Error,
+                Future = Box<
+                    dyn 'static
+                        + ::futures::Future<Item = ::hyper::Response, Error = ::hyper::Error>,
+                >,
+            >,
+    >
+    where
+        T: 'static + Commented<E>,
+        E: 'static + ::prost_twirp::IntoTwirpError,
+    {
+        let server = CommentedServer::new(v);
+        Box::new(::prost_twirp::HyperServer::new(server))
+    }
+}
+pub struct CommentedClient(pub ::prost_twirp::HyperClient);
+#[allow(dead_code)]
+impl CommentedClient {
+    pub fn new(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+    ) -> CommentedClient {
+        CommentedClient(::prost_twirp::HyperClient::new(client, root_url))
+    }
+    /// A client whose calls get their `Authorization` header from the credential provider
+    pub fn new_with_credentials<C>(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+        credentials: C,
+    ) -> CommentedClient
+    where
+        C: 'static + ::prost_twirp::CredentialProvider,
+    {
+        CommentedClient(
+            ::prost_twirp::HyperClient::new(client, root_url).with_credentials(credentials),
+        )
+    }
+    /// Documented has a leading comment.
+    pub fn call_documented(&self, i: Empty) -> ::prost_twirp::Call<Empty, Empty> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.comments.Commented/Documented",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+    /// Trailing has a trailing comment.
+    pub fn call_trailing(&self, i: Empty) -> ::prost_twirp::Call<Empty, Empty> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.comments.Commented/Trailing",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+    /// Both has a leading comment,
+    ///
+    /// and a trailing one.
+    pub fn call_both(&self, i: Empty) -> ::prost_twirp::Call<Empty, Empty> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.comments.Commented/Both",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+    pub fn call_undocumented(&self, i: Empty) -> ::prost_twirp::Call<Empty, Empty> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.comments.Commented/Undocumented",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+}
+impl Commented for CommentedClient {
+    fn documented(&self, i: ::prost_twirp::PTReq<Empty>) -> ::prost_twirp::PTRes<Empty> {
+        self.0.go("/twirp/golden.comments.Commented/Documented", i)
+    }
+    fn trailing(&self, i: ::prost_twirp::PTReq<Empty>) -> ::prost_twirp::PTRes<Empty> {
+        self.0.go("/twirp/golden.comments.Commented/Trailing", i)
+    }
+    fn both(&self, i: ::prost_twirp::PTReq<Empty>) -> ::prost_twirp::PTRes<Empty> {
+        self.0.go("/twirp/golden.comments.Commented/Both", i)
+    }
+    fn undocumented(&self, i: ::prost_twirp::PTReq<Empty>) -> ::prost_twirp::PTRes<Empty> {
+        self.0
+            .go("/twirp/golden.comments.Commented/Undocumented", i)
+    }
+}
+pub struct CommentedServer<T, E = ::prost_twirp::ProstTwirpError>(
+    ::std::sync::Arc<T>,
+    ::std::marker::PhantomData<fn() -> E>,
+)
+where
+    T: 'static + Commented<E>,
+    E: 'static;
+impl<T, E> CommentedServer<T, E>
+where
+    T: 'static + Commented<E>,
+    E: 'static,
+{
+    pub fn new(v: T) -> CommentedServer<T, E> {
+        let service = ::std::sync::Arc::new(v);
+        CommentedServer(service, ::std::marker::PhantomData)
+    }
+}
+impl<T, E> ::prost_twirp::HyperService for CommentedServer<T, E>
+where
+    T: 'static + Commented<E>,
+    E: 'static + ::prost_twirp::IntoTwirpError,
+{
+    fn handle(&self, req: ::prost_twirp::ServiceRequest<Vec<u8>>) -> ::prost_twirp::PTRes<Vec<u8>> {
+        let service = self.0.clone();
+        match (req.method.clone(), req.uri.path()) {
+            (::hyper::Method::Post, "/twirp/golden.comments.Commented/Documented") => {
+                req.handle_proto(move |v| service.documented(v))
+            }
+            (::hyper::Method::Post, "/twirp/golden.comments.Commented/Trailing") => {
+                req.handle_proto(move |v| service.trailing(v))
+            }
+            (::hyper::Method::Post, "/twirp/golden.comments.Commented/Both") => {
+                req.handle_proto(move |v| service.both(v))
+            }
+            (::hyper::Method::Post, "/twirp/golden.comments.Commented/Undocumented") => {
+                req.handle_proto(move |v| service.undocumented(v))
+            }
+            _ => {
+                let msg = format!("no handler for path {:?}", req.uri.path());
+                let err = ::prost_twirp::TwirpError::bad_route(&msg, &req.method, req.uri.path());
+                Box::new(::futures::future::ok(err.to_resp_raw()))
+            }
+        }
+    }
+}
@@ -0,0 +1,203 @@
+pub type PTReq<I> = ::prost_twirp::PTReq<I>;
+pub type PTRes<O, E = ::prost_twirp::ProstTwirpError> = ::prost_twirp::PTRes<O, E>;
+pub trait Courier<E = ::prost_twirp::ProstTwirpError> {
+    fn deliver(
+        &self,
+        i: ::prost_twirp::PTReq<super::shared::types::Envelope>,
+    ) -> ::prost_twirp::PTRes<Receipt, E>;
+    fn redeliver(
+        &self,
+        i: ::prost_twirp::PTReq<Receipt>,
+    ) -> ::prost_twirp::PTRes<super::shared::types::envelope::Payload, E>;
+    fn exchange(
+        &self,
+        i: ::prost_twirp::PTReq<super::shared::types::Token>,
+    ) -> ::prost_twirp::PTRes<super::shared::types::Token, E>;
+}
+#[allow(dead_code)]
+impl dyn Courier {
+    /// The `Deliver` method
+    pub const DELIVER_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "golden.cross.Courier",
+        method: "Deliver",
+        path: "/twirp/golden.cross.Courier/Deliver",
+        policy: None,
+    };
+    /// The `Redeliver` method
+    pub const REDELIVER_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "golden.cross.Courier",
+        method: "Redeliver",
+        path: "/twirp/golden.cross.Courier/Redeliver",
+        policy: None,
+    };
+    /// The `Exchange` method
+    pub const EXCHANGE_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "golden.cross.Courier",
+        method: "Exchange",
+        path: "/twirp/golden.cross.Courier/Exchange",
+        policy: None,
+    };
+    /// Every method of the service
+    pub const METHODS: &'static [::prost_twirp::MethodDescriptor] = &[
+        Self::DELIVER_METHOD,
+        Self::REDELIVER_METHOD,
+        Self::EXCHANGE_METHOD,
+    ];
+    pub fn new_client(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+    ) -> Box<dyn Courier> {
+        Box::new(CourierClient::new(client, root_url))
+    }
+    /// A client whose calls get their `Authorization` header from the credential provider
+    pub fn new_client_with_credentials<C>(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+        credentials: C,
+    ) -> Box<dyn Courier>
+    where
+        C: 'static + ::prost_twirp::CredentialProvider,
+    {
+        Box::new(CourierClient::new_with_credentials(
+            client,
+            root_url,
+            credentials,
+        ))
+    }
+    pub fn new_server<T, E>(
+        v: T,
+    ) -> Box<
+        dyn 'static
+            + ::hyper::server::Service<
+                Request = ::hyper::Request,
+                Response = ::hyper::Response,
+                Error = ::hyper::Error,
+                Future = Box<
+                    dyn 'static
+                        + ::futures::Future<Item = ::hyper::Response, Error = ::hyper::Error>,
+                >,
+            >,
+    >
+    where
+        T: 'static + Courier<E>,
+        E: 'static + ::prost_twirp::IntoTwirpError,
+    {
+        let server = CourierServer::new(v);
+        Box::new(::prost_twirp::HyperServer::new(server))
+    }
+}
+pub struct CourierClient(pub ::prost_twirp::HyperClient);
+#[allow(dead_code)]
+impl CourierClient {
+    pub fn new(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+    ) -> CourierClient {
+        CourierClient(::prost_twirp::HyperClient::new(client, root_url))
+    }
+    /// A client whose calls get their `Authorization` header from the credential provider
+    pub fn new_with_credentials<C>(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+        credentials: C,
+    ) -> CourierClient
+    where
+        C: 'static + ::prost_twirp::CredentialProvider,
+    {
+        CourierClient(
+            ::prost_twirp::HyperClient::new(client, root_url).with_credentials(credentials),
+        )
+    }
+    pub fn call_deliver(
+        &self,
+        i: super::shared::types::Envelope,
+    ) -> ::prost_twirp::Call<super::shared::types::Envelope, Receipt> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.cross.Courier/Deliver",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+    pub fn call_redeliver(
+        &self,
+        i: Receipt,
+    ) -> ::prost_twirp::Call<Receipt, super::shared::types::envelope::Payload> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.cross.Courier/Redeliver",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+    pub fn call_exchange(
+        &self,
+        i: super::shared::types::Token,
+    ) -> ::prost_twirp::Call<super::shared::types::Token, super::shared::types::Token> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.cross.Courier/Exchange",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+}
+impl Courier for CourierClient {
+    fn deliver(
+        &self,
+        i: ::prost_twirp::PTReq<super::shared::types::Envelope>,
+    ) -> ::prost_twirp::PTRes<Receipt> {
+        self.0.go("/twirp/golden.cross.Courier/Deliver", i)
+    }
+    fn redeliver(
+        &self,
+        i: ::prost_twirp::PTReq<Receipt>,
+    ) -> ::prost_twirp::PTRes<super::shared::types::envelope::Payload> {
+        self.0.go("/twirp/golden.cross.Courier/Redeliver", i)
+    }
+    fn exchange(
+        &self,
+        i: ::prost_twirp::PTReq<super::shared::types::Token>,
+    ) -> ::prost_twirp::PTRes<super::shared::types::Token> {
+        self.0.go("/twirp/golden.cross.Courier/Exchange", i)
+    }
+}
+pub struct CourierServer<T, E = ::prost_twirp::ProstTwirpError>(
+    ::std::sync::Arc<T>,
+    ::std::marker::PhantomData<fn() -> E>,
+)
+where
+    T: 'static + Courier<E>,
+    E: 'static;
+impl<T, E> CourierServer<T, E>
+where
+    T: 'static + Courier<E>,
+    E: 'static,
+{
+    pub fn new(v: T) -> CourierServer<T, E> {
+        let service = ::std::sync::Arc::new(v);
+        CourierServer(service, ::std::marker::PhantomData)
+    }
+}
+impl<T, E> ::prost_twirp::HyperService for CourierServer<T, E>
+where
+    T: 'static + Courier<E>,
+    E: 'static + ::prost_twirp::IntoTwirpError,
+{
+    fn handle(&self, req: ::prost_twirp::ServiceRequest<Vec<u8>>) -> ::prost_twirp::PTRes<Vec<u8>> {
+        let service = self.0.clone();
+        match (req.method.clone(), req.uri.path()) {
+            (::hyper::Method::Post, "/twirp/golden.cross.Courier/Deliver") => {
+                req.handle_proto(move |v| service.deliver(v))
+            }
+            (::hyper::Method::Post, "/twirp/golden.cross.Courier/Redeliver") => {
+                req.handle_proto(move |v| service.redeliver(v))
+            }
+            (::hyper::Method::Post, "/twirp/golden.cross.Courier/Exchange") => {
+                req.handle_proto(move |v| service.exchange(v))
+            }
+            _ => {
+                let msg = format!("no handler for path {:?}", req.uri.path());
+                let err = ::prost_twirp::TwirpError::bad_route(&msg, &req.method, req.uri.path());
+                Box::new(::futures::future::ok(err.to_resp_raw()))
+            }
+        }
+    }
+}
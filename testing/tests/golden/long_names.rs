@@ -0,0 +1,203 @@
+pub type PTReq<I> = ::prost_twirp::PTReq<I>;
+pub type PTRes<O, E = ::prost_twirp::ProstTwirpError> = ::prost_twirp::PTRes<O, E>;
+/// AnExtremelyDescriptivelyNamedService has names long enough that generated lines need wrapping.
+pub trait AnExtremelyDescriptivelyNamedService<E = ::prost_twirp::ProstTwirpError> {
+    fn get_the_resource_by_its_fully_qualified_name(
+        &self,
+        i: ::prost_twirp::PTReq<GetTheResourceByItsFullyQualifiedNameRequest>,
+    ) -> ::prost_twirp::PTRes<GetTheResourceByItsFullyQualifiedNameResponse, E>;
+    fn short(
+        &self,
+        i: ::prost_twirp::PTReq<GetTheResourceByItsFullyQualifiedNameRequest>,
+    ) -> ::prost_twirp::PTRes<GetTheResourceByItsFullyQualifiedNameRequest, E>;
+}
+#[allow(dead_code)]
+impl dyn AnExtremelyDescriptivelyNamedService {
+    /// The `GetTheResourceByItsFullyQualifiedName` method
+    pub const GET_THE_RESOURCE_BY_ITS_FULLY_QUALIFIED_NAME_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "golden.a.rather.long.package.name.for.testing.line.wrapping.v1.AnExtremelyDescriptivelyNamedService",
+        method: "GetTheResourceByItsFullyQualifiedName",
+        path: "/twirp/golden.a.rather.long.package.name.for.testing.line.wrapping.v1.AnExtremelyDescriptivelyNamedService/GetTheResourceByItsFullyQualifiedName",
+        policy: None,
+    };
+    /// The `Short` method
+    pub const SHORT_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "golden.a.rather.long.package.name.for.testing.line.wrapping.v1.AnExtremelyDescriptivelyNamedService",
+        method: "Short",
+        path: "/twirp/golden.a.rather.long.package.name.for.testing.line.wrapping.v1.AnExtremelyDescriptivelyNamedService/Short",
+        policy: None,
+    };
+    /// Every method of the service
+    pub const METHODS: &'static [::prost_twirp::MethodDescriptor] = &[
+        Self::GET_THE_RESOURCE_BY_ITS_FULLY_QUALIFIED_NAME_METHOD,
+        Self::SHORT_METHOD,
+    ];
+    pub fn new_client(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+    ) -> Box<dyn AnExtremelyDescriptivelyNamedService> {
+        Box::new(AnExtremelyDescriptivelyNamedServiceClient::new(
+            client, root_url,
+        ))
+    }
+    /// A client whose calls get their `Authorization` header from the credential provider
+    pub fn new_client_with_credentials<C>(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+        credentials: C,
+    ) -> Box<dyn AnExtremelyDescriptivelyNamedService>
+    where
+        C: 'static + ::prost_twirp::CredentialProvider,
+    {
+        Box::new(
+            AnExtremelyDescriptivelyNamedServiceClient::new_with_credentials(
+                client,
+                root_url,
+                credentials,
+            ),
+        )
+    }
+    pub fn new_server<T, E>(
+        v: T,
+    ) -> Box<
+        dyn 'static
+            + ::hyper::server::Service<
+                Request = ::hyper::Request,
+                Response = ::hyper::Response,
+                Error = ::hyper::Error,
+                Future = Box<
+                    dyn 'static
+                        + ::futures::Future<Item = ::hyper::Response, Error = ::hyper::Error>,
+                >,
+            >,
+    >
+    where
+        T: 'static + AnExtremelyDescriptivelyNamedService<E>,
+        E: 'static + ::prost_twirp::IntoTwirpError,
+    {
+        let server = AnExtremelyDescriptivelyNamedServiceServer::new(v);
+        Box::new(::prost_twirp::HyperServer::new(server))
+    }
+}
+pub struct AnExtremelyDescriptivelyNamedServiceClient(pub ::prost_twirp::HyperClient);
+#[allow(dead_code)]
+impl AnExtremelyDescriptivelyNamedServiceClient {
+    pub fn new(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+    ) -> AnExtremelyDescriptivelyNamedServiceClient {
+        AnExtremelyDescriptivelyNamedServiceClient(::prost_twirp::HyperClient::new(
+            client, root_url,
+        ))
+    }
+    /// A client whose calls get their `Authorization` header from the credential provider
+    pub fn new_with_credentials<C>(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+        credentials: C,
+    ) -> AnExtremelyDescriptivelyNamedServiceClient
+    where
+        C: 'static + ::prost_twirp::CredentialProvider,
+    {
+        AnExtremelyDescriptivelyNamedServiceClient(
+            ::prost_twirp::HyperClient::new(client, root_url).with_credentials(credentials),
+        )
+    }
+    pub fn call_get_the_resource_by_its_fully_qualified_name(
+        &self,
+        i: GetTheResourceByItsFullyQualifiedNameRequest,
+    ) -> ::prost_twirp::Call<
+        GetTheResourceByItsFullyQualifiedNameRequest,
+        GetTheResourceByItsFullyQualifiedNameResponse,
+    > {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.a.rather.long.package.name.for.testing.line.wrapping.v1.AnExtremelyDescriptivelyNamedService/GetTheResourceByItsFullyQualifiedName",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+    pub fn call_short(
+        &self,
+        i: GetTheResourceByItsFullyQualifiedNameRequest,
+    ) -> ::prost_twirp::Call<
+        GetTheResourceByItsFullyQualifiedNameRequest,
+        GetTheResourceByItsFullyQualifiedNameRequest,
+    > {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.a.rather.long.package.name.for.testing.line.wrapping.v1.AnExtremelyDescriptivelyNamedService/Short",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+}
+impl AnExtremelyDescriptivelyNamedService for AnExtremelyDescriptivelyNamedServiceClient {
+    fn get_the_resource_by_its_fully_qualified_name(
+        &self,
+        i: ::prost_twirp::PTReq<GetTheResourceByItsFullyQualifiedNameRequest>,
+    ) -> ::prost_twirp::PTRes<GetTheResourceByItsFullyQualifiedNameResponse> {
+        self.0
+            .go(
+                "/twirp/golden.a.rather.long.package.name.for.testing.line.wrapping.v1.AnExtremelyDescriptivelyNamedService/GetTheResourceByItsFullyQualifiedName",
+                i,
+            )
+    }
+    fn short(
+        &self,
+        i: ::prost_twirp::PTReq<GetTheResourceByItsFullyQualifiedNameRequest>,
+    ) -> ::prost_twirp::PTRes<GetTheResourceByItsFullyQualifiedNameRequest> {
+        self.0
+            .go(
+                "/twirp/golden.a.rather.long.package.name.for.testing.line.wrapping.v1.AnExtremelyDescriptivelyNamedService/Short",
+                i,
+            )
+    }
+}
+pub struct AnExtremelyDescriptivelyNamedServiceServer<T, E = ::prost_twirp::ProstTwirpError>(
+    ::std::sync::Arc<T>,
+    ::std::marker::PhantomData<fn() -> E>,
+)
+where
+    T: 'static + AnExtremelyDescriptivelyNamedService<E>,
+    E: 'static;
+impl<T, E> AnExtremelyDescriptivelyNamedServiceServer<T, E>
+where
+    T: 'static + AnExtremelyDescriptivelyNamedService<E>,
+    E: 'static,
+{
+    pub fn new(v: T) -> AnExtremelyDescriptivelyNamedServiceServer<T, E> {
+        let service = ::std::sync::Arc::new(v);
+        AnExtremelyDescriptivelyNamedServiceServer(service, ::std::marker::PhantomData)
+    }
+}
+impl<T, E> ::prost_twirp::HyperService for AnExtremelyDescriptivelyNamedServiceServer<T, E>
+where
+    T: 'static + AnExtremelyDescriptivelyNamedService<E>,
+    E: 'static + ::prost_twirp::IntoTwirpError,
+{
+    fn handle(&self, req: ::prost_twirp::ServiceRequest<Vec<u8>>) -> ::prost_twirp::PTRes<Vec<u8>> {
+        let service = self.0.clone();
+        match (req.method.clone(), req.uri.path()) {
+            (
+                ::hyper::Method::Post,
+                "/twirp/golden.a.rather.long.package.name.for.testing.line.wrapping.v1.AnExtremelyDescriptivelyNamedService/GetTheResourceByItsFullyQualifiedName",
+            ) => {
+                req.handle_proto(move |v| {
+                    service.get_the_resource_by_its_fully_qualified_name(v)
+                })
+            }
+            (
+                ::hyper::Method::Post,
+                "/twirp/golden.a.rather.long.package.name.for.testing.line.wrapping.v1.AnExtremelyDescriptivelyNamedService/Short",
+            ) => req.handle_proto(move |v| service.short(v)),
+            _ => {
+                let msg = format!("no handler for path {:?}", req.uri.path());
+                let err = ::prost_twirp::TwirpError::bad_route(
+                    &msg,
+                    &req.method,
+                    req.uri.path(),
+                );
+                Box::new(::futures::future::ok(err.to_resp_raw()))
+            }
+        }
+    }
+}
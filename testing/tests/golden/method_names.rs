@@ -0,0 +1,224 @@
+pub type PTReq<I> = ::prost_twirp::PTReq<I>;
+pub type PTRes<O, E = ::prost_twirp::ProstTwirpError> = ::prost_twirp::PTRes<O, E>;
+/// Registry has methods named like the items generated for every service.
+pub trait Registry<E = ::prost_twirp::ProstTwirpError> {
+    fn methods(&self, i: ::prost_twirp::PTReq<Empty>) -> ::prost_twirp::PTRes<Empty, E>;
+    fn all_methods(&self, i: ::prost_twirp::PTReq<Empty>) -> ::prost_twirp::PTRes<Empty, E>;
+    fn new(&self, i: ::prost_twirp::PTReq<Empty>) -> ::prost_twirp::PTRes<Empty, E>;
+    fn new_client(&self, i: ::prost_twirp::PTReq<Empty>) -> ::prost_twirp::PTRes<Empty, E>;
+    fn new_server(&self, i: ::prost_twirp::PTReq<Empty>) -> ::prost_twirp::PTRes<Empty, E>;
+}
+#[allow(dead_code)]
+impl dyn Registry {
+    /// The `Methods` method
+    pub const METHODS_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "golden.names.Registry",
+        method: "Methods",
+        path: "/twirp/golden.names.Registry/Methods",
+        policy: None,
+    };
+    /// The `AllMethods` method
+    pub const ALL_METHODS_METHOD: ::prost_twirp::MethodDescriptor =
+        ::prost_twirp::MethodDescriptor {
+            service: "golden.names.Registry",
+            method: "AllMethods",
+            path: "/twirp/golden.names.Registry/AllMethods",
+            policy: None,
+        };
+    /// The `New` method
+    pub const NEW_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "golden.names.Registry",
+        method: "New",
+        path: "/twirp/golden.names.Registry/New",
+        policy: None,
+    };
+    /// The `NewClient` method
+    pub const NEW_CLIENT_METHOD: ::prost_twirp::MethodDescriptor =
+        ::prost_twirp::MethodDescriptor {
+            service: "golden.names.Registry",
+            method: "NewClient",
+            path: "/twirp/golden.names.Registry/NewClient",
+            policy: None,
+        };
+    /// The `NewServer` method
+    pub const NEW_SERVER_METHOD: ::prost_twirp::MethodDescriptor =
+        ::prost_twirp::MethodDescriptor {
+            service: "golden.names.Registry",
+            method: "NewServer",
+            path: "/twirp/golden.names.Registry/NewServer",
+            policy: None,
+        };
+    /// Every method of the service
+    pub const METHODS: &'static [::prost_twirp::MethodDescriptor] = &[
+        Self::METHODS_METHOD,
+        Self::ALL_METHODS_METHOD,
+        Self::NEW_METHOD,
+        Self::NEW_CLIENT_METHOD,
+        Self::NEW_SERVER_METHOD,
+    ];
+    pub fn new_client(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+    ) -> Box<dyn Registry> {
+        Box::new(RegistryClient::new(client, root_url))
+    }
+    /// A client whose calls get their `Authorization` header from the credential provider
+    pub fn new_client_with_credentials<C>(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+        credentials: C,
+    ) -> Box<dyn Registry>
+    where
+        C: 'static + ::prost_twirp::CredentialProvider,
+    {
+        Box::new(RegistryClient::new_with_credentials(
+            client,
+            root_url,
+            credentials,
+        ))
+    }
+    pub fn new_server<T, E>(
+        v: T,
+    ) -> Box<
+        dyn 'static
+            + ::hyper::server::Service<
+                Request = ::hyper::Request,
+                Response = ::hyper::Response,
+                Error = ::hyper::Error,
+                Future = Box<
+                    dyn 'static
+                        + ::futures::Future<Item = ::hyper::Response, Error = ::hyper::Error>,
+                >,
+            >,
+    >
+    where
+        T: 'static + Registry<E>,
+        E: 'static + ::prost_twirp::IntoTwirpError,
+    {
+        let server = RegistryServer::new(v);
+        Box::new(::prost_twirp::HyperServer::new(server))
+    }
+}
+pub struct RegistryClient(pub ::prost_twirp::HyperClient);
+#[allow(dead_code)]
+impl RegistryClient {
+    pub fn new(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+    ) -> RegistryClient {
+        RegistryClient(::prost_twirp::HyperClient::new(client, root_url))
+    }
+    /// A client whose calls get their `Authorization` header from the credential provider
+    pub fn new_with_credentials<C>(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+        credentials: C,
+    ) -> RegistryClient
+    where
+        C: 'static + ::prost_twirp::CredentialProvider,
+    {
+        RegistryClient(
+            ::prost_twirp::HyperClient::new(client, root_url).with_credentials(credentials),
+        )
+    }
+    pub fn call_methods(&self, i: Empty) -> ::prost_twirp::Call<Empty, Empty> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.names.Registry/Methods",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+    pub fn call_all_methods(&self, i: Empty) -> ::prost_twirp::Call<Empty, Empty> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.names.Registry/AllMethods",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+    pub fn call_new(&self, i: Empty) -> ::prost_twirp::Call<Empty, Empty> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.names.Registry/New",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+    pub fn call_new_client(&self, i: Empty) -> ::prost_twirp::Call<Empty, Empty> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.names.Registry/NewClient",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+    pub fn call_new_server(&self, i: Empty) -> ::prost_twirp::Call<Empty, Empty> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.names.Registry/NewServer",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+}
+impl Registry for RegistryClient {
+    fn methods(&self, i: ::prost_twirp::PTReq<Empty>) -> ::prost_twirp::PTRes<Empty> {
+        self.0.go("/twirp/golden.names.Registry/Methods", i)
+    }
+    fn all_methods(&self, i: ::prost_twirp::PTReq<Empty>) -> ::prost_twirp::PTRes<Empty> {
+        self.0.go("/twirp/golden.names.Registry/AllMethods", i)
+    }
+    fn new(&self, i: ::prost_twirp::PTReq<Empty>) -> ::prost_twirp::PTRes<Empty> {
+        self.0.go("/twirp/golden.names.Registry/New", i)
+    }
+    fn new_client(&self, i: ::prost_twirp::PTReq<Empty>) -> ::prost_twirp::PTRes<Empty> {
+        self.0.go("/twirp/golden.names.Registry/NewClient", i)
+    }
+    fn new_server(&self, i: ::prost_twirp::PTReq<Empty>) -> ::prost_twirp::PTRes<Empty> {
+        self.0.go("/twirp/golden.names.Registry/NewServer", i)
+    }
+}
+pub struct RegistryServer<T, E = ::prost_twirp::ProstTwirpError>(
+    ::std::sync::Arc<T>,
+    ::std::marker::PhantomData<fn() -> E>,
+)
+where
+    T: 'static + Registry<E>,
+    E: 'static;
+impl<T, E> RegistryServer<T, E>
+where
+    T: 'static + Registry<E>,
+    E: 'static,
+{
+    pub fn new(v: T) -> RegistryServer<T, E> {
+        let service = ::std::sync::Arc::new(v);
+        RegistryServer(service, ::std::marker::PhantomData)
+    }
+}
+impl<T, E> ::prost_twirp::HyperService for RegistryServer<T, E>
+where
+    T: 'static + Registry<E>,
+    E: 'static + ::prost_twirp::IntoTwirpError,
+{
+    fn handle(&self, req: ::prost_twirp::ServiceRequest<Vec<u8>>) -> ::prost_twirp::PTRes<Vec<u8>> {
+        let service = self.0.clone();
+        match (req.method.clone(), req.uri.path()) {
+            (::hyper::Method::Post, "/twirp/golden.names.Registry/Methods") => {
+                req.handle_proto(move |v| service.methods(v))
+            }
+            (::hyper::Method::Post, "/twirp/golden.names.Registry/AllMethods") => {
+                req.handle_proto(move |v| service.all_methods(v))
+            }
+            (::hyper::Method::Post, "/twirp/golden.names.Registry/New") => {
+                req.handle_proto(move |v| service.new(v))
+            }
+            (::hyper::Method::Post, "/twirp/golden.names.Registry/NewClient") => {
+                req.handle_proto(move |v| service.new_client(v))
+            }
+            (::hyper::Method::Post, "/twirp/golden.names.Registry/NewServer") => {
+                req.handle_proto(move |v| service.new_server(v))
+            }
+            _ => {
+                let msg = format!("no handler for path {:?}", req.uri.path());
+                let err = ::prost_twirp::TwirpError::bad_route(&msg, &req.method, req.uri.path());
+                Box::new(::futures::future::ok(err.to_resp_raw()))
+            }
+        }
+    }
+}
@@ -0,0 +1,277 @@
+pub type PTReq<I> = prost_twirp::PTReq<I>;
+pub type PTRes<O, E = prost_twirp::ProstTwirpError> = prost_twirp::PTRes<O, E>;
+pub trait Pinger<E = prost_twirp::ProstTwirpError> {
+    fn ping(&self, i: prost_twirp::PTReq<PingRequest>) -> prost_twirp::PTRes<PingResponse, E>;
+    fn ping_again(&self, i: prost_twirp::PTReq<PingRequest>)
+        -> prost_twirp::PTRes<PingResponse, E>;
+}
+#[allow(dead_code)]
+impl dyn Pinger {
+    /// The `Ping` method
+    pub const PING_METHOD: prost_twirp::MethodDescriptor = prost_twirp::MethodDescriptor {
+        service: "golden.multi.Pinger",
+        method: "Ping",
+        path: "/twirp/golden.multi.Pinger/Ping",
+        policy: None,
+    };
+    /// The `PingAgain` method
+    pub const PING_AGAIN_METHOD: prost_twirp::MethodDescriptor = prost_twirp::MethodDescriptor {
+        service: "golden.multi.Pinger",
+        method: "PingAgain",
+        path: "/twirp/golden.multi.Pinger/PingAgain",
+        policy: None,
+    };
+    /// Every method of the service
+    pub const METHODS: &'static [prost_twirp::MethodDescriptor] =
+        &[Self::PING_METHOD, Self::PING_AGAIN_METHOD];
+    pub fn new_client(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+    ) -> Box<dyn Pinger> {
+        Box::new(PingerClient::new(client, root_url))
+    }
+    /// A client whose calls get their `Authorization` header from the credential provider
+    pub fn new_client_with_credentials<C>(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+        credentials: C,
+    ) -> Box<dyn Pinger>
+    where
+        C: 'static + prost_twirp::CredentialProvider,
+    {
+        Box::new(PingerClient::new_with_credentials(
+            client,
+            root_url,
+            credentials,
+        ))
+    }
+    pub fn new_server<T, E>(
+        v: T,
+    ) -> Box<
+        dyn 'static
+            + ::hyper::server::Service<
+                Request = ::hyper::Request,
+                Response = ::hyper::Response,
+                Error = ::hyper::Error,
+                Future = Box<
+                    dyn 'static
+                        + ::futures::Future<Item = ::hyper::Response, Error = ::hyper::Error>,
+                >,
+            >,
+    >
+    where
+        T: 'static + Pinger<E>,
+        E: 'static + prost_twirp::IntoTwirpError,
+    {
+        let server = PingerServer::new(v);
+        Box::new(prost_twirp::HyperServer::new(server))
+    }
+}
+pub struct PingerClient(pub prost_twirp::HyperClient);
+#[allow(dead_code)]
+impl PingerClient {
+    pub fn new(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+    ) -> PingerClient {
+        PingerClient(prost_twirp::HyperClient::new(client, root_url))
+    }
+    /// A client whose calls get their `Authorization` header from the credential provider
+    pub fn new_with_credentials<C>(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+        credentials: C,
+    ) -> PingerClient
+    where
+        C: 'static + prost_twirp::CredentialProvider,
+    {
+        PingerClient(prost_twirp::HyperClient::new(client, root_url).with_credentials(credentials))
+    }
+    pub fn call_ping(&self, i: PingRequest) -> prost_twirp::Call<PingRequest, PingResponse> {
+        prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.multi.Pinger/Ping",
+            prost_twirp::ServiceRequest::new(i),
+        )
+    }
+    pub fn call_ping_again(&self, i: PingRequest) -> prost_twirp::Call<PingRequest, PingResponse> {
+        prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.multi.Pinger/PingAgain",
+            prost_twirp::ServiceRequest::new(i),
+        )
+    }
+}
+impl Pinger for PingerClient {
+    fn ping(&self, i: prost_twirp::PTReq<PingRequest>) -> prost_twirp::PTRes<PingResponse> {
+        self.0.go("/twirp/golden.multi.Pinger/Ping", i)
+    }
+    fn ping_again(&self, i: prost_twirp::PTReq<PingRequest>) -> prost_twirp::PTRes<PingResponse> {
+        self.0.go("/twirp/golden.multi.Pinger/PingAgain", i)
+    }
+}
+pub struct PingerServer<T, E = prost_twirp::ProstTwirpError>(
+    ::std::sync::Arc<T>,
+    ::std::marker::PhantomData<fn() -> E>,
+)
+where
+    T: 'static + Pinger<E>,
+    E: 'static;
+impl<T, E> PingerServer<T, E>
+where
+    T: 'static + Pinger<E>,
+    E: 'static,
+{
+    pub fn new(v: T) -> PingerServer<T, E> {
+        let service = ::std::sync::Arc::new(v);
+        PingerServer(service, ::std::marker::PhantomData)
+    }
+}
+impl<T, E> prost_twirp::HyperService for PingerServer<T, E>
+where
+    T: 'static + Pinger<E>,
+    E: 'static + prost_twirp::IntoTwirpError,
+{
+    fn handle(&self, req: prost_twirp::ServiceRequest<Vec<u8>>) -> prost_twirp::PTRes<Vec<u8>> {
+        let service = self.0.clone();
+        match (req.method.clone(), req.uri.path()) {
+            (::hyper::Method::Post, "/twirp/golden.multi.Pinger/Ping") => {
+                req.handle_proto(move |v| service.ping(v))
+            }
+            (::hyper::Method::Post, "/twirp/golden.multi.Pinger/PingAgain") => {
+                req.handle_proto(move |v| service.ping_again(v))
+            }
+            _ => {
+                let msg = format!("no handler for path {:?}", req.uri.path());
+                let err = prost_twirp::TwirpError::bad_route(&msg, &req.method, req.uri.path());
+                Box::new(::futures::future::ok(err.to_resp_raw()))
+            }
+        }
+    }
+}
+pub trait Ponger<E = prost_twirp::ProstTwirpError> {
+    fn pong(&self, i: prost_twirp::PTReq<PingResponse>) -> prost_twirp::PTRes<PingRequest, E>;
+}
+#[allow(dead_code)]
+impl dyn Ponger {
+    /// The `Pong` method
+    pub const PONG_METHOD: prost_twirp::MethodDescriptor = prost_twirp::MethodDescriptor {
+        service: "golden.multi.Ponger",
+        method: "Pong",
+        path: "/twirp/golden.multi.Ponger/Pong",
+        policy: None,
+    };
+    /// Every method of the service
+    pub const METHODS: &'static [prost_twirp::MethodDescriptor] = &[Self::PONG_METHOD];
+    pub fn new_client(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+    ) -> Box<dyn Ponger> {
+        Box::new(PongerClient::new(client, root_url))
+    }
+    /// A client whose calls get their `Authorization` header from the credential provider
+    pub fn new_client_with_credentials<C>(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+        credentials: C,
+    ) -> Box<dyn Ponger>
+    where
+        C: 'static + prost_twirp::CredentialProvider,
+    {
+        Box::new(PongerClient::new_with_credentials(
+            client,
+            root_url,
+            credentials,
+        ))
+    }
+    pub fn new_server<T, E>(
+        v: T,
+    ) -> Box<
+        dyn 'static
+            + ::hyper::server::Service<
+                Request = ::hyper::Request,
+                Response = ::hyper::Response,
+                Error = ::hyper::Error,
+                Future = Box<
+                    dyn 'static
+                        + ::futures::Future<Item = ::hyper::Response, Error = ::hyper::Error>,
+                >,
+            >,
+    >
+    where
+        T: 'static + Ponger<E>,
+        E: 'static + prost_twirp::IntoTwirpError,
+    {
+        let server = PongerServer::new(v);
+        Box::new(prost_twirp::HyperServer::new(server))
+    }
+}
+pub struct PongerClient(pub prost_twirp::HyperClient);
+#[allow(dead_code)]
+impl PongerClient {
+    pub fn new(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+    ) -> PongerClient {
+        PongerClient(prost_twirp::HyperClient::new(client, root_url))
+    }
+    /// A client whose calls get their `Authorization` header from the credential provider
+    pub fn new_with_credentials<C>(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+        credentials: C,
+    ) -> PongerClient
+    where
+        C: 'static + prost_twirp::CredentialProvider,
+    {
+        PongerClient(prost_twirp::HyperClient::new(client, root_url).with_credentials(credentials))
+    }
+    pub fn call_pong(&self, i: PingResponse) -> prost_twirp::Call<PingResponse, PingRequest> {
+        prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.multi.Ponger/Pong",
+            prost_twirp::ServiceRequest::new(i),
+        )
+    }
+}
+impl Ponger for PongerClient {
+    fn pong(&self, i: prost_twirp::PTReq<PingResponse>) -> prost_twirp::PTRes<PingRequest> {
+        self.0.go("/twirp/golden.multi.Ponger/Pong", i)
+    }
+}
+pub struct PongerServer<T, E = prost_twirp::ProstTwirpError>(
+    ::std::sync::Arc<T>,
+    ::std::marker::PhantomData<fn() -> E>,
+)
+where
+    T: 'static + Ponger<E>,
+    E: 'static;
+impl<T, E> PongerServer<T, E>
+where
+    T: 'static + Ponger<E>,
+    E: 'static,
+{
+    pub fn new(v: T) -> PongerServer<T, E> {
+        let service = ::std::sync::Arc::new(v);
+        PongerServer(service, ::std::marker::PhantomData)
+    }
+}
+impl<T, E> prost_twirp::HyperService for PongerServer<T, E>
+where
+    T: 'static + Ponger<E>,
+    E: 'static + prost_twirp::IntoTwirpError,
+{
+    fn handle(&self, req: prost_twirp::ServiceRequest<Vec<u8>>) -> prost_twirp::PTRes<Vec<u8>> {
+        let service = self.0.clone();
+        match (req.method.clone(), req.uri.path()) {
+            (::hyper::Method::Post, "/twirp/golden.multi.Ponger/Pong") => {
+                req.handle_proto(move |v| service.pong(v))
+            }
+            _ => {
+                let msg = format!("no handler for path {:?}", req.uri.path());
+                let err = prost_twirp::TwirpError::bad_route(&msg, &req.method, req.uri.path());
+                Box::new(::futures::future::ok(err.to_resp_raw()))
+            }
+        }
+    }
+}
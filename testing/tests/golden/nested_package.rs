@@ -0,0 +1,154 @@
+pub type PTReq<I> = ::prost_twirp::PTReq<I>;
+pub type PTRes<O, E = ::prost_twirp::ProstTwirpError> = ::prost_twirp::PTRes<O, E>;
+pub trait NestedService<E = ::prost_twirp::ProstTwirpError> {
+    fn get_inner(&self, i: ::prost_twirp::PTReq<Outer>) -> ::prost_twirp::PTRes<outer::Inner, E>;
+    fn put_inner(&self, i: ::prost_twirp::PTReq<outer::Inner>) -> ::prost_twirp::PTRes<Outer, E>;
+}
+#[allow(dead_code)]
+impl dyn NestedService {
+    /// The `GetInner` method
+    pub const GET_INNER_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "golden.nested.deeper.v1.NestedService",
+        method: "GetInner",
+        path: "/twirp/golden.nested.deeper.v1.NestedService/GetInner",
+        policy: None,
+    };
+    /// The `PutInner` method
+    pub const PUT_INNER_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "golden.nested.deeper.v1.NestedService",
+        method: "PutInner",
+        path: "/twirp/golden.nested.deeper.v1.NestedService/PutInner",
+        policy: None,
+    };
+    /// Every method of the service
+    pub const METHODS: &'static [::prost_twirp::MethodDescriptor] =
+        &[Self::GET_INNER_METHOD, Self::PUT_INNER_METHOD];
+    pub fn new_client(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+    ) -> Box<dyn NestedService> {
+        Box::new(NestedServiceClient::new(client, root_url))
+    }
+    /// A client whose calls get their `Authorization` header from the credential provider
+    pub fn new_client_with_credentials<C>(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+        credentials: C,
+    ) -> Box<dyn NestedService>
+    where
+        C: 'static + ::prost_twirp::CredentialProvider,
+    {
+        Box::new(NestedServiceClient::new_with_credentials(
+            client,
+            root_url,
+            credentials,
+        ))
+    }
+    pub fn new_server<T, E>(
+        v: T,
+    ) -> Box<
+        dyn 'static
+            + ::hyper::server::Service<
+                Request = ::hyper::Request,
+                Response = ::hyper::Response,
+                Error = ::hyper::Error,
+                Future = Box<
+                    dyn 'static
+                        + ::futures::Future<Item = ::hyper::Response, Error = ::hyper::Error>,
+                >,
+            >,
+    >
+    where
+        T: 'static + NestedService<E>,
+        E: 'static + ::prost_twirp::IntoTwirpError,
+    {
+        let server = NestedServiceServer::new(v);
+        Box::new(::prost_twirp::HyperServer::new(server))
+    }
+}
+pub struct NestedServiceClient(pub ::prost_twirp::HyperClient);
+#[allow(dead_code)]
+impl NestedServiceClient {
+    pub fn new(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+    ) -> NestedServiceClient {
+        NestedServiceClient(::prost_twirp::HyperClient::new(client, root_url))
+    }
+    /// A client whose calls get their `Authorization` header from the credential provider
+    pub fn new_with_credentials<C>(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+        credentials: C,
+    ) -> NestedServiceClient
+    where
+        C: 'static + ::prost_twirp::CredentialProvider,
+    {
+        NestedServiceClient(
+            ::prost_twirp::HyperClient::new(client, root_url).with_credentials(credentials),
+        )
+    }
+    pub fn call_get_inner(&self, i: Outer) -> ::prost_twirp::Call<Outer, outer::Inner> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.nested.deeper.v1.NestedService/GetInner",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+    pub fn call_put_inner(&self, i: outer::Inner) -> ::prost_twirp::Call<outer::Inner, Outer> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.nested.deeper.v1.NestedService/PutInner",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+}
+impl NestedService for NestedServiceClient {
+    fn get_inner(&self, i: ::prost_twirp::PTReq<Outer>) -> ::prost_twirp::PTRes<outer::Inner> {
+        self.0
+            .go("/twirp/golden.nested.deeper.v1.NestedService/GetInner", i)
+    }
+    fn put_inner(&self, i: ::prost_twirp::PTReq<outer::Inner>) -> ::prost_twirp::PTRes<Outer> {
+        self.0
+            .go("/twirp/golden.nested.deeper.v1.NestedService/PutInner", i)
+    }
+}
+pub struct NestedServiceServer<T, E = ::prost_twirp::ProstTwirpError>(
+    ::std::sync::Arc<T>,
+    ::std::marker::PhantomData<fn() -> E>,
+)
+where
+    T: 'static + NestedService<E>,
+    E: 'static;
+impl<T, E> NestedServiceServer<T, E>
+where
+    T: 'static + NestedService<E>,
+    E: 'static,
+{
+    pub fn new(v: T) -> NestedServiceServer<T, E> {
+        let service = ::std::sync::Arc::new(v);
+        NestedServiceServer(service, ::std::marker::PhantomData)
+    }
+}
+impl<T, E> ::prost_twirp::HyperService for NestedServiceServer<T, E>
+where
+    T: 'static + NestedService<E>,
+    E: 'static + ::prost_twirp::IntoTwirpError,
+{
+    fn handle(&self, req: ::prost_twirp::ServiceRequest<Vec<u8>>) -> ::prost_twirp::PTRes<Vec<u8>> {
+        let service = self.0.clone();
+        match (req.method.clone(), req.uri.path()) {
+            (::hyper::Method::Post, "/twirp/golden.nested.deeper.v1.NestedService/GetInner") => {
+                req.handle_proto(move |v| service.get_inner(v))
+            }
+            (::hyper::Method::Post, "/twirp/golden.nested.deeper.v1.NestedService/PutInner") => {
+                req.handle_proto(move |v| service.put_inner(v))
+            }
+            _ => {
+                let msg = format!("no handler for path {:?}", req.uri.path());
+                let err = ::prost_twirp::TwirpError::bad_route(&msg, &req.method, req.uri.path());
+                Box::new(::futures::future::ok(err.to_resp_raw()))
+            }
+        }
+    }
+}
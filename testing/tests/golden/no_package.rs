@@ -0,0 +1,172 @@
+pub type PTReq<I> = ::prost_twirp::PTReq<I>;
+pub type PTRes<O, E = ::prost_twirp::ProstTwirpError> = ::prost_twirp::PTRes<O, E>;
+/// Greets without a package
+pub trait Greeter<E = ::prost_twirp::ProstTwirpError> {
+    fn say_hello(
+        &self,
+        i: ::prost_twirp::PTReq<HelloRequest>,
+    ) -> ::prost_twirp::PTRes<HelloResponse, E>;
+    fn say_goodbye(
+        &self,
+        i: ::prost_twirp::PTReq<HelloRequest>,
+    ) -> ::prost_twirp::PTRes<HelloResponse, E>;
+}
+#[allow(dead_code)]
+impl dyn Greeter {
+    /// The `SayHello` method
+    pub const SAY_HELLO_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "Greeter",
+        method: "SayHello",
+        path: "/twirp/Greeter/SayHello",
+        policy: None,
+    };
+    /// The `SayGoodbye` method
+    pub const SAY_GOODBYE_METHOD: ::prost_twirp::MethodDescriptor =
+        ::prost_twirp::MethodDescriptor {
+            service: "Greeter",
+            method: "SayGoodbye",
+            path: "/twirp/Greeter/SayGoodbye",
+            policy: None,
+        };
+    /// Every method of the service
+    pub const METHODS: &'static [::prost_twirp::MethodDescriptor] =
+        &[Self::SAY_HELLO_METHOD, Self::SAY_GOODBYE_METHOD];
+    pub fn new_client(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+    ) -> Box<dyn Greeter> {
+        Box::new(GreeterClient::new(client, root_url))
+    }
+    /// A client whose calls get their `Authorization` header from the credential provider
+    pub fn new_client_with_credentials<C>(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+        credentials: C,
+    ) -> Box<dyn Greeter>
+    where
+        C: 'static + ::prost_twirp::CredentialProvider,
+    {
+        Box::new(GreeterClient::new_with_credentials(
+            client,
+            root_url,
+            credentials,
+        ))
+    }
+    pub fn new_server<T, E>(
+        v: T,
+    ) -> Box<
+        dyn 'static
+            + ::hyper::server::Service<
+                Request = ::hyper::Request,
+                Response = ::hyper::Response,
+                Error = ::hyper::Error,
+                Future = Box<
+                    dyn 'static
+                        + ::futures::Future<Item = ::hyper::Response, Error = ::hyper::Error>,
+                >,
+            >,
+    >
+    where
+        T: 'static + Greeter<E>,
+        E: 'static + ::prost_twirp::IntoTwirpError,
+    {
+        let server = GreeterServer::new(v);
+        Box::new(::prost_twirp::HyperServer::new(server))
+    }
+}
+pub struct GreeterClient(pub ::prost_twirp::HyperClient);
+#[allow(dead_code)]
+impl GreeterClient {
+    pub fn new(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+    ) -> GreeterClient {
+        GreeterClient(::prost_twirp::HyperClient::new(client, root_url))
+    }
+    /// A client whose calls get their `Authorization` header from the credential provider
+    pub fn new_with_credentials<C>(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+        credentials: C,
+    ) -> GreeterClient
+    where
+        C: 'static + ::prost_twirp::CredentialProvider,
+    {
+        GreeterClient(
+            ::prost_twirp::HyperClient::new(client, root_url).with_credentials(credentials),
+        )
+    }
+    pub fn call_say_hello(
+        &self,
+        i: HelloRequest,
+    ) -> ::prost_twirp::Call<HelloRequest, HelloResponse> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/Greeter/SayHello",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+    pub fn call_say_goodbye(
+        &self,
+        i: HelloRequest,
+    ) -> ::prost_twirp::Call<HelloRequest, HelloResponse> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/Greeter/SayGoodbye",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+}
+impl Greeter for GreeterClient {
+    fn say_hello(
+        &self,
+        i: ::prost_twirp::PTReq<HelloRequest>,
+    ) -> ::prost_twirp::PTRes<HelloResponse> {
+        self.0.go("/twirp/Greeter/SayHello", i)
+    }
+    fn say_goodbye(
+        &self,
+        i: ::prost_twirp::PTReq<HelloRequest>,
+    ) -> ::prost_twirp::PTRes<HelloResponse> {
+        self.0.go("/twirp/Greeter/SayGoodbye", i)
+    }
+}
+pub struct GreeterServer<T, E = ::prost_twirp::ProstTwirpError>(
+    ::std::sync::Arc<T>,
+    ::std::marker::PhantomData<fn() -> E>,
+)
+where
+    T: 'static + Greeter<E>,
+    E: 'static;
+impl<T, E> GreeterServer<T, E>
+where
+    T: 'static + Greeter<E>,
+    E: 'static,
+{
+    pub fn new(v: T) -> GreeterServer<T, E> {
+        let service = ::std::sync::Arc::new(v);
+        GreeterServer(service, ::std::marker::PhantomData)
+    }
+}
+impl<T, E> ::prost_twirp::HyperService for GreeterServer<T, E>
+where
+    T: 'static + Greeter<E>,
+    E: 'static + ::prost_twirp::IntoTwirpError,
+{
+    fn handle(&self, req: ::prost_twirp::ServiceRequest<Vec<u8>>) -> ::prost_twirp::PTRes<Vec<u8>> {
+        let service = self.0.clone();
+        match (req.method.clone(), req.uri.path()) {
+            (::hyper::Method::Post, "/twirp/Greeter/SayHello") => {
+                req.handle_proto(move |v| service.say_hello(v))
+            }
+            (::hyper::Method::Post, "/twirp/Greeter/SayGoodbye") => {
+                req.handle_proto(move |v| service.say_goodbye(v))
+            }
+            _ => {
+                let msg = format!("no handler for path {:?}", req.uri.path());
+                let err = ::prost_twirp::TwirpError::bad_route(&msg, &req.method, req.uri.path());
+                Box::new(::futures::future::ok(err.to_resp_raw()))
+            }
+        }
+    }
+}
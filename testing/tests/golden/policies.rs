@@ -0,0 +1,231 @@
+pub type PTReq<I> = ::prost_twirp::PTReq<I>;
+pub type PTRes<O, E = ::prost_twirp::ProstTwirpError> = ::prost_twirp::PTRes<O, E>;
+pub trait Vault<E = ::prost_twirp::ProstTwirpError> {
+    /// Anyone authenticated can list.
+    fn list(&self, i: ::prost_twirp::PTReq<Secret>) -> ::prost_twirp::PTRes<Secret, E>;
+    fn read(&self, i: ::prost_twirp::PTReq<Secret>) -> ::prost_twirp::PTRes<Secret, E>;
+    fn write(&self, i: ::prost_twirp::PTReq<Secret>) -> ::prost_twirp::PTRes<Secret, E>;
+    fn health(&self, i: ::prost_twirp::PTReq<Secret>) -> ::prost_twirp::PTRes<Secret, E>;
+}
+#[allow(dead_code)]
+impl dyn Vault {
+    /// The `List` method
+    pub const LIST_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "golden.policies.Vault",
+        method: "List",
+        path: "/twirp/golden.policies.Vault/List",
+        policy: Some(::prost_twirp::MethodPolicy {
+            scopes: &[],
+            roles: &[],
+        }),
+    };
+    /// The `Read` method
+    pub const READ_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "golden.policies.Vault",
+        method: "Read",
+        path: "/twirp/golden.policies.Vault/Read",
+        policy: Some(::prost_twirp::MethodPolicy {
+            scopes: &["secrets.read"],
+            roles: &[],
+        }),
+    };
+    /// The `Write` method
+    pub const WRITE_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "golden.policies.Vault",
+        method: "Write",
+        path: "/twirp/golden.policies.Vault/Write",
+        policy: Some(::prost_twirp::MethodPolicy {
+            scopes: &["secrets.read", "secrets.write"],
+            roles: &["admin", "owner"],
+        }),
+    };
+    /// The `Health` method
+    pub const HEALTH_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "golden.policies.Vault",
+        method: "Health",
+        path: "/twirp/golden.policies.Vault/Health",
+        policy: None,
+    };
+    /// Every method of the service
+    pub const METHODS: &'static [::prost_twirp::MethodDescriptor] = &[
+        Self::LIST_METHOD,
+        Self::READ_METHOD,
+        Self::WRITE_METHOD,
+        Self::HEALTH_METHOD,
+    ];
+    pub fn new_client(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+    ) -> Box<dyn Vault> {
+        Box::new(VaultClient::new(client, root_url))
+    }
+    /// A client whose calls get their `Authorization` header from the credential provider
+    pub fn new_client_with_credentials<C>(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+        credentials: C,
+    ) -> Box<dyn Vault>
+    where
+        C: 'static + ::prost_twirp::CredentialProvider,
+    {
+        Box::new(VaultClient::new_with_credentials(
+            client,
+            root_url,
+            credentials,
+        ))
+    }
+    pub fn new_server<T, E>(
+        v: T,
+    ) -> Box<
+        dyn 'static
+            + ::hyper::server::Service<
+                Request = ::hyper::Request,
+                Response = ::hyper::Response,
+                Error = ::hyper::Error,
+                Future = Box<
+                    dyn 'static
+                        + ::futures::Future<Item = ::hyper::Response, Error = ::hyper::Error>,
+                >,
+            >,
+    >
+    where
+        T: 'static + Vault<E>,
+        E: 'static + ::prost_twirp::IntoTwirpError,
+    {
+        let server = VaultServer::new(v);
+        Box::new(::prost_twirp::HyperServer::new(server))
+    }
+}
+pub struct VaultClient(pub ::prost_twirp::HyperClient);
+#[allow(dead_code)]
+impl VaultClient {
+    pub fn new(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+    ) -> VaultClient {
+        VaultClient(::prost_twirp::HyperClient::new(client, root_url))
+    }
+    /// A client whose calls get their `Authorization` header from the credential provider
+    pub fn new_with_credentials<C>(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+        credentials: C,
+    ) -> VaultClient
+    where
+        C: 'static + ::prost_twirp::CredentialProvider,
+    {
+        VaultClient(::prost_twirp::HyperClient::new(client, root_url).with_credentials(credentials))
+    }
+    /// Anyone authenticated can list.
+    pub fn call_list(&self, i: Secret) -> ::prost_twirp::Call<Secret, Secret> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.policies.Vault/List",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+    pub fn call_read(&self, i: Secret) -> ::prost_twirp::Call<Secret, Secret> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.policies.Vault/Read",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+    pub fn call_write(&self, i: Secret) -> ::prost_twirp::Call<Secret, Secret> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.policies.Vault/Write",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+    pub fn call_health(&self, i: Secret) -> ::prost_twirp::Call<Secret, Secret> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.policies.Vault/Health",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+}
+impl Vault for VaultClient {
+    fn list(&self, i: ::prost_twirp::PTReq<Secret>) -> ::prost_twirp::PTRes<Secret> {
+        self.0.go("/twirp/golden.policies.Vault/List", i)
+    }
+    fn read(&self, i: ::prost_twirp::PTReq<Secret>) -> ::prost_twirp::PTRes<Secret> {
+        self.0.go("/twirp/golden.policies.Vault/Read", i)
+    }
+    fn write(&self, i: ::prost_twirp::PTReq<Secret>) -> ::prost_twirp::PTRes<Secret> {
+        self.0.go("/twirp/golden.policies.Vault/Write", i)
+    }
+    fn health(&self, i: ::prost_twirp::PTReq<Secret>) -> ::prost_twirp::PTRes<Secret> {
+        self.0.go("/twirp/golden.policies.Vault/Health", i)
+    }
+}
+pub struct VaultServer<T, E = ::prost_twirp::ProstTwirpError>(
+    ::std::sync::Arc<T>,
+    ::std::marker::PhantomData<fn() -> E>,
+)
+where
+    T: 'static + Vault<E>,
+    E: 'static;
+impl<T, E> VaultServer<T, E>
+where
+    T: 'static + Vault<E>,
+    E: 'static,
+{
+    pub fn new(v: T) -> VaultServer<T, E> {
+        let service = ::std::sync::Arc::new(v);
+        VaultServer(service, ::std::marker::PhantomData)
+    }
+}
+impl<T, E> ::prost_twirp::HyperService for VaultServer<T, E>
+where
+    T: 'static + Vault<E>,
+    E: 'static + ::prost_twirp::IntoTwirpError,
+{
+    fn handle(&self, req: ::prost_twirp::ServiceRequest<Vec<u8>>) -> ::prost_twirp::PTRes<Vec<u8>> {
+        let service = self.0.clone();
+        match (req.method.clone(), req.uri.path()) {
+            (::hyper::Method::Post, "/twirp/golden.policies.Vault/List") => {
+                match (::prost_twirp::MethodPolicy {
+                    scopes: &[],
+                    roles: &[],
+                })
+                .check(req.principal.as_ref())
+                {
+                    Ok(()) => req.handle_proto(move |v| service.list(v)),
+                    Err(err) => Box::new(::futures::future::ok(err.to_resp_raw())),
+                }
+            }
+            (::hyper::Method::Post, "/twirp/golden.policies.Vault/Read") => {
+                match (::prost_twirp::MethodPolicy {
+                    scopes: &["secrets.read"],
+                    roles: &[],
+                })
+                .check(req.principal.as_ref())
+                {
+                    Ok(()) => req.handle_proto(move |v| service.read(v)),
+                    Err(err) => Box::new(::futures::future::ok(err.to_resp_raw())),
+                }
+            }
+            (::hyper::Method::Post, "/twirp/golden.policies.Vault/Write") => {
+                match (::prost_twirp::MethodPolicy {
+                    scopes: &["secrets.read", "secrets.write"],
+                    roles: &["admin", "owner"],
+                })
+                .check(req.principal.as_ref())
+                {
+                    Ok(()) => req.handle_proto(move |v| service.write(v)),
+                    Err(err) => Box::new(::futures::future::ok(err.to_resp_raw())),
+                }
+            }
+            (::hyper::Method::Post, "/twirp/golden.policies.Vault/Health") => {
+                req.handle_proto(move |v| service.health(v))
+            }
+            _ => {
+                let msg = format!("no handler for path {:?}", req.uri.path());
+                let err = ::prost_twirp::TwirpError::bad_route(&msg, &req.method, req.uri.path());
+                Box::new(::futures::future::ok(err.to_resp_raw()))
+            }
+        }
+    }
+}
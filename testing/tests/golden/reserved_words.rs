@@ -0,0 +1,285 @@
+pub type PTReq<I> = ::prost_twirp::PTReq<I>;
+pub type PTRes<O, E = ::prost_twirp::ProstTwirpError> = ::prost_twirp::PTRes<O, E>;
+/// Match has methods whose names are Rust keywords.
+pub trait Match<E = ::prost_twirp::ProstTwirpError> {
+    fn type_(&self, i: ::prost_twirp::PTReq<Struct>) -> ::prost_twirp::PTRes<Self_, E>;
+    fn self_(&self, i: ::prost_twirp::PTReq<Self_>) -> ::prost_twirp::PTRes<Struct, E>;
+    fn fn_(&self, i: ::prost_twirp::PTReq<Struct>) -> ::prost_twirp::PTRes<Struct, E>;
+    fn r#async(&self, i: ::prost_twirp::PTReq<Struct>) -> ::prost_twirp::PTRes<Struct, E>;
+    fn r#await(&self, i: ::prost_twirp::PTReq<Struct>) -> ::prost_twirp::PTRes<Struct, E>;
+    fn r#dyn(&self, i: ::prost_twirp::PTReq<Struct>) -> ::prost_twirp::PTRes<Struct, E>;
+    fn r#try(&self, i: ::prost_twirp::PTReq<Struct>) -> ::prost_twirp::PTRes<Struct, E>;
+    fn loop_(&self, i: ::prost_twirp::PTReq<Struct>) -> ::prost_twirp::PTRes<Struct, E>;
+}
+#[allow(dead_code)]
+impl dyn Match {
+    /// The `Type` method
+    pub const TYPE_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "golden.reserved.Match",
+        method: "Type",
+        path: "/twirp/golden.reserved.Match/Type",
+        policy: None,
+    };
+    /// The `Self` method
+    pub const SELF_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "golden.reserved.Match",
+        method: "Self",
+        path: "/twirp/golden.reserved.Match/Self",
+        policy: None,
+    };
+    /// The `Fn` method
+    pub const FN_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "golden.reserved.Match",
+        method: "Fn",
+        path: "/twirp/golden.reserved.Match/Fn",
+        policy: None,
+    };
+    /// The `Async` method
+    pub const ASYNC_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "golden.reserved.Match",
+        method: "Async",
+        path: "/twirp/golden.reserved.Match/Async",
+        policy: None,
+    };
+    /// The `Await` method
+    pub const AWAIT_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "golden.reserved.Match",
+        method: "Await",
+        path: "/twirp/golden.reserved.Match/Await",
+        policy: None,
+    };
+    /// The `Dyn` method
+    pub const DYN_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "golden.reserved.Match",
+        method: "Dyn",
+        path: "/twirp/golden.reserved.Match/Dyn",
+        policy: None,
+    };
+    /// The `Try` method
+    pub const TRY_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "golden.reserved.Match",
+        method: "Try",
+        path: "/twirp/golden.reserved.Match/Try",
+        policy: None,
+    };
+    /// The `Loop` method
+    pub const LOOP_METHOD: ::prost_twirp::MethodDescriptor = ::prost_twirp::MethodDescriptor {
+        service: "golden.reserved.Match",
+        method: "Loop",
+        path: "/twirp/golden.reserved.Match/Loop",
+        policy: None,
+    };
+    /// Every method of the service
+    pub const METHODS: &'static [::prost_twirp::MethodDescriptor] = &[
+        Self::TYPE_METHOD,
+        Self::SELF_METHOD,
+        Self::FN_METHOD,
+        Self::ASYNC_METHOD,
+        Self::AWAIT_METHOD,
+        Self::DYN_METHOD,
+        Self::TRY_METHOD,
+        Self::LOOP_METHOD,
+    ];
+    pub fn new_client(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+    ) -> Box<dyn Match> {
+        Box::new(MatchClient::new(client, root_url))
+    }
+    /// A client whose calls get their `Authorization` header from the credential provider
+    pub fn new_client_with_credentials<C>(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+        credentials: C,
+    ) -> Box<dyn Match>
+    where
+        C: 'static + ::prost_twirp::CredentialProvider,
+    {
+        Box::new(MatchClient::new_with_credentials(
+            client,
+            root_url,
+            credentials,
+        ))
+    }
+    pub fn new_server<T, E>(
+        v: T,
+    ) -> Box<
+        dyn 'static
+            + ::hyper::server::Service<
+                Request = ::hyper::Request,
+                Response = ::hyper::Response,
+                Error = ::hyper::Error,
+                Future = Box<
+                    dyn 'static
+                        + ::futures::Future<Item = ::hyper::Response, Error = ::hyper::Error>,
+                >,
+            >,
+    >
+    where
+        T: 'static + Match<E>,
+        E: 'static + ::prost_twirp::IntoTwirpError,
+    {
+        let server = MatchServer::new(v);
+        Box::new(::prost_twirp::HyperServer::new(server))
+    }
+}
+pub struct MatchClient(pub ::prost_twirp::HyperClient);
+#[allow(dead_code)]
+impl MatchClient {
+    pub fn new(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+    ) -> MatchClient {
+        MatchClient(::prost_twirp::HyperClient::new(client, root_url))
+    }
+    /// A client whose calls get their `Authorization` header from the credential provider
+    pub fn new_with_credentials<C>(
+        client: ::hyper::Client<::hyper::client::HttpConnector, ::hyper::Body>,
+        root_url: &str,
+        credentials: C,
+    ) -> MatchClient
+    where
+        C: 'static + ::prost_twirp::CredentialProvider,
+    {
+        MatchClient(::prost_twirp::HyperClient::new(client, root_url).with_credentials(credentials))
+    }
+    pub fn call_type(&self, i: Struct) -> ::prost_twirp::Call<Struct, Self_> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.reserved.Match/Type",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+    pub fn call_self(&self, i: Self_) -> ::prost_twirp::Call<Self_, Struct> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.reserved.Match/Self",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+    pub fn call_fn(&self, i: Struct) -> ::prost_twirp::Call<Struct, Struct> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.reserved.Match/Fn",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+    pub fn call_async(&self, i: Struct) -> ::prost_twirp::Call<Struct, Struct> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.reserved.Match/Async",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+    pub fn call_await(&self, i: Struct) -> ::prost_twirp::Call<Struct, Struct> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.reserved.Match/Await",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+    pub fn call_dyn(&self, i: Struct) -> ::prost_twirp::Call<Struct, Struct> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.reserved.Match/Dyn",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+    pub fn call_try(&self, i: Struct) -> ::prost_twirp::Call<Struct, Struct> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.reserved.Match/Try",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+    pub fn call_loop(&self, i: Struct) -> ::prost_twirp::Call<Struct, Struct> {
+        ::prost_twirp::Call::new(
+            &self.0,
+            "/twirp/golden.reserved.Match/Loop",
+            ::prost_twirp::ServiceRequest::new(i),
+        )
+    }
+}
+impl Match for MatchClient {
+    fn type_(&self, i: ::prost_twirp::PTReq<Struct>) -> ::prost_twirp::PTRes<Self_> {
+        self.0.go("/twirp/golden.reserved.Match/Type", i)
+    }
+    fn self_(&self, i: ::prost_twirp::PTReq<Self_>) -> ::prost_twirp::PTRes<Struct> {
+        self.0.go("/twirp/golden.reserved.Match/Self", i)
+    }
+    fn fn_(&self, i: ::prost_twirp::PTReq<Struct>) -> ::prost_twirp::PTRes<Struct> {
+        self.0.go("/twirp/golden.reserved.Match/Fn", i)
+    }
+    fn r#async(&self, i: ::prost_twirp::PTReq<Struct>) -> ::prost_twirp::PTRes<Struct> {
+        self.0.go("/twirp/golden.reserved.Match/Async", i)
+    }
+    fn r#await(&self, i: ::prost_twirp::PTReq<Struct>) -> ::prost_twirp::PTRes<Struct> {
+        self.0.go("/twirp/golden.reserved.Match/Await", i)
+    }
+    fn r#dyn(&self, i: ::prost_twirp::PTReq<Struct>) -> ::prost_twirp::PTRes<Struct> {
+        self.0.go("/twirp/golden.reserved.Match/Dyn", i)
+    }
+    fn r#try(&self, i: ::prost_twirp::PTReq<Struct>) -> ::prost_twirp::PTRes<Struct> {
+        self.0.go("/twirp/golden.reserved.Match/Try", i)
+    }
+    fn loop_(&self, i: ::prost_twirp::PTReq<Struct>) -> ::prost_twirp::PTRes<Struct> {
+        self.0.go("/twirp/golden.reserved.Match/Loop", i)
+    }
+}
+pub struct MatchServer<T, E = ::prost_twirp::ProstTwirpError>(
+    ::std::sync::Arc<T>,
+    ::std::marker::PhantomData<fn() -> E>,
+)
+where
+    T: 'static + Match<E>,
+    E: 'static;
+impl<T, E> MatchServer<T, E>
+where
+    T: 'static + Match<E>,
+    E: 'static,
+{
+    pub fn new(v: T) -> MatchServer<T, E> {
+        let service = ::std::sync::Arc::new(v);
+        MatchServer(service, ::std::marker::PhantomData)
+    }
+}
+impl<T, E> ::prost_twirp::HyperService for MatchServer<T, E>
+where
+    T: 'static + Match<E>,
+    E: 'static + ::prost_twirp::IntoTwirpError,
+{
+    fn handle(&self, req: ::prost_twirp::ServiceRequest<Vec<u8>>) -> ::prost_twirp::PTRes<Vec<u8>> {
+        let service = self.0.clone();
+        match (req.method.clone(), req.uri.path()) {
+            (::hyper::Method::Post, "/twirp/golden.reserved.Match/Type") => {
+                req.handle_proto(move |v| service.type_(v))
+            }
+            (::hyper::Method::Post, "/twirp/golden.reserved.Match/Self") => {
+                req.handle_proto(move |v| service.self_(v))
+            }
+            (::hyper::Method::Post, "/twirp/golden.reserved.Match/Fn") => {
+                req.handle_proto(move |v| service.fn_(v))
+            }
+            (::hyper::Method::Post, "/twirp/golden.reserved.Match/Async") => {
+                req.handle_proto(move |v| service.r#async(v))
+            }
+            (::hyper::Method::Post, "/twirp/golden.reserved.Match/Await") => {
+                req.handle_proto(move |v| service.r#await(v))
+            }
+            (::hyper::Method::Post, "/twirp/golden.reserved.Match/Dyn") => {
+                req.handle_proto(move |v| service.r#dyn(v))
+            }
+            (::hyper::Method::Post, "/twirp/golden.reserved.Match/Try") => {
+                req.handle_proto(move |v| service.r#try(v))
+            }
+            (::hyper::Method::Post, "/twirp/golden.reserved.Match/Loop") => {
+                req.handle_proto(move |v| service.loop_(v))
+            }
+            _ => {
+                let msg = format!("no handler for path {:?}", req.uri.path());
+                let err = ::prost_twirp::TwirpError::bad_route(&msg, &req.method, req.uri.path());
+                Box::new(::futures::future::ok(err.to_resp_raw()))
+            }
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! Builds the expected files of the golden tests, with the messages prost generates for their corpus files.
+//!
+//! Each expected file goes in a module named for its package, so references to other packages resolve. Expected files
+//! for an embedded client use this crate's `prost_twirp` in place of the embedded runtime.
+
+#![deny(bare_trait_objects)]
+
+extern crate bytes;
+extern crate futures;
+extern crate hyper;
+extern crate prost;
+#[macro_use]
+extern crate prost_derive;
+extern crate prost_twirp;
+
+#[allow(dead_code)]
+mod golden {
+    pub mod comments {
+        include!(concat!(env!("OUT_DIR"), "/golden.comments.rs"));
+        include!("golden/comments.rs");
+    }
+
+    pub mod cross {
+        include!(concat!(env!("OUT_DIR"), "/golden.cross.rs"));
+        include!("golden/cross_package.rs");
+    }
+
+    pub mod shared {
+        pub mod types {
+            include!(concat!(env!("OUT_DIR"), "/golden.shared.types.rs"));
+        }
+    }
+
+    pub mod long_names {
+        include!(concat!(env!("OUT_DIR"), "/golden.a.rather.long.package.name.for_.testing.line.wrapping.v1.rs"));
+        include!("golden/long_names.rs");
+    }
+
+    // An RPC named `New` makes a trait method clippy doesn't like the name of
+    #[allow(clippy::new_ret_no_self, clippy::wrong_self_convention)]
+    pub mod names {
+        include!(concat!(env!("OUT_DIR"), "/golden.names.rs"));
+        include!("golden/method_names.rs");
+    }
+
+    pub mod multi {
+        include!(concat!(env!("OUT_DIR"), "/golden.multi.rs"));
+        include!("golden/multi_service.rs");
+    }
+
+    pub mod multi_embed {
+        use prost_twirp;
+        include!(concat!(env!("OUT_DIR"), "/golden.multi.rs"));
+        include!("golden/multi_service.embed.rs");
+    }
+
+    pub mod nested {
+        include!(concat!(env!("OUT_DIR"), "/golden.nested.deeper.v1.rs"));
+        include!("golden/nested_package.rs");
+    }
+
+    pub mod no_package {
+        #[derive(Clone, PartialEq, Message)]
+        pub struct HelloRequest {}
+
+        #[derive(Clone, PartialEq, Message)]
+        pub struct HelloResponse {}
+
+        include!("golden/no_package.rs");
+    }
+
+    pub mod policies {
+        include!(concat!(env!("OUT_DIR"), "/golden.policies.rs"));
+        include!("golden/policies.rs");
+    }
+
+    pub mod policies_embed {
+        use prost_twirp;
+        include!(concat!(env!("OUT_DIR"), "/golden.policies.rs"));
+        include!("golden/policies.embed.rs");
+    }
+
+    pub mod reserved {
+        include!(concat!(env!("OUT_DIR"), "/golden.reserved.rs"));
+        include!("golden/reserved_words.rs");
+    }
+}
+
+#[test]
+fn descriptors() {
+    use golden::*;
+    assert_eq!(<dyn comments::Commented>::METHODS.len(), 4);
+    assert_eq!(<dyn cross::Courier>::METHODS.len(), 3);
+    assert_eq!(<dyn long_names::AnExtremelyDescriptivelyNamedService>::METHODS.len(), 2);
+    assert_eq!(<dyn names::Registry>::METHODS, &[<dyn names::Registry>::METHODS_METHOD,
+        <dyn names::Registry>::ALL_METHODS_METHOD, <dyn names::Registry>::NEW_METHOD,
+        <dyn names::Registry>::NEW_CLIENT_METHOD, <dyn names::Registry>::NEW_SERVER_METHOD]);
+    assert_eq!(<dyn multi::Pinger>::METHODS.len(), 2);
+    assert_eq!(<dyn multi_embed::Ponger>::METHODS.len(), 1);
+    assert_eq!(<dyn nested::NestedService>::METHODS.len(), 2);
+    assert_eq!(<dyn no_package::Greeter>::METHODS.len(), 2);
+    assert_eq!(<dyn policies::Vault>::METHODS, <dyn policies_embed::Vault>::METHODS);
+    assert_eq!(<dyn reserved::Match>::METHODS.len(), 8);
+}
@@ -0,0 +1,140 @@
+//! Tests for the Prometheus-style metrics recorded for servers and clients.
+
+extern crate futures;
+extern crate hyper;
+extern crate prost_twirp;
+extern crate prost_twirp_testing;
+extern crate tokio_core;
+
+use futures::{future, Future, Stream};
+use hyper::{Client, Method, Request, StatusCode};
+use prost_twirp::{CallObserver, HyperClient, HyperServer, Metrics, MetricsEndpoint, PROMETHEUS_CONTENT_TYPE};
+use prost_twirp_testing::{EchoService, LoopbackServer};
+use prost_twirp_testing::bench::{Blob, Echo, EchoClient, EchoServer};
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_core::reactor::Core;
+
+fn blob() -> Blob { Blob { data: b"hello".to_vec() } }
+
+/// Whether the rendered metrics have the exact line
+fn has_line(rendered: &str, line: &str) -> bool { rendered.lines().any(|v| v == line) }
+
+#[test]
+fn rendered_format() {
+    let mut metrics = Metrics::new("test");
+    metrics.buckets = vec![0.1, 1.0];
+    metrics.started("/twirp/pkg.Service/Fast");
+    metrics.finished("/twirp/pkg.Service/Fast", Duration::from_millis(50), None);
+    metrics.started("/twirp/pkg.Service/Fast");
+    metrics.finished("/twirp/pkg.Service/Fast", Duration::from_millis(1500), Some("internal"));
+    metrics.started("/twirp/pkg.Service/Slow");
+    assert_eq!(metrics.render(), "\
+        # HELP test_requests_total Calls started.\n\
+        # TYPE test_requests_total counter\n\
+        test_requests_total{service=\"pkg.Service\",method=\"Fast\"} 2\n\
+        test_requests_total{service=\"pkg.Service\",method=\"Slow\"} 1\n\
+        # HELP test_errors_total Calls failed, by Twirp error code.\n\
+        # TYPE test_errors_total counter\n\
+        test_errors_total{service=\"pkg.Service\",method=\"Fast\",code=\"internal\"} 1\n\
+        # HELP test_in_flight Calls started and not finished.\n\
+        # TYPE test_in_flight gauge\n\
+        test_in_flight{service=\"pkg.Service\",method=\"Fast\"} 0\n\
+        test_in_flight{service=\"pkg.Service\",method=\"Slow\"} 1\n\
+        # HELP test_request_duration_seconds How long calls took.\n\
+        # TYPE test_request_duration_seconds histogram\n\
+        test_request_duration_seconds_bucket{service=\"pkg.Service\",method=\"Fast\",le=\"0.1\"} 1\n\
+        test_request_duration_seconds_bucket{service=\"pkg.Service\",method=\"Fast\",le=\"1\"} 1\n\
+        test_request_duration_seconds_bucket{service=\"pkg.Service\",method=\"Fast\",le=\"+Inf\"} 2\n\
+        test_request_duration_seconds_sum{service=\"pkg.Service\",method=\"Fast\"} 1.55\n\
+        test_request_duration_seconds_count{service=\"pkg.Service\",method=\"Fast\"} 2\n\
+        test_request_duration_seconds_bucket{service=\"pkg.Service\",method=\"Slow\",le=\"0.1\"} 0\n\
+        test_request_duration_seconds_bucket{service=\"pkg.Service\",method=\"Slow\",le=\"1\"} 0\n\
+        test_request_duration_seconds_bucket{service=\"pkg.Service\",method=\"Slow\",le=\"+Inf\"} 0\n\
+        test_request_duration_seconds_sum{service=\"pkg.Service\",method=\"Slow\"} 0\n\
+        test_request_duration_seconds_count{service=\"pkg.Service\",method=\"Slow\"} 0\n");
+}
+
+#[test]
+fn unknown_paths() {
+    let metrics = Metrics::server();
+    metrics.started("/favicon.ico");
+    metrics.started("/twirp/evil\"Service\n/Method");
+    let rendered = metrics.render();
+    assert!(has_line(&rendered, "twirp_server_requests_total{service=\"unknown\",method=\"unknown\"} 1"));
+    assert!(has_line(&rendered, "twirp_server_requests_total{service=\"evil\\\"Service\\n\",method=\"Method\"} 1"));
+    // Only known methods are labeled by name once they are given
+    let mut metrics = Metrics::server();
+    metrics.methods = <dyn Echo>::METHODS.to_vec();
+    metrics.started(<dyn Echo>::ECHO_METHOD.path);
+    metrics.started("/twirp/prost_twirp.bench.Echo/Missing");
+    let rendered = metrics.render();
+    assert!(has_line(&rendered, "twirp_server_requests_total{service=\"prost_twirp.bench.Echo\",method=\"Echo\"} 1"));
+    assert!(has_line(&rendered, "twirp_server_requests_total{service=\"unknown\",method=\"unknown\"} 1"));
+}
+
+#[test]
+fn server_and_client_calls() {
+    let mut server_metrics = Metrics::server();
+    server_metrics.methods = <dyn Echo>::METHODS.to_vec();
+    let (server_metrics, client_metrics) = (Arc::new(server_metrics), Arc::new(Metrics::client()));
+    let (observer, endpoint_metrics) = (server_metrics.clone(), vec![server_metrics.clone(), client_metrics.clone()]);
+    let server = LoopbackServer::start(move || {
+        let mut server = HyperServer::new(EchoServer::new(EchoService));
+        server.observer = Some(observer.clone());
+        MetricsEndpoint::new(server, "/metrics", endpoint_metrics.clone())
+    });
+    let mut core = Core::new().unwrap();
+    let hyper_client = Client::new(&core.handle());
+    let client = EchoClient(HyperClient::new(hyper_client.clone(), &server.root_url()).
+        with_observer(client_metrics.clone()));
+    core.run(client.call_echo(blob())).unwrap();
+    core.run(client.call_echo(blob())).unwrap();
+    assert_eq!(core.run(client.call_fail(blob())).unwrap_err().twirp_code(), Some("invalid_argument"));
+
+    let req = Request::new(Method::Get, format!("{}/metrics", server.root_url()).parse().unwrap());
+    let resp = core.run(hyper_client.request(req)).unwrap();
+    assert_eq!(resp.status(), StatusCode::Ok);
+    assert_eq!(resp.headers().get_raw("Content-Type").unwrap().one(), Some(PROMETHEUS_CONTENT_TYPE.as_bytes()));
+    let body = core.run(resp.body().concat2()).unwrap();
+    let rendered = String::from_utf8(body.to_vec()).unwrap();
+    for side in &["server", "client"] {
+        let labels = |method| format!("service=\"prost_twirp.bench.Echo\",method=\"{}\"", method);
+        assert!(has_line(&rendered, &format!("twirp_{}_requests_total{{{}}} 2", side, labels("Echo"))));
+        assert!(has_line(&rendered, &format!("twirp_{}_requests_total{{{}}} 1", side, labels("Fail"))));
+        assert!(has_line(&rendered,
+            &format!("twirp_{}_errors_total{{{},code=\"invalid_argument\"}} 1", side, labels("Fail"))));
+        assert!(has_line(&rendered, &format!("twirp_{}_in_flight{{{}}} 0", side, labels("Echo"))));
+        assert!(has_line(&rendered,
+            &format!("twirp_{}_request_duration_seconds_count{{{}}} 2", side, labels("Echo"))));
+    }
+    assert!(!rendered.contains("twirp_server_errors_total{service=\"prost_twirp.bench.Echo\",method=\"Echo\""));
+
+    // Other requests for the path are passed on
+    let req = Request::new(Method::Post, format!("{}/metrics", server.root_url()).parse().unwrap());
+    assert_eq!(core.run(hyper_client.request(req)).unwrap().status(), StatusCode::NotFound);
+    assert!(has_line(&server_metrics.render(),
+        "twirp_server_errors_total{service=\"unknown\",method=\"unknown\",code=\"bad_route\"} 1"));
+}
+
+#[test]
+fn client_transport_errors() {
+    let mut core = Core::new().unwrap();
+    let metrics = Arc::new(Metrics::client());
+    // Nothing listens on the discard port
+    let client = EchoClient(HyperClient::new(Client::new(&core.handle()), "http://127.0.0.1:9").
+        with_observer(metrics.clone()));
+    assert!(core.run(client.call_echo(blob())).unwrap_err().is_transport());
+    let labels = "service=\"prost_twirp.bench.Echo\",method=\"Echo\"";
+    let rendered = metrics.render();
+    assert!(has_line(&rendered, &format!("twirp_client_errors_total{{{},code=\"unavailable\"}} 1", labels)));
+    // Calls dropped before finishing are canceled, like when nothing answers
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = EchoClient(HyperClient::new(Client::new(&core.handle()),
+        &format!("http://{}", listener.local_addr().unwrap())).with_observer(metrics.clone()));
+    assert!(core.run(client.call_echo(blob()).select2(future::ok::<_, ()>(()))).is_ok());
+    let rendered = metrics.render();
+    assert!(has_line(&rendered, &format!("twirp_client_errors_total{{{},code=\"canceled\"}} 1", labels)));
+    assert!(has_line(&rendered, &format!("twirp_client_in_flight{{{}}} 0", labels)));
+}
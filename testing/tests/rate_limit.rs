@@ -0,0 +1,94 @@
+//! Tests for rate limiting generated servers by caller.
+
+extern crate futures;
+extern crate hyper;
+extern crate prost_twirp;
+extern crate prost_twirp_testing;
+extern crate tokio_core;
+
+use futures::{Future, Stream};
+use hyper::header::RetryAfter;
+use hyper::server::Service;
+use hyper::{Client, Request, StatusCode};
+use prost_twirp::{CallerKey, HyperServer, RateLimit, RateLimited, TwirpError};
+use prost_twirp_testing::{proto_request, EchoService, LoopbackServer};
+use prost_twirp_testing::bench::{Blob, Echo, EchoClient, EchoServer};
+use std::time::Duration;
+use tokio_core::reactor::Core;
+
+/// Two calls, then one every 100 seconds
+const SLOW: RateLimit = RateLimit { burst: 2, per_second: 0.01 };
+
+fn limited(key: CallerKey, limit: Option<RateLimit>) -> RateLimited<EchoServer<EchoService>> {
+    let mut limited = RateLimited::new(EchoServer::new(EchoService), key, SLOW);
+    limited.limit = limit;
+    limited
+}
+
+/// Call the method, returning the status, Twirp code, and Retry-After
+fn call<S>(server: &S, path: &str, api_key: Option<&str>) -> (StatusCode, Option<String>, Option<RetryAfter>)
+        where S: Service<Request=Request, Response=hyper::Response, Error=hyper::Error> {
+    let mut req = proto_request(path, &Blob { data: b"hi".to_vec() });
+    if let Some(api_key) = api_key { req.headers_mut().set_raw("X-Api-Key", api_key); }
+    let resp = server.call(req).wait().unwrap();
+    let (status, retry_after) = (resp.status(), resp.headers().get::<RetryAfter>().cloned());
+    let body = resp.body().concat2().wait().unwrap();
+    let code = if status == StatusCode::Ok { None } else {
+        Some(TwirpError::from_json_bytes(status, &body).unwrap().error_type)
+    };
+    (status, code, retry_after)
+}
+
+fn ok() -> (StatusCode, Option<String>, Option<RetryAfter>) { (StatusCode::Ok, None, None) }
+
+fn exhausted(secs: u64) -> (StatusCode, Option<String>, Option<RetryAfter>) {
+    (StatusCode::TooManyRequests, Some("resource_exhausted".to_string()),
+        Some(RetryAfter::Delay(Duration::from_secs(secs))))
+}
+
+#[test]
+fn header_key() {
+    let server = HyperServer::new(limited(CallerKey::Header("X-Api-Key".to_string()), Some(SLOW)));
+    let echo = <dyn Echo>::ECHO_METHOD.path;
+    assert_eq!(call(&server, echo, Some("a")), ok());
+    // The budget is shared across methods
+    assert_eq!(call(&server, <dyn Echo>::FAIL_METHOD.path, Some("a")).1.as_deref(), Some("invalid_argument"));
+    assert_eq!(call(&server, echo, Some("a")), exhausted(100));
+    // Other callers have their own budget, and unidentified callers aren't limited
+    assert_eq!(call(&server, echo, Some("b")), ok());
+    for _ in 0..3 { assert_eq!(call(&server, echo, None), ok()); }
+    // Clones for other connections share budgets
+    assert_eq!(call(&server.clone(), echo, Some("a")), exhausted(100));
+}
+
+#[test]
+fn method_limits() {
+    let mut limited = limited(CallerKey::Header("X-Api-Key".to_string()), None);
+    limited.method_limits.insert(<dyn Echo>::ECHO_METHOD.path.to_string(), RateLimit { burst: 1, per_second: 0.5 });
+    let server = HyperServer::new(limited);
+    let echo = <dyn Echo>::ECHO_METHOD.path;
+    assert_eq!(call(&server, echo, Some("a")), ok());
+    assert_eq!(call(&server, echo, Some("a")), exhausted(2));
+    // Methods without a limit aren't limited without a default
+    for _ in 0..3 { assert_eq!(call(&server, <dyn Echo>::FAIL_METHOD.path, Some("a")).0, StatusCode::BadRequest); }
+}
+
+#[test]
+fn custom_key() {
+    let key = CallerKey::Custom(Box::new(|req| req.headers.get_raw("X-Api-Key").map(|_| "everyone".to_string())));
+    let server = HyperServer::new(limited(key, Some(RateLimit { burst: 1, per_second: 0.01 })));
+    assert_eq!(call(&server, <dyn Echo>::ECHO_METHOD.path, Some("a")), ok());
+    assert_eq!(call(&server, <dyn Echo>::ECHO_METHOD.path, Some("b")), exhausted(100));
+}
+
+#[test]
+fn remote_ip() {
+    let limited = HyperServer::new(limited(CallerKey::RemoteIp, Some(RateLimit { burst: 1, per_second: 0.01 })));
+    let server = LoopbackServer::start(move || limited.clone());
+    let mut core = Core::new().unwrap();
+    let client = EchoClient::new(Client::new(&core.handle()), &server.root_url());
+    core.run(client.call_echo(Blob::default())).unwrap();
+    let err = core.run(client.call_echo(Blob::default())).unwrap_err();
+    assert_eq!(err.twirp_code(), Some("resource_exhausted"));
+    assert_eq!(err.retry_after(), Some(Duration::from_secs(100)));
+}
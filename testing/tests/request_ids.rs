@@ -0,0 +1,169 @@
+//! Tests for request ID handling by servers and forwarding by clients.
+
+extern crate futures;
+extern crate hyper;
+extern crate prost_twirp;
+extern crate prost_twirp_testing;
+#[macro_use]
+extern crate serde_json;
+extern crate tokio_core;
+
+use futures::{future, Future, Stream};
+use hyper::server::Service;
+use hyper::{Client, Request, StatusCode};
+use prost_twirp::{CallContext, HyperServer, RequestIds, ServiceRequest, TwirpError, REQUEST_ID_HEADER};
+use prost_twirp_testing::{proto_request, LoopbackServer};
+use prost_twirp_testing::bench::{self, Blob, Echo, EchoClient, EchoServer};
+use std::sync::{Arc, Mutex};
+use tokio_core::reactor::Core;
+
+/// Echoes, recording the request IDs it sees
+///
+/// `fail` fails with `internal` for an empty blob and `invalid_argument` otherwise.
+#[derive(Clone, Default)]
+struct Recording {
+    ids: Arc<Mutex<Vec<Option<String>>>>,
+    headers: Arc<Mutex<Vec<Option<String>>>>,
+}
+
+impl Recording {
+    fn ids(&self) -> Vec<Option<String>> { self.ids.lock().unwrap().drain(..).collect() }
+
+    fn headers(&self) -> Vec<Option<String>> { self.headers.lock().unwrap().drain(..).collect() }
+
+    fn record<T>(&self, i: &ServiceRequest<T>) {
+        self.ids.lock().unwrap().push(i.request_id.clone());
+        self.headers.lock().unwrap().push(i.headers.get_raw(REQUEST_ID_HEADER).and_then(|v| v.one()).
+            map(|v| String::from_utf8_lossy(v).into_owned()));
+    }
+}
+
+impl Echo for Recording {
+    fn echo(&self, i: bench::PTReq<Blob>) -> bench::PTRes<Blob> {
+        self.record(&i);
+        Box::new(future::ok(i.input.into()))
+    }
+
+    fn fail(&self, i: bench::PTReq<Blob>) -> bench::PTRes<Blob> {
+        self.record(&i);
+        Box::new(future::err(if i.input.data.is_empty() {
+            TwirpError::new_meta(StatusCode::InternalServerError, "internal", "boom", Some(json!({ "retry": "no" })))
+        } else {
+            TwirpError::new(StatusCode::BadRequest, "invalid_argument", "bad blob")
+        }.into()))
+    }
+}
+
+/// Echoes by calling another echo server, recording the request IDs it sees
+struct Forward {
+    recording: Recording,
+    client: EchoClient,
+}
+
+impl Echo for Forward {
+    fn echo(&self, i: bench::PTReq<Blob>) -> bench::PTRes<Blob> {
+        self.recording.record(&i);
+        Box::new(self.client.call_echo(i.input))
+    }
+
+    fn fail(&self, i: bench::PTReq<Blob>) -> bench::PTRes<Blob> { Box::new(self.client.call_fail(i.input)) }
+}
+
+fn request(path: &str, request_id: Option<&str>, data: &[u8]) -> Request {
+    let mut req = proto_request(path, &Blob { data: data.to_vec() });
+    if let Some(id) = request_id { req.headers_mut().set_raw(REQUEST_ID_HEADER, id); }
+    req
+}
+
+/// Call the server, returning the echoed request ID and the JSON error body if any
+fn call(server: &HyperServer<EchoServer<Recording>>, req: Request) -> (Option<String>, Option<serde_json::Value>) {
+    let resp = server.call(req).wait().unwrap();
+    let id = resp.headers().get_raw(REQUEST_ID_HEADER).and_then(|v| v.one()).
+        map(|v| String::from_utf8_lossy(v).into_owned());
+    let ok = resp.status() == StatusCode::Ok;
+    let body = resp.body().concat2().wait().unwrap();
+    (id, if ok { None } else { Some(serde_json::from_slice(&body).unwrap()) })
+}
+
+#[test]
+fn server_modes() {
+    let service = Recording::default();
+    let mut server = HyperServer::new(EchoServer::new(service.clone()));
+    let echo = <dyn Echo>::ECHO_METHOD.path;
+    // Ignored by default
+    assert_eq!(call(&server, request(echo, Some("abc"), b"hi")), (None, None));
+    assert_eq!(service.ids(), vec![None]);
+
+    server.request_ids = RequestIds::Echo;
+    assert_eq!(call(&server, request(echo, Some("abc"), b"hi")), (Some("abc".to_string()), None));
+    assert_eq!(call(&server, request(echo, None, b"hi")), (None, None));
+    // Invalid IDs are dropped
+    let long = "a".repeat(129);
+    for invalid in &["has space", "", &long] {
+        assert_eq!(call(&server, request(echo, Some(invalid), b"hi")), (None, None));
+    }
+    assert_eq!(service.ids(), vec![Some("abc".to_string()), None, None, None, None]);
+
+    server.request_ids = RequestIds::Generate;
+    assert_eq!(call(&server, request(echo, Some("abc"), b"hi")).0.as_deref(), Some("abc"));
+    let (generated, _) = call(&server, request(echo, Some("has space"), b"hi"));
+    let generated = generated.unwrap();
+    assert!(generated.len() == 32 && generated.chars().all(|v| v.is_ascii_hexdigit()), "{}", generated);
+    assert_eq!(service.ids(), vec![Some("abc".to_string()), Some(generated.clone())]);
+    let (other, _) = call(&server, request(echo, None, b"hi"));
+    assert_ne!(other, Some(generated));
+
+    // Early errors echo the ID too
+    assert_eq!(call(&server, request("/twirp/nope/Nope", Some("abc"), b"hi")).0.as_deref(), Some("abc"));
+}
+
+#[test]
+fn internal_errors_have_id() {
+    let service = Recording::default();
+    let mut server = HyperServer::new(EchoServer::new(service.clone()));
+    server.request_ids = RequestIds::Echo;
+    let fail = <dyn Echo>::FAIL_METHOD.path;
+    assert_eq!(call(&server, request(fail, Some("abc"), b"")), (Some("abc".to_string()), Some(json!({
+        "code": "internal", "msg": "boom", "meta": { "retry": "no", "request_id": "abc" },
+    }))));
+    assert_eq!(call(&server, request(fail, Some("abc"), b"hi")), (Some("abc".to_string()), Some(json!({
+        "code": "invalid_argument", "msg": "bad blob",
+    }))));
+    assert_eq!(call(&server, request(fail, None, b"")).1.unwrap()["meta"], json!({ "retry": "no" }));
+    assert_eq!(TwirpError::from_code("internal", "boom").with_meta("request_id", "abc").meta,
+        Some(json!({ "request_id": "abc" })));
+}
+
+#[test]
+fn clients_forward() {
+    let downstream_service = Recording::default();
+    let downstream_recorded = downstream_service.clone();
+    let downstream = LoopbackServer::start(move || HyperServer::new(EchoServer::new(downstream_recorded.clone())));
+    let mut core = Core::new().unwrap();
+    let client = EchoClient::new(Client::new(&core.handle()), &downstream.root_url());
+    let blob = || Blob { data: b"hi".to_vec() };
+    // Nothing is sent without an ID
+    core.run(client.call_echo(blob())).unwrap();
+    assert_eq!(downstream_service.headers(), vec![None]);
+    // The request's ID is used over the current one, and headers already set over both
+    let context = CallContext { request_id: Some("current".to_string()), ..Default::default() };
+    core.run(context.scope(client.call_echo(blob()))).unwrap();
+    let mut req = ServiceRequest::new(blob());
+    req.request_id = Some("mine".to_string());
+    core.run(context.scope(Echo::echo(&client, req))).unwrap();
+    core.run(context.scope(client.call_echo(blob()).header_raw(REQUEST_ID_HEADER, "header"))).unwrap();
+    assert_eq!(downstream_service.headers(),
+        vec![Some("current".to_string()), Some("mine".to_string()), Some("header".to_string())]);
+
+    // Handlers carry the ID of the call they are handling on
+    let service = Recording::default();
+    let mut server = HyperServer::new(EchoServer::new(Forward { recording: service.clone(), client }));
+    server.request_ids = RequestIds::Generate;
+    let resp = core.run(server.call(request(<dyn Echo>::ECHO_METHOD.path, None, b"hi"))).unwrap();
+    let id = resp.headers().get_raw(REQUEST_ID_HEADER).and_then(|v| v.one()).
+        map(|v| String::from_utf8_lossy(v).into_owned());
+    assert!(id.is_some());
+    assert_eq!(service.ids(), vec![id.clone()]);
+    assert_eq!(downstream_service.headers(), vec![id]);
+    assert_eq!(CallContext::current(), CallContext::default());
+}
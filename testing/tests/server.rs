@@ -0,0 +1,539 @@
+//! Checks generated servers behind `HyperServer` follow the Twirp routing and error spec.
+//!
+//! Requests are made in-process by calling the hyper service directly.
+
+extern crate futures;
+extern crate hyper;
+extern crate prost;
+extern crate prost_twirp;
+extern crate prost_twirp_testing;
+#[macro_use]
+extern crate serde_json;
+
+use futures::{future, Future, Sink, Stream};
+use hyper::header::{ContentLength, ContentType};
+use hyper::server::Service;
+use hyper::{Body, Method, Request, Response, StatusCode};
+use prost::Message;
+use prost_twirp::{Cors, HyperServer, ProstTwirpError, ServiceRequest, ServiceResponse, TwirpError};
+use prost_twirp_testing::{proto_request, EchoService};
+use prost_twirp_testing::bench::{self, Blob, Echo, EchoServer};
+use std::thread;
+use std::time::Duration;
+
+const ECHO_PATH: &str = "/twirp/prost_twirp.bench.Echo/Echo";
+
+type BoxService = Box<dyn Service<Request=Request, Response=Response, Error=hyper::Error,
+    Future=Box<dyn Future<Item=Response, Error=hyper::Error>>>>;
+
+fn echo_server() -> BoxService { <dyn Echo>::new_server(EchoService) }
+
+fn request(method: Method, path: &str, content_type: Option<&str>, body: Vec<u8>) -> Request {
+    let mut req = Request::new(method, path.parse().unwrap());
+    if let Some(content_type) = content_type { req.headers_mut().set_raw("Content-Type", content_type); }
+    req.headers_mut().set(ContentLength(body.len() as u64));
+    req.set_body(body);
+    req
+}
+
+fn blob(data: &[u8]) -> Blob { Blob { data: data.to_vec() } }
+
+fn blob_bytes(data: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    blob(data).encode(&mut bytes).unwrap();
+    bytes
+}
+
+fn call<S>(server: &S, req: Request) -> (StatusCode, Option<String>, Vec<u8>)
+        where S: Service<Request=Request, Response=Response, Error=hyper::Error> + ?Sized {
+    let resp = server.call(req).wait().unwrap();
+    let status = resp.status();
+    let content_type = resp.headers().get::<ContentType>().map(|v| v.to_string());
+    (status, content_type, resp.body().concat2().wait().unwrap().to_vec())
+}
+
+/// Assert the response is a Twirp error with the given status and exact JSON body
+fn assert_twirp_error(resp: (StatusCode, Option<String>, Vec<u8>), status: StatusCode, body: serde_json::Value) {
+    assert_eq!(resp.0, status);
+    assert_eq!(resp.1.as_deref(), Some("application/json"));
+    assert_eq!(serde_json::from_slice::<serde_json::Value>(&resp.2).unwrap(), body);
+}
+
+fn bad_route(msg: &str, route: &str) -> serde_json::Value {
+    json!({ "code": "bad_route", "msg": msg, "meta": { "twirp_invalid_route": route } })
+}
+
+#[test]
+fn success() {
+    let resp = call(&*echo_server(), proto_request(ECHO_PATH, &blob(b"hello")));
+    assert_eq!(resp.0, StatusCode::Ok);
+    assert_eq!(resp.1.as_deref(), Some("application/protobuf"));
+    assert_eq!(Blob::decode(&resp.2).unwrap(), Blob { data: b"hello".to_vec() });
+}
+
+#[test]
+fn empty_body() {
+    // An empty body is a valid protobuf message with all defaults
+    let resp = call(&*echo_server(), request(Method::Post, ECHO_PATH, Some("application/protobuf"), Vec::new()));
+    assert_eq!(resp.0, StatusCode::Ok);
+    assert_eq!(resp.2, Vec::<u8>::new());
+}
+
+#[test]
+fn wrong_method() {
+    for method in [Method::Get, Method::Put, Method::Delete, Method::Patch, Method::Options] {
+        let resp = call(&*echo_server(), request(method.clone(), ECHO_PATH, Some("application/protobuf"),
+            blob_bytes(b"hello")));
+        assert_twirp_error(resp, StatusCode::NotFound, bad_route(
+            &format!("unsupported method \"{}\" (only POST is allowed)", method),
+            &format!("{} {}", method, ECHO_PATH)));
+    }
+}
+
+#[test]
+fn missing_content_type() {
+    let resp = call(&*echo_server(), request(Method::Post, ECHO_PATH, None, blob_bytes(b"hello")));
+    assert_twirp_error(resp, StatusCode::NotFound,
+        bad_route("unexpected Content-Type: \"\" (supported: application/protobuf, application/x-protobuf)",
+            &format!("POST {}", ECHO_PATH)));
+}
+
+#[test]
+fn content_type_parameters_and_case() {
+    let content_types = &["application/protobuf; charset=utf-8", "Application/Protobuf",
+        " application/PROTOBUF ;foo=bar"];
+    for &content_type in content_types {
+        let resp = call(&*echo_server(), request(Method::Post, ECHO_PATH, Some(content_type), blob_bytes(b"hello")));
+        assert_eq!(resp.0, StatusCode::Ok, "{}", content_type);
+        assert_eq!(resp.1.as_deref(), Some("application/protobuf"));
+        assert_eq!(Blob::decode(&resp.2).unwrap(), Blob { data: b"hello".to_vec() });
+    }
+}
+
+#[test]
+fn x_protobuf_content_type_mirrored() {
+    let resp = call(&*echo_server(), request(Method::Post, ECHO_PATH, Some("application/x-protobuf; proto=Blob"),
+        blob_bytes(b"hello")));
+    assert_eq!(resp.0, StatusCode::Ok);
+    assert_eq!(resp.1.as_deref(), Some("application/x-protobuf"));
+    assert_eq!(Blob::decode(&resp.2).unwrap(), Blob { data: b"hello".to_vec() });
+    // Errors are still JSON
+    let resp = call(&*echo_server(), request(Method::Post, "/twirp/prost_twirp.bench.Echo/Fail",
+        Some("application/x-protobuf"), blob_bytes(b"hello")));
+    assert_twirp_error(resp, StatusCode::BadRequest, json!({ "code": "invalid_argument", "msg": "Always fails" }));
+}
+
+#[test]
+fn unsupported_content_type() {
+    for &content_type in &["text/plain", "application/grpc", "application/proto", "application/protobuf-json"] {
+        let resp = call(&*echo_server(), request(Method::Post, ECHO_PATH, Some(content_type), blob_bytes(b"hello")));
+        assert_twirp_error(resp, StatusCode::NotFound, bad_route(
+            &format!("unexpected Content-Type: \"{}\" (supported: application/protobuf, application/x-protobuf)",
+                content_type),
+            &format!("POST {}", ECHO_PATH)));
+    }
+}
+
+#[test]
+fn non_utf8_json() {
+    // JSON isn't supported, so the body is never even looked at
+    let resp = call(&*echo_server(), request(Method::Post, ECHO_PATH, Some("application/json"),
+        vec![b'{', 0xff, 0xfe, b'}']));
+    assert_twirp_error(resp, StatusCode::NotFound,
+        bad_route("unexpected Content-Type: \"application/json\" (JSON is not supported, use application/protobuf)",
+            &format!("POST {}", ECHO_PATH)));
+}
+
+#[test]
+fn bad_routes() {
+    let paths = &[
+        "/twirp/prost_twirp.bench.Echo/Missing",
+        "/twirp/prost_twirp.bench.Missing/Echo",
+        "/twirp/prost_twirp.Echo/Echo",
+        "/twirp/Echo/Echo",
+        "/twirp/prost_twirp.bench.Echo/echo",
+        "/twirp/prost_twirp.bench.Echo/Echo/",
+        "/twirp/prost_twirp.bench.Echo",
+        "/prost_twirp.bench.Echo/Echo",
+        "/",
+    ];
+    for &path in paths {
+        let resp = call(&*echo_server(), proto_request(path, &blob(b"hello")));
+        assert_twirp_error(resp, StatusCode::NotFound,
+            bad_route(&format!("no handler for path \"{}\"", path), &format!("POST {}", path)));
+    }
+}
+
+#[test]
+fn query_string_ignored() {
+    let resp = call(&*echo_server(), proto_request(&format!("{}?foo=bar", ECHO_PATH), &blob(b"hello")));
+    assert_eq!(resp.0, StatusCode::Ok);
+}
+
+#[test]
+fn malformed_body() {
+    let resp = call(&*echo_server(), request(Method::Post, ECHO_PATH, Some("application/protobuf"),
+        vec![0xff, 0xff, 0xff]));
+    assert_twirp_error(resp, StatusCode::BadRequest,
+        json!({ "code": "malformed", "msg": "the protobuf request could not be decoded" }));
+}
+
+#[test]
+fn oversized_body_content_length() {
+    let mut server = HyperServer::new(EchoServer::new(EchoService));
+    server.max_body_size = Some(16);
+    let resp = call(&server, proto_request(ECHO_PATH, &blob(&[7; 32])));
+    assert_twirp_error(resp, StatusCode::BadRequest,
+        json!({ "code": "malformed", "msg": "the request body exceeds the maximum of 16 bytes" }));
+    // At the limit is fine
+    let resp = call(&server, proto_request(ECHO_PATH, &blob(&[7; 14])));
+    assert_eq!(resp.0, StatusCode::Ok);
+}
+
+#[test]
+fn oversized_body_chunked() {
+    let mut server = HyperServer::new(EchoServer::new(EchoService));
+    server.max_body_size = Some(16);
+    let (sender, body) = Body::pair();
+    let sending = thread::spawn(move || {
+        let chunks = blob_bytes(&[7; 32]).chunks(8).map(|v| Ok(v.to_vec().into())).collect::<Vec<_>>();
+        // The server stops reading once over the limit, so sending may fail
+        let _ = sender.send_all(futures::stream::iter_ok::<_, futures::sync::mpsc::SendError<_>>(chunks)).wait();
+    });
+    let mut req = Request::new(Method::Post, ECHO_PATH.parse().unwrap());
+    req.headers_mut().set_raw("Content-Type", "application/protobuf");
+    req.set_body(body);
+    let resp = call(&server, req);
+    assert_twirp_error(resp, StatusCode::BadRequest,
+        json!({ "code": "malformed", "msg": "the request body exceeds the maximum of 16 bytes" }));
+    sending.join().unwrap();
+}
+
+#[test]
+fn lying_content_length() {
+    // Claiming more than there is only preallocates a little, or up to the max
+    let mut req = proto_request(ECHO_PATH, &blob(b"hi"));
+    req.headers_mut().set(ContentLength(1 << 30));
+    let raw = ServiceRequest::from_hyper_raw(req).wait().unwrap();
+    assert_eq!(raw.input, blob_bytes(b"hi"));
+    assert!(raw.input.capacity() <= 64 * 1024, "{}", raw.input.capacity());
+    let mut req = proto_request(ECHO_PATH, &blob(b"hi"));
+    req.headers_mut().set(ContentLength(1000));
+    let raw = ServiceRequest::from_hyper_raw_limited(req, Some(100)).wait().unwrap_err();
+    assert_eq!(raw.twirp_code(), Some("malformed"));
+    let mut req = proto_request(ECHO_PATH, &blob(b"hi"));
+    req.headers_mut().set(ContentLength(50));
+    assert!(ServiceRequest::from_hyper_raw_limited(req, Some(100)).wait().unwrap().input.capacity() <= 100);
+
+    // Claiming less than there is doesn't get past the max
+    let mut server = HyperServer::new(EchoServer::new(EchoService));
+    server.max_body_size = Some(16);
+    let mut req = proto_request(ECHO_PATH, &blob(&[7; 32]));
+    req.headers_mut().set(ContentLength(4));
+    assert_twirp_error(call(&server, req), StatusCode::BadRequest,
+        json!({ "code": "malformed", "msg": "the request body exceeds the maximum of 16 bytes" }));
+    // Without a max, the body is read whatever the claim
+    server.max_body_size = None;
+    let mut req = proto_request(ECHO_PATH, &blob(&[7; 32]));
+    req.headers_mut().set(ContentLength(1 << 30));
+    assert_eq!(call(&server, req), (StatusCode::Ok, Some("application/protobuf".to_string()), blob_bytes(&[7; 32])));
+}
+
+/// Fails `echo` with the Twirp code in the blob and `fail` with a non-Twirp error
+struct FailingService;
+impl bench::Echo for FailingService {
+    fn echo(&self, i: bench::PTReq<Blob>) -> bench::PTRes<Blob> {
+        let code = String::from_utf8(i.input.data).unwrap();
+        Box::new(future::err(TwirpError::from_code(&code, "Failed on purpose").into()))
+    }
+
+    fn fail(&self, _: bench::PTReq<Blob>) -> bench::PTRes<Blob> {
+        Box::new(future::err(ProstTwirpError::JsonDecodeError(serde_json::from_str::<serde_json::Value>("{").
+            unwrap_err())))
+    }
+}
+
+#[test]
+fn handler_errors() {
+    let codes = &[
+        ("canceled", StatusCode::RequestTimeout),
+        ("unknown", StatusCode::InternalServerError),
+        ("invalid_argument", StatusCode::BadRequest),
+        ("malformed", StatusCode::BadRequest),
+        ("deadline_exceeded", StatusCode::RequestTimeout),
+        ("not_found", StatusCode::NotFound),
+        ("bad_route", StatusCode::NotFound),
+        ("already_exists", StatusCode::Conflict),
+        ("permission_denied", StatusCode::Forbidden),
+        ("unauthenticated", StatusCode::Unauthorized),
+        ("resource_exhausted", StatusCode::TooManyRequests),
+        ("failed_precondition", StatusCode::PreconditionFailed),
+        ("aborted", StatusCode::Conflict),
+        ("out_of_range", StatusCode::BadRequest),
+        ("unimplemented", StatusCode::NotImplemented),
+        ("internal", StatusCode::InternalServerError),
+        ("unavailable", StatusCode::ServiceUnavailable),
+        ("dataloss", StatusCode::InternalServerError),
+    ];
+    let server = <dyn Echo>::new_server(FailingService);
+    for &(code, status) in codes {
+        let resp = call(&*server, proto_request(ECHO_PATH, &blob(code.as_bytes())));
+        assert_twirp_error(resp, status, json!({ "code": code, "msg": "Failed on purpose" }));
+    }
+}
+
+#[test]
+fn service_twirp_error() {
+    let resp = call(&*echo_server(), proto_request("/twirp/prost_twirp.bench.Echo/Fail", &blob(b"hello")));
+    assert_twirp_error(resp, StatusCode::BadRequest, json!({ "code": "invalid_argument", "msg": "Always fails" }));
+}
+
+#[test]
+fn internal_error() {
+    let resp = call(&*<dyn Echo>::new_server(FailingService),
+        proto_request("/twirp/prost_twirp.bench.Echo/Fail", &blob(b"hello")));
+    assert_twirp_error(resp, StatusCode::InternalServerError, json!({ "code": "internal", "msg": "Internal Error" }));
+}
+
+/// Sets response headers both ways, failing for `Fail`
+struct HeaderService;
+impl Echo for HeaderService {
+    fn echo(&self, i: bench::PTReq<Blob>) -> bench::PTRes<Blob> {
+        i.response_headers.set_raw("X-Request-Id", "abc");
+        i.response_headers.set_raw("X-Echo", "shared");
+        let mut resp = ServiceResponse::new(i.input);
+        resp.headers.set_raw("X-Echo", "response");
+        Box::new(future::ok(resp))
+    }
+
+    fn fail(&self, i: bench::PTReq<Blob>) -> bench::PTRes<Blob> {
+        i.response_headers.set_raw("X-Request-Id", "abc");
+        i.response_headers.set_raw("Retry-After", "5");
+        i.response_headers.set(ContentType::plaintext());
+        Box::new(future::err(TwirpError::from_code("unavailable", "Try later").
+            with_header_raw("Retry-After", "30").
+            with_header(ContentType::html()).
+            into()))
+    }
+}
+
+fn header(resp: &Response, name: &str) -> Option<String> {
+    resp.headers().get_raw(name).and_then(|v| v.one()).map(|v| String::from_utf8_lossy(v).into_owned())
+}
+
+#[test]
+fn response_headers_on_success() {
+    let resp = <dyn Echo>::new_server(HeaderService).
+        call(proto_request(ECHO_PATH, &blob(b"hello"))).wait().unwrap();
+    assert_eq!(resp.status(), StatusCode::Ok);
+    assert_eq!(header(&resp, "X-Request-Id").as_deref(), Some("abc"));
+    // The response's own headers win
+    assert_eq!(header(&resp, "X-Echo").as_deref(), Some("response"));
+    assert_eq!(header(&resp, "Content-Type").as_deref(), Some("application/protobuf"));
+}
+
+#[test]
+fn response_headers_on_error() {
+    let resp = <dyn Echo>::new_server(HeaderService).
+        call(proto_request("/twirp/prost_twirp.bench.Echo/Fail", &blob(b"hello"))).wait().unwrap();
+    assert_eq!(resp.status(), StatusCode::ServiceUnavailable);
+    assert_eq!(header(&resp, "X-Request-Id").as_deref(), Some("abc"));
+    // The error's own headers win, but neither replaces the content type
+    assert_eq!(header(&resp, "Retry-After").as_deref(), Some("30"));
+    assert_eq!(header(&resp, "Content-Type").as_deref(), Some("application/json"));
+    let body = resp.body().concat2().wait().unwrap();
+    assert_eq!(serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+        json!({ "code": "unavailable", "msg": "Try later" }));
+}
+
+/// Sets a response header whose name has a multi-byte character where `Access-Control-` would end
+struct NonAsciiHeaderService;
+impl Echo for NonAsciiHeaderService {
+    fn echo(&self, i: bench::PTReq<Blob>) -> bench::PTRes<Blob> {
+        i.response_headers.set_raw("Access-Control\u{e9}", "1");
+        Box::new(future::ok(i.input.into()))
+    }
+
+    fn fail(&self, i: bench::PTReq<Blob>) -> bench::PTRes<Blob> { Box::new(future::ok(i.input.into())) }
+}
+
+#[test]
+fn non_ascii_response_header() {
+    let resp = <dyn Echo>::new_server(NonAsciiHeaderService).call(echo_request(ECHO_PATH)).wait().unwrap();
+    assert_eq!(resp.status(), StatusCode::Ok);
+    assert_eq!(header(&resp, "Access-Control\u{e9}").as_deref(), Some("1"));
+}
+
+/// Passes on the error of a downstream response with headers of its own
+struct ProxyService;
+impl Echo for ProxyService {
+    fn echo(&self, i: bench::PTReq<Blob>) -> bench::PTRes<Blob> {
+        let mut headers = hyper::Headers::new();
+        for &(name, value) in &[("Retry-After", "7"), ("Transfer-Encoding", "chunked"), ("Connection", "close"),
+                ("Set-Cookie", "session=downstream"), ("Date", "Thu, 01 Jan 1970 00:00:00 GMT"),
+                ("Server", "downstream"), ("Vary", "Accept"), ("Access-Control-Allow-Origin", "*"),
+                ("X-Request-Id", "downstream")] {
+            headers.set_raw(name, value);
+        }
+        let output = br#"{"code":"unavailable","msg":"Downstream is down"}"#.to_vec();
+        let resp = ServiceResponse { version: hyper::HttpVersion::Http11, headers, status: StatusCode::ServiceUnavailable,
+            output };
+        i.response_headers.set_raw("Keep-Alive", "timeout=5");
+        i.response_headers.set_raw("Access-Control-Allow-Credentials", "true");
+        Box::new(future::result(resp.to_proto::<Blob>()))
+    }
+
+    fn fail(&self, i: bench::PTReq<Blob>) -> bench::PTRes<Blob> { Box::new(future::ok(i.input.into())) }
+}
+
+#[test]
+fn downstream_error_headers() {
+    let resp = <dyn Echo>::new_server(ProxyService).call(echo_request(ECHO_PATH)).wait().unwrap();
+    assert_eq!(resp.status(), StatusCode::ServiceUnavailable);
+    // Only the downstream's Retry-After is passed on, and hop-by-hop and CORS headers are never set from handlers
+    assert_eq!(header(&resp, "Retry-After").as_deref(), Some("7"));
+    for name in &["Transfer-Encoding", "Connection", "Set-Cookie", "Date", "Server", "Vary", "Access-Control-Allow-Origin",
+            "X-Request-Id", "Keep-Alive", "Access-Control-Allow-Credentials"] {
+        assert_eq!(header(&resp, name), None, "{}", name);
+    }
+    let body = resp.body().concat2().wait().unwrap();
+    assert_eq!(serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+        json!({ "code": "unavailable", "msg": "Downstream is down" }));
+}
+
+/// Never answers `Echo`, so calls stay in flight until dropped
+struct StuckService;
+impl Echo for StuckService {
+    fn echo(&self, _: bench::PTReq<Blob>) -> bench::PTRes<Blob> { Box::new(future::empty()) }
+
+    fn fail(&self, i: bench::PTReq<Blob>) -> bench::PTRes<Blob> { Box::new(future::ok(i.input.into())) }
+}
+
+fn echo_request(path: &str) -> Request { proto_request(path, &blob(b"hi")) }
+
+#[test]
+fn method_descriptors() {
+    assert_eq!(<dyn Echo>::ECHO_METHOD.service, "prost_twirp.bench.Echo");
+    assert_eq!(<dyn Echo>::ECHO_METHOD.method, "Echo");
+    assert_eq!(<dyn Echo>::ECHO_METHOD.path, ECHO_PATH);
+    assert_eq!(<dyn Echo>::METHODS, &[<dyn Echo>::ECHO_METHOD, <dyn Echo>::FAIL_METHOD]);
+}
+
+#[test]
+fn max_in_flight() {
+    let mut server = HyperServer::new(EchoServer::new(StuckService));
+    server.max_in_flight = Some(2);
+    let first = server.call(echo_request(ECHO_PATH));
+    // Clones share the count like servers for separate connections
+    let _second = server.clone().call(echo_request(ECHO_PATH));
+    assert_eq!(server.in_flight.total(), 2);
+    assert_twirp_error(call(&server, echo_request(<dyn Echo>::FAIL_METHOD.path)), StatusCode::ServiceUnavailable,
+        json!({ "code": "unavailable", "msg": "too many calls in flight for /twirp/prost_twirp.bench.Echo/Fail" }));
+    assert_eq!(server.in_flight.rejected(), 1);
+    // Dropped and finished calls stop counting
+    drop(first);
+    assert_eq!(server.in_flight.total(), 1);
+    assert_eq!(call(&server, echo_request(<dyn Echo>::FAIL_METHOD.path)).0, StatusCode::Ok);
+    assert_eq!(server.in_flight.total(), 1);
+    assert_eq!(server.in_flight.method(ECHO_PATH), 1);
+    assert_eq!(server.in_flight.method(<dyn Echo>::FAIL_METHOD.path), 0);
+}
+
+#[test]
+fn method_max_in_flight() {
+    let mut server = HyperServer::new(EchoServer::new(StuckService));
+    server.method_max_in_flight.insert(<dyn Echo>::ECHO_METHOD.path.to_string(), 1);
+    let _first = server.call(echo_request(ECHO_PATH));
+    assert_eq!(call(&server, echo_request(ECHO_PATH)).0, StatusCode::ServiceUnavailable);
+    // Other methods aren't limited
+    assert_eq!(call(&server, echo_request(<dyn Echo>::FAIL_METHOD.path)).0, StatusCode::Ok);
+    assert_eq!(server.in_flight.rejected(), 1);
+}
+
+fn cors_server(cors: Cors) -> HyperServer<EchoServer<EchoService>> {
+    let mut server = HyperServer::new(EchoServer::new(EchoService));
+    server.cors = Some(cors);
+    server
+}
+
+fn preflight(origin: &str, method: &str, headers: Option<&str>) -> Request {
+    let mut req = Request::new(Method::Options, ECHO_PATH.parse().unwrap());
+    req.headers_mut().set_raw("Origin", origin.to_string());
+    req.headers_mut().set_raw("Access-Control-Request-Method", method.to_string());
+    if let Some(headers) = headers { req.headers_mut().set_raw("Access-Control-Request-Headers", headers.to_string()); }
+    req
+}
+
+fn with_origin(mut req: Request, origin: &str) -> Request {
+    req.headers_mut().set_raw("Origin", origin.to_string());
+    req
+}
+
+#[test]
+fn cors_preflight() {
+    let mut cors = Cors::new(&["https://a.example"]);
+    cors.allowed_headers = vec!["Authorization".to_string()];
+    cors.max_age = Some(Duration::from_secs(600));
+    let server = cors_server(cors);
+    let resp = server.call(preflight("https://a.example", "POST", Some("content-type, authorization"))).wait().unwrap();
+    assert_eq!(resp.status(), StatusCode::NoContent);
+    assert_eq!(header(&resp, "Access-Control-Allow-Origin").as_deref(), Some("https://a.example"));
+    assert_eq!(header(&resp, "Access-Control-Allow-Methods").as_deref(), Some("POST"));
+    assert_eq!(header(&resp, "Access-Control-Allow-Headers").as_deref(), Some("content-type, authorization"));
+    assert_eq!(header(&resp, "Access-Control-Max-Age").as_deref(), Some("600"));
+    assert_eq!(header(&resp, "Vary").as_deref(), Some("Origin"));
+    assert_eq!(header(&resp, "Access-Control-Allow-Credentials"), None);
+    // Anything not allowed is denied
+    let denied = |msg: &str| json!({ "code": "permission_denied", "msg": msg });
+    assert_twirp_error(call(&server, preflight("https://b.example", "POST", None)), StatusCode::Forbidden,
+        denied("origin \"https://b.example\" is not allowed"));
+    assert_twirp_error(call(&server, preflight("https://a.example", "PUT", None)), StatusCode::Forbidden,
+        denied("method \"PUT\" is not allowed (only POST is allowed)"));
+    assert_twirp_error(call(&server, preflight("https://a.example", "POST", Some("Content-Type, X-Other"))),
+        StatusCode::Forbidden, denied("header \"X-Other\" is not allowed"));
+    // Without CORS, preflights are bad routes like any other non-POST request
+    let resp = call(&*echo_server(), preflight("https://a.example", "POST", None));
+    assert_eq!(resp.0, StatusCode::NotFound);
+}
+
+#[test]
+fn cors_responses() {
+    let mut cors = Cors::new(&["*"]);
+    cors.exposed_headers = vec!["Retry-After".to_string(), "WWW-Authenticate".to_string()];
+    let server = cors_server(cors.clone());
+    let resp = server.call(with_origin(echo_request(ECHO_PATH), "https://a.example")).wait().unwrap();
+    assert_eq!(resp.status(), StatusCode::Ok);
+    assert_eq!(header(&resp, "Access-Control-Allow-Origin").as_deref(), Some("*"));
+    assert_eq!(header(&resp, "Access-Control-Expose-Headers").as_deref(), Some("Retry-After, WWW-Authenticate"));
+    assert_eq!(header(&resp, "Vary"), None);
+    // Errors, including those before the service is called, get the headers too
+    for req in [echo_request("/twirp/prost_twirp.bench.Echo/Fail"), echo_request("/nope"),
+            request(Method::Post, ECHO_PATH, Some("text/plain"), Vec::new())] {
+        let resp = server.call(with_origin(req, "https://a.example")).wait().unwrap();
+        assert!(resp.status().is_client_error(), "{}", resp.status());
+        assert_eq!(header(&resp, "Access-Control-Allow-Origin").as_deref(), Some("*"));
+    }
+    // With credentials, listed origins are sent back instead of `*`
+    cors.allowed_origins.push("https://a.example".to_string());
+    cors.allow_credentials = true;
+    let server = cors_server(cors);
+    let resp = server.call(with_origin(echo_request(ECHO_PATH), "https://a.example")).wait().unwrap();
+    assert_eq!(header(&resp, "Access-Control-Allow-Origin").as_deref(), Some("https://a.example"));
+    assert_eq!(header(&resp, "Access-Control-Allow-Credentials").as_deref(), Some("true"));
+    assert_eq!(header(&resp, "Vary").as_deref(), Some("Origin"));
+    // But origins only allowed by `*` never get credentials
+    let resp = server.call(with_origin(echo_request(ECHO_PATH), "https://b.example")).wait().unwrap();
+    assert_eq!(header(&resp, "Access-Control-Allow-Origin").as_deref(), Some("*"));
+    assert_eq!(header(&resp, "Access-Control-Allow-Credentials"), None);
+    let resp = server.call(preflight("https://b.example", "POST", None)).wait().unwrap();
+    assert_eq!(resp.status(), StatusCode::NoContent);
+    assert_eq!(header(&resp, "Access-Control-Allow-Origin").as_deref(), Some("*"));
+    assert_eq!(header(&resp, "Access-Control-Allow-Credentials"), None);
+    // Calls without an origin or from others aren't changed
+    let server = cors_server(Cors::new(&["https://a.example"]));
+    for req in [echo_request(ECHO_PATH), with_origin(echo_request(ECHO_PATH), "https://b.example")] {
+        let resp = server.call(req).wait().unwrap();
+        assert_eq!(resp.status(), StatusCode::Ok);
+        assert_eq!(header(&resp, "Access-Control-Allow-Origin"), None);
+    }
+}
@@ -0,0 +1,185 @@
+//! Tests for W3C trace context propagation through clients and servers.
+
+extern crate hyper;
+extern crate prost_twirp;
+extern crate prost_twirp_testing;
+extern crate tokio_core;
+
+use hyper::server::Service;
+use hyper::{Client, Headers, StatusCode};
+use prost_twirp::{Authenticated, HyperServer, Principal, ServiceRequest, TraceContext, TwirpError, Verifier};
+use prost_twirp_testing::{proto_request, EchoService, LoopbackServer};
+use prost_twirp_testing::bench::{self, Blob, Echo, EchoClient, EchoServer};
+use std::sync::{Arc, Mutex};
+use tokio_core::reactor::Core;
+
+const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+/// What a server saw of a call's trace
+#[derive(Clone, Debug, PartialEq)]
+struct Seen {
+    traceparent: Option<String>,
+    tracestate: Option<String>,
+    context: Option<TraceContext>,
+}
+
+/// Records what each call had of a trace, letting every caller in
+#[derive(Clone, Default)]
+struct RecordTraces(Arc<Mutex<Vec<Seen>>>);
+
+impl RecordTraces {
+    fn seen(&self) -> Vec<Seen> { self.0.lock().unwrap().drain(..).collect() }
+}
+
+impl Verifier for RecordTraces {
+    fn verify(&self, req: &ServiceRequest<Vec<u8>>) -> Option<Result<Principal, TwirpError>> {
+        let header = |name| req.headers.get_raw(name).and_then(|v| v.one()).
+            map(|v| String::from_utf8_lossy(v).into_owned());
+        self.0.lock().unwrap().push(Seen { traceparent: header("traceparent"), tracestate: header("tracestate"),
+            context: req.trace_context.clone() });
+        Some(Ok(Default::default()))
+    }
+}
+
+fn recording_server(traces: &RecordTraces) -> LoopbackServer {
+    let traces = traces.clone();
+    LoopbackServer::start(move || {
+        HyperServer::new(Authenticated::new(EchoServer::new(EchoService), vec![Box::new(traces.clone())]))
+    })
+}
+
+fn blob() -> Blob { Blob { data: b"hello".to_vec() } }
+
+#[test]
+fn parse_and_format() {
+    let context = TraceContext::parse(TRACEPARENT, Some(" rojo=00f067aa0ba902b7,congo=t61rcWkgMzE ")).unwrap();
+    assert_eq!(context, TraceContext { trace_id: 0x4bf92f3577b34da6a3ce929d0e0e4736, span_id: 0x00f067aa0ba902b7,
+        flags: TraceContext::SAMPLED, state: Some("rojo=00f067aa0ba902b7,congo=t61rcWkgMzE".to_string()) });
+    assert_eq!(context.traceparent(), TRACEPARENT);
+    assert!(context.sampled());
+    let unsampled = TraceContext::parse("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00", Some("")).unwrap();
+    assert!(!unsampled.sampled());
+    assert_eq!(unsampled.state, None);
+    // Later versions may add fields
+    let later = TraceContext::parse("cc-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-what", None).unwrap();
+    assert_eq!(later.traceparent(), TRACEPARENT);
+    for invalid in &[
+        "", "00", "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+        "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+        "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+        "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+        "00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+        "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-what",
+        "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        "00-+bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+    ] {
+        assert_eq!(TraceContext::parse(invalid, None), None, "{:?}", invalid);
+    }
+
+    let mut headers = Headers::new();
+    headers.set_raw("traceparent", TRACEPARENT);
+    headers.set_raw("tracestate", vec![b"rojo=1".to_vec(), b"congo=2".to_vec()]);
+    let context = TraceContext::from_headers(&headers).unwrap();
+    assert_eq!(context.state.as_deref(), Some("rojo=1,congo=2"));
+    let child = context.child();
+    assert_eq!((child.trace_id, child.flags, &child.state), (context.trace_id, context.flags, &context.state));
+    assert_ne!(child.span_id, context.span_id);
+    let mut headers = Headers::new();
+    child.set_headers(&mut headers);
+    assert_eq!(TraceContext::from_headers(&headers), Some(child));
+    headers.set_raw("traceparent", vec![TRACEPARENT.as_bytes().to_vec(), TRACEPARENT.as_bytes().to_vec()]);
+    assert_eq!(TraceContext::from_headers(&headers), None);
+
+    let started = TraceContext::start();
+    assert!(started.trace_id != 0 && started.span_id != 0 && started.sampled());
+    assert_ne!(TraceContext::start().trace_id, started.trace_id);
+}
+
+#[test]
+fn clients_inject() {
+    let traces = RecordTraces::default();
+    let server = recording_server(&traces);
+    let mut core = Core::new().unwrap();
+    let client = EchoClient::new(Client::new(&core.handle()), &server.root_url());
+    // Nothing is sent without a context
+    core.run(client.call_echo(blob())).unwrap();
+    assert_eq!(traces.seen(), vec![Seen { traceparent: None, tracestate: None, context: None }]);
+
+    // The current context is carried on with a new span for the call
+    let context = TraceContext::parse(TRACEPARENT, Some("rojo=1")).unwrap();
+    core.run(context.scope(client.call_echo(blob()))).unwrap();
+    assert_eq!(TraceContext::current(), None);
+    let seen = traces.seen().remove(0);
+    let sent = TraceContext::parse(seen.traceparent.as_ref().unwrap(), None).unwrap();
+    assert_eq!((sent.trace_id, sent.flags), (context.trace_id, context.flags));
+    assert_ne!(sent.span_id, context.span_id);
+    assert_eq!(seen.tracestate.as_deref(), Some("rojo=1"));
+    // Servers give the call its own span
+    let server_context = seen.context.unwrap();
+    assert_eq!((server_context.trace_id, &server_context.state), (context.trace_id, &context.state));
+    assert!(server_context.span_id != sent.span_id && server_context.span_id != context.span_id);
+
+    // The request's context is used over the current one
+    let other = TraceContext::start();
+    let mut req = ServiceRequest::new(blob());
+    req.trace_context = Some(other.clone());
+    core.run(context.scope(Echo::echo(&client, req))).unwrap();
+    let seen = traces.seen().remove(0);
+    assert_eq!(seen.context.unwrap().trace_id, other.trace_id);
+    assert_eq!(seen.tracestate, None);
+
+    // Headers already set are sent as they are
+    core.run(context.scope(client.call_echo(blob()).header_raw("traceparent", TRACEPARENT))).unwrap();
+    assert_eq!(traces.seen().remove(0).traceparent.as_deref(), Some(TRACEPARENT));
+}
+
+/// Echoes by calling another echo server, recording the context it was called with
+struct Forward {
+    client: EchoClient,
+    contexts: Arc<Mutex<Vec<Option<TraceContext>>>>,
+}
+
+impl Echo for Forward {
+    fn echo(&self, i: bench::PTReq<Blob>) -> bench::PTRes<Blob> {
+        self.contexts.lock().unwrap().push(i.trace_context.clone());
+        // The call is only sent when polled, after the handler returns
+        Box::new(self.client.call_echo(i.input))
+    }
+
+    fn fail(&self, i: bench::PTReq<Blob>) -> bench::PTRes<Blob> { Box::new(self.client.call_fail(i.input)) }
+}
+
+#[test]
+fn handlers_carry_it_forward() {
+    let traces = RecordTraces::default();
+    let downstream = recording_server(&traces);
+    let mut core = Core::new().unwrap();
+    let contexts = Arc::new(Mutex::new(Vec::new()));
+    let client = EchoClient::new(Client::new(&core.handle()), &downstream.root_url());
+    let server = HyperServer::new(EchoServer::new(Forward { client, contexts: contexts.clone() }));
+    let call = |traceparent: Option<&str>| {
+        let mut req = proto_request(<dyn Echo>::ECHO_METHOD.path, &blob());
+        if let Some(traceparent) = traceparent {
+            req.headers_mut().set_raw("traceparent", traceparent);
+            req.headers_mut().set_raw("tracestate", "rojo=1");
+        }
+        server.call(req)
+    };
+
+    assert_eq!(core.run(call(Some(TRACEPARENT))).unwrap().status(), StatusCode::Ok);
+    let context = contexts.lock().unwrap().remove(0).unwrap();
+    assert_eq!(context.trace_id, 0x4bf92f3577b34da6a3ce929d0e0e4736);
+    let seen = traces.seen().remove(0);
+    let sent = TraceContext::parse(seen.traceparent.as_ref().unwrap(), None).unwrap();
+    assert_eq!(sent.trace_id, context.trace_id);
+    assert_ne!(sent.span_id, context.span_id);
+    assert_eq!(seen.tracestate.as_deref(), Some("rojo=1"));
+    assert_eq!(TraceContext::current(), None);
+
+    // Invalid or missing contexts aren't carried
+    for traceparent in &[None, Some("00-bad")] {
+        assert_eq!(core.run(call(*traceparent)).unwrap().status(), StatusCode::Ok);
+        assert_eq!(contexts.lock().unwrap().remove(0), None);
+        assert_eq!(traces.seen().remove(0).traceparent, None);
+    }
+}
@@ -0,0 +1,185 @@
+//! Tests for the `tracing` spans around client and server calls.
+
+extern crate futures;
+extern crate hyper;
+extern crate prost_twirp;
+extern crate prost_twirp_testing;
+extern crate tokio_core;
+extern crate tracing;
+extern crate tracing_core;
+
+use futures::{future, Future};
+use hyper::Client;
+use prost_twirp::{Authenticated, HyperClient, HyperServer, Principal, ServiceRequest, TwirpError, Verifier};
+use prost_twirp_testing::{EchoService, LoopbackServer};
+use prost_twirp_testing::bench::{Blob, Echo, EchoClient, EchoServer};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{Mutex, Once};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio_core::reactor::Core;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+use tracing_core::span::Current;
+
+/// A recorded span or event
+#[derive(Clone, Debug, Default)]
+struct Recorded {
+    name: String,
+    metadata: Option<&'static Metadata<'static>>,
+    parent: Option<u64>,
+    fields: BTreeMap<String, String>,
+}
+
+impl Visit for Recorded {
+    fn record_str(&mut self, field: &Field, value: &str) { self.fields.insert(field.name().to_string(), value.into()); }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.fields.insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+static SPANS: Mutex<Option<HashMap<u64, Recorded>>> = Mutex::new(None);
+static EVENTS: Mutex<Vec<Recorded>> = Mutex::new(Vec::new());
+
+thread_local!(static ENTERED: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) });
+
+/// Records every span and event with its contextual parent
+struct Recorder {
+    next_id: AtomicU64,
+}
+
+impl Recorder {
+    fn parent(explicit: Option<&Id>, contextual: bool) -> Option<u64> {
+        if contextual { ENTERED.with(|v| v.borrow().last().cloned()) } else { explicit.map(|v| v.into_u64()) }
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata) -> bool { true }
+
+    fn new_span(&self, attrs: &Attributes) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut span = Recorded { name: attrs.metadata().name().to_string(), metadata: Some(attrs.metadata()),
+            parent: Recorder::parent(attrs.parent(), attrs.is_contextual()), ..Default::default() };
+        attrs.record(&mut span);
+        SPANS.lock().unwrap().get_or_insert_with(HashMap::new).insert(id, span);
+        Id::from_u64(id)
+    }
+
+    fn record(&self, id: &Id, values: &Record) {
+        if let Some(span) = SPANS.lock().unwrap().get_or_insert_with(HashMap::new).get_mut(&id.into_u64()) {
+            values.record(span);
+        }
+    }
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn current_span(&self) -> Current {
+        let id = match ENTERED.with(|v| v.borrow().last().cloned()) {
+            Some(id) => id,
+            None => return Current::none(),
+        };
+        let metadata = SPANS.lock().unwrap().get_or_insert_with(HashMap::new).get(&id).and_then(|v| v.metadata);
+        metadata.map_or_else(Current::none, |metadata| Current::new(Id::from_u64(id), metadata))
+    }
+
+    fn event(&self, event: &Event) {
+        let mut recorded = Recorded { name: event.metadata().name().to_string(),
+            parent: Recorder::parent(event.parent(), event.is_contextual()), ..Default::default() };
+        event.record(&mut recorded);
+        EVENTS.lock().unwrap().push(recorded);
+    }
+
+    fn enter(&self, id: &Id) { ENTERED.with(|v| v.borrow_mut().push(id.into_u64())); }
+
+    fn exit(&self, id: &Id) {
+        ENTERED.with(|v| {
+            let mut entered = v.borrow_mut();
+            if let Some(i) = entered.iter().rposition(|&v| v == id.into_u64()) { entered.remove(i); }
+        });
+    }
+}
+
+fn record() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| tracing::subscriber::set_global_default(Recorder { next_id: AtomicU64::new(1) }).unwrap());
+}
+
+/// The recorded spans with the name, by ID
+fn spans(name: &str) -> Vec<(u64, Recorded)> {
+    let mut spans: Vec<_> = SPANS.lock().unwrap().get_or_insert_with(HashMap::new).iter().
+        filter(|&(_, v)| v.name == name).map(|(&id, v)| (id, v.clone())).collect();
+    spans.sort_by_key(|v| v.0);
+    spans
+}
+
+fn field<'a>(span: &'a Recorded, name: &str) -> Option<&'a str> { span.fields.get(name).map(|v| v.as_str()) }
+
+/// Logs an event for each call, letting every caller in
+struct LogCalls;
+
+impl Verifier for LogCalls {
+    fn verify(&self, _: &ServiceRequest<Vec<u8>>) -> Option<Result<Principal, TwirpError>> {
+        tracing::info!(handling = true);
+        Some(Ok(Default::default()))
+    }
+}
+
+#[test]
+fn call_spans() {
+    record();
+    let server = LoopbackServer::start(|| {
+        HyperServer::new(Authenticated::new(EchoServer::new(EchoService), vec![Box::new(LogCalls)]))
+    });
+    let mut core = Core::new().unwrap();
+    let client = EchoClient::new(Client::new(&core.handle()), &server.root_url());
+    let outer = tracing::info_span!("outer");
+    let mut call = client.call_echo(Blob { data: b"hello".to_vec() });
+    core.run(future::poll_fn(|| outer.in_scope(|| call.poll()))).unwrap();
+    assert!(core.run(client.call_fail(Blob { data: b"hi".to_vec() })).is_err());
+
+    let client_spans = spans("twirp_client");
+    assert_eq!(client_spans.len(), 2);
+    let (_, ref echo) = client_spans[0];
+    assert_eq!(echo.parent, spans("outer").first().map(|v| v.0));
+    assert_eq!(field(echo, "service"), Some("prost_twirp.bench.Echo"));
+    assert_eq!(field(echo, "method"), Some("Echo"));
+    assert_eq!(field(echo, "status"), Some("200"));
+    assert_eq!(field(echo, "error_code"), None);
+    assert_eq!(field(echo, "request_size"), Some("7"));
+    assert_eq!(field(echo, "response_size"), Some("7"));
+    let (_, ref fail) = client_spans[1];
+    assert_eq!(field(fail, "method"), Some("Fail"));
+    assert_eq!(field(fail, "status"), Some("400"));
+    assert_eq!(field(fail, "error_code"), Some("invalid_argument"));
+    assert_eq!(field(fail, "request_size"), Some("4"));
+
+    // The server spans are finished by the time the responses are read
+    let server_spans = spans("twirp_server");
+    assert_eq!(server_spans.len(), 2);
+    let (echo_id, ref echo) = server_spans[0];
+    assert_eq!(echo.parent, None);
+    assert_eq!(field(echo, "method"), Some("Echo"));
+    assert_eq!(field(echo, "status"), Some("200"));
+    assert_eq!(field(echo, "request_size"), Some("7"));
+    assert_eq!(field(echo, "response_size"), Some("7"));
+    let (fail_id, ref fail) = server_spans[1];
+    assert_eq!(field(fail, "status"), Some("400"));
+    assert_eq!(field(fail, "error_code"), Some("invalid_argument"));
+    // Handlers run in the server span
+    let handled: Vec<_> = EVENTS.lock().unwrap().iter().filter(|v| v.fields.contains_key("handling")).
+        map(|v| v.parent).collect();
+    assert_eq!(handled, vec![Some(echo_id), Some(fail_id)]);
+
+    // Transport failures have a code but no status
+    drop(server);
+    let client = HyperClient::new(Client::new(&core.handle()), "http://127.0.0.1:9");
+    assert!(core.run(client.go::<Blob, Blob>(<dyn Echo>::ECHO_METHOD.path, Blob::default().into())).is_err());
+    let client_spans = spans("twirp_client");
+    let (_, ref refused) = client_spans[2];
+    assert_eq!(field(refused, "error_code"), Some("unavailable"));
+    assert_eq!(field(refused, "status"), None);
+}